@@ -0,0 +1,184 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::exit::{CONFLICT, Failure, NOT_FOUND, fail};
+use crate::paths::alternatives_dir;
+use crate::term::{dim, green, yellow};
+use crate::types::Alternative;
+use crate::utils::installed_packages;
+use crate::{ROOT, say};
+
+pub struct Candidate {
+    pub package: String,
+    pub target: PathBuf,
+    pub priority: i32,
+}
+
+fn installed_path(config: &Config, rel: &str) -> PathBuf {
+    let (top, rest) = rel.split_once('/').unwrap_or((rel, ""));
+    let dest = config
+        .install_dirs()
+        .into_iter()
+        .find(|(dir, _)| *dir == top)
+        .map(|(_, dest)| format!("{}/{}", dest, rest))
+        .unwrap_or_else(|| format!("{}/{}", config.prefix, rel));
+    Path::new(ROOT).join(dest.trim_start_matches('/'))
+}
+
+fn link_path(config: &Config, name: &str) -> PathBuf {
+    installed_path(config, &format!("bin/{}", name))
+}
+
+fn state_path(name: &str) -> PathBuf {
+    Path::new(&alternatives_dir()).join(name)
+}
+
+pub fn chosen(name: &str) -> Option<String> {
+    let state = fs::read_to_string(state_path(name)).ok()?;
+    state.trim().strip_prefix("manual ").map(|pkg| pkg.trim().to_string())
+}
+
+fn offered() -> Vec<(String, Alternative)> {
+    installed_packages()
+        .into_iter()
+        .flat_map(|pkg| pkg.alternatives.into_iter().map(move |alt| (pkg.name.clone(), alt)))
+        .collect()
+}
+
+pub fn names() -> Vec<String> {
+    let mut names: BTreeSet<String> = offered().into_iter().map(|(_, alt)| alt.name).collect();
+    if let Ok(entries) = fs::read_dir(alternatives_dir()) {
+        names.extend(entries.flatten().map(|entry| entry.file_name().to_string_lossy().to_string()));
+    }
+    names.into_iter().collect()
+}
+
+pub fn candidates(name: &str) -> Vec<Candidate> {
+    let config = Config::load();
+    let mut candidates: Vec<Candidate> = offered()
+        .into_iter()
+        .filter(|(_, alt)| alt.name == name)
+        .map(|(package, alt)| Candidate { package, target: installed_path(&config, &alt.path), priority: alt.priority })
+        .filter(|candidate| fs::symlink_metadata(&candidate.target).is_ok())
+        .collect();
+    candidates.sort_by(|a, b| b.priority.cmp(&a.priority).then(a.package.cmp(&b.package)));
+    candidates
+}
+
+pub fn current(name: &str) -> Option<PathBuf> {
+    fs::read_link(link_path(&Config::load(), name)).ok()
+}
+
+fn write_state(name: &str, state: &str) -> Result<(), String> {
+    fs::create_dir_all(alternatives_dir()).map_err(|e| format!("couldn't create {}: {}", alternatives_dir(), e))?;
+    fs::write(state_path(name), format!("{}\n", state)).map_err(|e| format!("couldn't save alternative {}: {}", name, e))
+}
+
+fn point(link: &Path, target: &Path) -> Result<(), String> {
+    if let Some(parent) = link.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("couldn't create {}: {}", parent.display(), e))?;
+    }
+    let temp = link.with_file_name(format!(".{}.pls-alt", link.file_name().unwrap_or_default().to_string_lossy()));
+    let _ = fs::remove_file(&temp);
+    symlink(target, &temp)
+        .and_then(|_| fs::rename(&temp, link))
+        .map_err(|e| format!("couldn't point {} at {}: {}", link.display(), target.display(), e))
+}
+
+pub fn refresh(name: &str) -> Result<(), Failure> {
+    let config = Config::load();
+    let link = link_path(&config, name);
+    let candidates = candidates(name);
+    let owned = fs::symlink_metadata(&link).map(|meta| meta.file_type().is_symlink());
+
+    let Some(pick) = chosen(name)
+        .and_then(|pkg| candidates.iter().find(|c| c.package == pkg))
+        .or(candidates.first())
+    else {
+        if owned.unwrap_or(false) {
+            let _ = fs::remove_file(&link);
+        }
+        let _ = fs::remove_file(state_path(name));
+        return Ok(());
+    };
+
+    if owned.is_ok_and(|symlink| !symlink) {
+        return Err(fail(CONFLICT, format!("{} is a real file, not touching it for the {} alternative", link.display(), name)));
+    }
+    if chosen(name).is_some_and(|pkg| pkg != pick.package) || !state_path(name).exists() {
+        write_state(name, "auto")?;
+    }
+    if fs::read_link(&link).is_ok_and(|target| target == pick.target) {
+        return Ok(());
+    }
+    point(&link, &pick.target)?;
+    say!("{} is {} now ({})", name, pick.target.display(), pick.package);
+    Ok(())
+}
+
+pub fn refresh_all() {
+    for name in names() {
+        if let Err(e) = refresh(&name) {
+            say!("{} {}", yellow("heads up,"), e);
+        }
+    }
+}
+
+pub fn set(name: &str, package: &str) -> Result<(), Failure> {
+    let candidates = candidates(name);
+    if candidates.is_empty() {
+        return Err(fail(NOT_FOUND, format!("nothing installed offers {}", name)));
+    }
+    if !candidates.iter().any(|c| c.package == package) {
+        let offered: Vec<&str> = candidates.iter().map(|c| c.package.as_str()).collect();
+        return Err(fail(NOT_FOUND, format!("{} doesn't offer {}, pick one of {}", package, name, offered.join(", "))));
+    }
+    write_state(name, &format!("manual {}", package))?;
+    refresh(name)?;
+    say!("{} {} sticks with {} until you say pls alternatives auto {}", green("got ya!"), name, package, name);
+    Ok(())
+}
+
+pub fn auto(name: &str) -> Result<(), Failure> {
+    if candidates(name).is_empty() {
+        return Err(fail(NOT_FOUND, format!("nothing installed offers {}", name)));
+    }
+    write_state(name, "auto")?;
+    refresh(name)?;
+    say!("{} {} follows priorities again", green("got ya!"), name);
+    Ok(())
+}
+
+pub fn show(only: Option<&str>) -> Result<(), Failure> {
+    let names: Vec<String> = match only {
+        Some(name) => vec![name.to_string()],
+        None => names(),
+    };
+    if names.is_empty() {
+        say!("no alternatives around, nothing installed offers any");
+        return Ok(());
+    }
+    for name in names {
+        let candidates = candidates(&name);
+        if candidates.is_empty() {
+            return Err(fail(NOT_FOUND, format!("nothing installed offers {}", name)));
+        }
+        let current = current(&name);
+        let mode = match chosen(&name) {
+            Some(_) => "manual",
+            None => "auto",
+        };
+        println!("{} {}", name, dim(format!("({})", mode)));
+        for candidate in candidates {
+            let line = format!("{} {} {}", candidate.package, candidate.target.display(), dim(format!("priority {}", candidate.priority)));
+            match current.as_ref() == Some(&candidate.target) {
+                true => println!("  {} {}", green("*"), line),
+                false => println!("    {}", line),
+            }
+        }
+    }
+    Ok(())
+}
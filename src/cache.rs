@@ -0,0 +1,235 @@
+use std::fs::{self, File, TryLockError};
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::process;
+use std::time::SystemTime;
+
+use crate::config::Config;
+use crate::exit::{Failure, io_fail};
+use crate::network::SIGNATURES;
+use crate::paths::cache_dir;
+use crate::types::RepoIndex;
+use crate::utils::{compare_versions, format_size, installed_packages, read_package_info};
+use crate::say;
+
+pub struct CachedPackage {
+    pub path: String,
+    pub name: String,
+    pub version: String,
+    pub size: u64,
+    pub last_used: SystemTime,
+}
+
+pub fn cache_path(name: &str, version: &str) -> String {
+    format!("{}/{}-{}.pls", cache_dir(), name, version)
+}
+
+pub fn touch(path: &str) {
+    if let Ok(file) = File::options().write(true).open(path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
+
+pub struct CacheLock {
+    file: File,
+    path: String,
+}
+
+impl Drop for CacheLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+        let _ = self.file.unlock();
+    }
+}
+
+fn open_lock(path: &str) -> io::Result<File> {
+    fs::create_dir_all(cache_dir())?;
+    File::options().create(true).write(true).truncate(false).open(path)
+}
+
+pub fn lock_blocking(path: &str) -> Result<CacheLock, Failure> {
+    let lock_path = format!("{}.lock", path);
+    let mut waited = false;
+    loop {
+        let file = open_lock(&lock_path).map_err(|e| io_fail(&e, format!("couldn't lock {}: {}", path, e)))?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                if !waited {
+                    say!("waiting for another pls to finish with {}...", path.rsplit('/').next().unwrap_or(path));
+                    waited = true;
+                }
+                file.lock().map_err(|e| io_fail(&e, format!("couldn't lock {}: {}", path, e)))?;
+            }
+            Err(TryLockError::Error(e)) => return Err(io_fail(&e, format!("couldn't lock {}: {}", path, e))),
+        }
+        // whoever held it before removed the file on the way out, so lock the one that's there now
+        let current = match (file.metadata(), fs::metadata(&lock_path)) {
+            (Ok(held), Ok(on_disk)) => held.dev() == on_disk.dev() && held.ino() == on_disk.ino(),
+            _ => false,
+        };
+        if current {
+            return Ok(CacheLock { file, path: lock_path });
+        }
+    }
+}
+
+pub async fn lock(path: &str) -> Result<CacheLock, Failure> {
+    let path = path.to_string();
+    tokio::task::spawn_blocking(move || lock_blocking(&path)).await.map_err(|e| Failure::from(e.to_string()))?
+}
+
+pub fn write_atomic(path: &str, bytes: &[u8]) -> io::Result<()> {
+    let temp = format!("{}.{}.tmp", path, process::id());
+    fs::write(&temp, bytes)?;
+    fs::rename(&temp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temp);
+    })
+}
+
+pub fn remove_cached(path: &str) -> io::Result<()> {
+    let lock_path = format!("{}.lock", path);
+    let held = File::options().write(true).open(&lock_path).ok();
+    if let Some(lock) = &held
+        && let Err(TryLockError::WouldBlock) = lock.try_lock()
+    {
+        return Err(io::Error::other(format!("{} is in use by another pls", path)));
+    }
+    for kind in SIGNATURES {
+        let _ = fs::remove_file(format!("{}.{}", path, kind));
+    }
+    fs::remove_file(path)?;
+    if held.is_some() {
+        let _ = fs::remove_file(&lock_path);
+    }
+    Ok(())
+}
+
+fn names_path() -> String {
+    format!("{}/names", cache_dir())
+}
+
+pub fn remember_names<'a>(names: impl Iterator<Item = &'a String>) {
+    let mut names: Vec<&String> = names.collect();
+    names.sort();
+    let content: String = names.iter().map(|name| format!("{}\n", name)).collect();
+    let _ = fs::create_dir_all(cache_dir());
+    let _ = write_atomic(&names_path(), content.as_bytes());
+}
+
+pub fn known_names() -> Vec<String> {
+    fs::read_to_string(names_path())
+        .map(|content| content.lines().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+pub fn scan() -> Vec<CachedPackage> {
+    let Ok(entries) = fs::read_dir(cache_dir()) else {
+        return Vec::new();
+    };
+
+    let mut cached = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("pls") {
+            continue;
+        }
+        let path = path.to_string_lossy().to_string();
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        let Ok(info) = read_package_info(&path) else {
+            continue;
+        };
+        cached.push(CachedPackage {
+            path,
+            name: info.name,
+            version: info.version,
+            size: meta.len(),
+            last_used: meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        });
+    }
+    cached
+}
+
+pub fn evict_to_limit(max_size: u64) -> Vec<CachedPackage> {
+    let mut cached = scan();
+    let mut total: u64 = cached.iter().map(|c| c.size).sum();
+    if total <= max_size {
+        return Vec::new();
+    }
+
+    let installed = installed_packages();
+    cached.retain(|c| !installed.iter().any(|i| i.name == c.name && i.version == c.version));
+    cached.sort_by_key(|c| c.last_used);
+
+    let mut evicted = Vec::new();
+    for pkg in cached {
+        if total <= max_size {
+            break;
+        }
+        if remove_cached(&pkg.path).is_ok() {
+            total -= pkg.size;
+            evicted.push(pkg);
+        }
+    }
+    evicted
+}
+
+fn retained(cached: &[CachedPackage], keep: usize) -> Vec<String> {
+    let mut paths = Vec::new();
+    for pkg in installed_packages() {
+        let mut versions: Vec<&CachedPackage> = cached.iter().filter(|c| c.name == pkg.name).collect();
+        versions.sort_by(|a, b| compare_versions(&b.version, &a.version));
+        for (i, c) in versions.iter().enumerate() {
+            if i < keep || c.version == pkg.version {
+                paths.push(c.path.clone());
+            }
+        }
+    }
+    paths
+}
+
+pub fn apply_retention(keep: usize) -> Vec<CachedPackage> {
+    let cached = scan();
+    let keep_paths = retained(&cached, keep);
+    let installed = installed_packages();
+    cached
+        .into_iter()
+        .filter(|c| installed.iter().any(|i| i.name == c.name))
+        .filter(|c| !keep_paths.contains(&c.path))
+        .filter(|c| remove_cached(&c.path).is_ok())
+        .collect()
+}
+
+pub fn remove_unused(index: &RepoIndex, keep_versions: Option<usize>) -> Vec<CachedPackage> {
+    let installed = installed_packages();
+    let cached = scan();
+    let keep_paths = keep_versions.map(|keep| retained(&cached, keep)).unwrap_or_default();
+    cached
+        .into_iter()
+        .filter(|c| !installed.iter().any(|i| i.name == c.name && i.version == c.version))
+        .filter(|c| !keep_paths.contains(&c.path))
+        .filter(|c| index.packages.get(&c.name).is_none_or(|meta| meta.version != c.version))
+        .filter(|c| remove_cached(&c.path).is_ok())
+        .collect()
+}
+
+pub fn auto_clean(config: &Config) {
+    if let Some(keep) = config.keep_versions {
+        let pruned = apply_retention(keep);
+        if !pruned.is_empty() {
+            let freed: u64 = pruned.iter().map(|c| c.size).sum();
+            say!("dropped {} old cached version(s) past keep_versions ({})", pruned.len(), format_size(freed));
+        }
+    }
+
+    let Some(max_size) = config.cache_max_size else {
+        return;
+    };
+    let evicted = evict_to_limit(max_size);
+    if !evicted.is_empty() {
+        let freed: u64 = evicted.iter().map(|c| c.size).sum();
+        say!("cache over limit, evicted {} old package(s) ({})", evicted.len(), format_size(freed));
+    }
+}
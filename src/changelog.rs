@@ -0,0 +1,97 @@
+use std::fs;
+use std::process::Command;
+
+const CHANGELOG_FILES: [&str; 5] = ["CHANGELOG.md", "CHANGELOG", "CHANGELOG.txt", "CHANGES.md", "CHANGES"];
+const MAX_TAGS: usize = 20;
+
+pub fn git(project_path: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(project_path)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn from_git_tags(project_path: &str) -> Option<String> {
+    let tags = git(
+        project_path,
+        &["tag", "--sort=-version:refname", "--format=%(refname:short) %(creatordate:short)"],
+    )?;
+    let tags: Vec<(&str, &str)> = tags
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .take(MAX_TAGS + 1)
+        .collect();
+    if tags.is_empty() {
+        return None;
+    }
+
+    let mut out = String::from("# Changelog\n");
+    for (i, (tag, date)) in tags.iter().take(MAX_TAGS).enumerate() {
+        let range = match tags.get(i + 1) {
+            Some((prev, _)) => format!("{}..{}", prev, tag),
+            None => tag.to_string(),
+        };
+        let subjects = git(project_path, &["log", "--no-merges", "--format=- %s", &range]).unwrap_or_default();
+
+        out.push_str(&format!("\n## {} ({})\n", tag, date));
+        out.push_str(&subjects);
+    }
+    Some(out)
+}
+
+fn since_commit(project_path: &str, version: &str, commit: &str, previous: &str) -> Option<String> {
+    git(project_path, &["merge-base", "--is-ancestor", commit, "HEAD"])?;
+    let subjects = git(project_path, &["log", "--no-merges", "--format=- %s", &format!("{}..HEAD", commit)])?;
+    if subjects.trim().is_empty() {
+        return None;
+    }
+
+    let today = chrono::Local::now().format("%Y-%m-%d");
+    let mut out = format!("# Changelog\n\n## {} ({})\n{}", version, today, subjects);
+    let older = previous.trim_start().strip_prefix("# Changelog").unwrap_or(previous).trim();
+    if !older.is_empty() {
+        out.push('\n');
+        out.push_str(older);
+        out.push('\n');
+    }
+    Some(out)
+}
+
+pub fn find_or_generate(project_path: &str, version: &str, previous: Option<(&str, &str)>) -> Option<String> {
+    CHANGELOG_FILES
+        .iter()
+        .find_map(|name| fs::read_to_string(format!("{}/{}", project_path, name)).ok())
+        .or_else(|| previous.and_then(|(commit, changelog)| since_commit(project_path, version, commit, changelog)))
+        .or_else(|| from_git_tags(project_path))
+}
+
+pub fn latest_entries(content: &str, count: usize) -> String {
+    let heading = if content.lines().any(|l| l.starts_with("## ")) { "## " } else { "# " };
+
+    let mut entries: Vec<Vec<&str>> = Vec::new();
+    for line in content.lines() {
+        if line.starts_with(heading) {
+            if entries.len() == count {
+                break;
+            }
+            entries.push(vec![line]);
+        } else if let Some(entry) = entries.last_mut() {
+            entry.push(line);
+        }
+    }
+
+    if entries.is_empty() {
+        return content.lines().take(20).collect::<Vec<_>>().join("\n");
+    }
+
+    entries
+        .iter()
+        .map(|entry| entry.join("\n").trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
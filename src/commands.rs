@@ -1,505 +1,4135 @@
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
-use std::fs;
-use std::path::Path;
+use std::fs::{self, File};
+use std::io::{self, IsTerminal};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
 use std::process::{self, Command};
+use std::sync::{Arc, Mutex};
 
-use crate::network::{fetch_index, resolve_or_download};
-use crate::types::{PackageInfo, PackageMeta, RepoIndex};
-use crate::utils::{calculate_sha256, create_package, extract_package, is_installed};
-use crate::{DB_DIR, PACKAGES_DIR, ROOT};
+use sha2::{Digest, Sha256};
+use tokio::sync::Semaphore;
 
-pub async fn cmd_install(package_input: &str) -> Result<(), String> {
-    let package_path = resolve_or_download(package_input).await?;
+use crate::cache::{apply_retention, auto_clean, cache_path, evict_to_limit, lock_blocking, remove_cached, remove_unused, scan, touch};
+use crate::changelog;
+use crate::conflicts::{self, Choice};
+use crate::deb;
+use crate::paths::{cache_dir, config_path, db_dir, is_user, packages_dir, store_dir, tmp_dir, versions_dir};
+use crate::plan::Plan;
+use crate::provenance;
+use crate::term::{bold, confirm, dim, green, red, yellow};
+use crate::throttle;
+use crate::config::Config;
+use crate::elf::{check_binary, soname};
+use crate::exit::{CHECKSUM, CONFLICT, Failure, NETWORK, NOT_FOUND, PARTIAL, fail};
+use crate::fakeroot;
+use crate::extras::{find_alternatives, find_completions, find_contact, find_dev_files, find_examples, find_man_pages, split_rules};
+use crate::hooks::{self, post_transaction};
+use crate::mirrors;
+use crate::network::{
+    FILES_DB, download_resumable, fetch_advisories, fetch_files_db, fetch_index, fetch_index_from, fetch_package, fetch_signature, is_repo_name,
+    SIGNATURES, post_json, resolve_all, resolve_or_download, upload_file,
+};
+use crate::oci;
+use crate::resolver::{closure, dep_provider, installed_provider, levels, split_siblings, topo_order};
+use crate::store;
+use crate::sysext;
+use crate::transaction::Transaction;
+use crate::types::{ArchBuild, Change, Deprecation, InstallReason, PackageInfo, PackageMeta, Provenance, RepoIndex};
+use crate::versions;
+use crate::utils::{
+    calculate_b3sum, calculate_sha256, create_package, extract_package, format_size, hash_files, installed_packages, is_installed, list_package,
+    bump_version, compare_versions, copy_attributes, copy_tree, glob_match, host_arch, install_reason, installed_files, installed_origin, is_major_jump,
+    package_file_name, package_file_version,
+    parse_list, parse_package_file_name, split_dep, read_package_file, read_package_info, remove_tree, valid_package_name, valid_version, version_in_range,
+    walk_files, write_files_db, write_index,
+};
+use crate::{ROOT, VERSION, brief, say};
 
-    let temp_dir = format!("/tmp/pls-extract-{}", process::id());
+const BUNDLE_JOBS: usize = 4;
+const PAYLOAD_DIRS: [&str; 4] = ["bin", "include", "lib", "share"];
 
-    extract_package(&package_path, &temp_dir)
-        .map_err(|e| format!("couldn't unpack that thing: {}", e))?;
+#[derive(Default)]
+pub struct InstallOptions {
+    pub with_optional: bool,
+    pub force: bool,
+    pub side_by_side: bool,
+}
 
-    let pkg = PackageInfo::from_file(&format!("{}/info", &temp_dir))
-        .map_err(|_| "package seems broken, no info file found")?;
+fn read_stdin_package() -> Result<String, String> {
+    if io::stdin().is_terminal() {
+        return Err("install - reads a .pls from stdin, pipe one in (curl ... | pls install -)".to_string());
+    }
+    fs::create_dir_all(tmp_dir()).map_err(|e| format!("couldn't create {}: {}", tmp_dir(), e))?;
+    let path = format!("{}/pls-stdin-{}.pls", tmp_dir(), process::id());
+    let mut file = File::create(&path).map_err(|e| format!("couldn't create {}: {}", path, e))?;
+    io::copy(&mut io::stdin().lock(), &mut file).map_err(|e| format!("couldn't read stdin: {}", e))?;
+    if read_package_info(&path).is_err() {
+        let _ = fs::remove_file(&path);
+        return Err("whatever came in on stdin isn't a .pls".to_string());
+    }
+    Ok(path)
+}
+
+pub async fn cmd_install(inputs: &[String], opts: &InstallOptions) -> Result<(), Failure> {
+    if inputs.iter().any(|input| input == "-") {
+        let piped = read_stdin_package()?;
+        let inputs: Vec<String> = inputs
+            .iter()
+            .map(|input| if input == "-" { piped.clone() } else { input.clone() })
+            .collect();
+        let result = Box::pin(cmd_install(&inputs, opts)).await;
+        let _ = fs::remove_file(&piped);
+        return result;
+    }
+
+    if let [input] = inputs
+        && input.ends_with(".plsx")
+    {
+        return install_bundle_file(input);
+    }
+
+    if opts.side_by_side {
+        return install_side_by_side(inputs, opts.force).await;
+    }
+
+    let config = Config::load();
+    if !opts.force {
+        let ignored: Vec<&str> = inputs
+            .iter()
+            .filter(|input| is_repo_name(input) && config.is_ignored(input.rsplit('/').next().unwrap_or(input)))
+            .map(|input| input.as_str())
+            .collect();
+        if !ignored.is_empty() {
+            return Err(fail(CONFLICT, format!("{} on the ignore list, use --force to install anyway", ignored.join(", "))));
+        }
+    }
+
+    let mut index = if inputs.iter().any(|input| is_repo_name(input)) {
+        say!("lemme check the repo...");
+        Some(fetch_index().await?)
+    } else {
+        None
+    };
+    let inputs: Vec<String> = match &mut index {
+        Some(index) => inputs.iter().map(|input| settle_repo(index, &config, input)).collect(),
+        None => inputs.to_vec(),
+    };
+
+    let mut wanted = inputs.clone();
+    let mut from_repo = Vec::new();
+    if let Some(index) = &index {
+        let (repo_names, other): (Vec<String>, Vec<String>) = inputs
+            .iter()
+            .cloned()
+            .partition(|input| index.packages.contains_key(input));
+        wanted = other;
+
+        let mut roots = repo_names.clone();
+        if opts.with_optional {
+            for name in &repo_names {
+                for opt in &index.packages[name].optional {
+                    if !roots.contains(&opt.name) && index.packages.contains_key(&opt.name) {
+                        roots.push(opt.name.clone());
+                    }
+                }
+            }
+        }
+
+        let siblings = split_siblings(index, &roots, &installed_packages());
+        if !siblings.is_empty() {
+            say!("bringing {} along, split packages move together", siblings.join(", "));
+        }
+        roots.extend(siblings.iter().cloned());
+
+        for name in closure(index, &roots)? {
+            if repo_names.contains(&name) || siblings.contains(&name) {
+                from_repo.push(name.clone());
+                wanted.push(name);
+            } else if config.is_ignored(&name) {
+                say!("not pulling in {}, it's on the ignore list", name);
+            } else if !is_installed(&name) {
+                from_repo.push(name.clone());
+                wanted.push(name);
+            }
+        }
+        let mut refused = Vec::new();
+        for name in &from_repo {
+            let Some(deprecation) = &index.packages[name].deprecated else {
+                continue;
+            };
+            if deprecation.is_eol() && repo_names.contains(name) && !opts.force {
+                refused.push(deprecation.describe(name));
+            } else {
+                say!("{} {}", yellow("heads up,"), deprecation.describe(name));
+            }
+        }
+        if !refused.is_empty() {
+            return Err(fail(CONFLICT, format!("{}, pass --force to install anyway", refused.join("; "))));
+        }
+
+        let deps: Vec<String> =
+            from_repo.iter().filter(|name| !repo_names.contains(name) && !siblings.contains(name)).cloned().collect();
+        let plan = Plan::build(index, &from_repo, &deps);
+        plan.print();
+        if !opts.force {
+            plan.check_space()?;
+        }
+    }
+
+    let reasons: Vec<Option<InstallReason>> = wanted
+        .iter()
+        .map(|name| match inputs.contains(name) {
+            true => Some(InstallReason::Explicit),
+            false => Some(InstallReason::Dependency),
+        })
+        .collect();
+
+    let paths = resolve_all(&wanted, index.as_ref()).await?;
+    let changes = install_files(&paths, &reasons, opts.force)?;
+    for change in &changes {
+        let meta = index.as_ref().filter(|_| from_repo.contains(&change.name)).and_then(|i| i.packages.get(&change.name));
+        record_origin(&change.name, meta);
+    }
+    auto_clean(&config);
+    post_transaction("install", &changes);
+    Ok(())
+}
+
+fn settle_repo(index: &mut RepoIndex, config: &Config, input: &str) -> String {
+    let repos = config.repos();
+    let available: Vec<&str> = repos
+        .iter()
+        .map(|(repo, _)| repo.as_str())
+        .filter(|repo| index.by_repo.contains_key(&format!("{}/{}", repo, input)))
+        .collect();
+    match conflicts::choose_repo(input, &config.tied_repos(input, &available)) {
+        Some(repo) => index.pin(&format!("{}/{}", repo, input)),
+        None => index.pin(input),
+    }
+}
+
+fn record_origin(name: &str, meta: Option<&PackageMeta>) {
+    let path = format!("{}/{}/repo", db_dir(), name);
+    match meta.filter(|m| !m.repo.is_empty()) {
+        Some(meta) => {
+            let _ = fs::write(path, format!("{}\n", meta.repo));
+        }
+        None => {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+async fn side_by_side_path(input: &str) -> Result<String, Failure> {
+    let Some((name, version)) = versions::parse(input) else {
+        return resolve_or_download(input).await;
+    };
+    let cached = cache_path(name, version);
+    if Path::new(&cached).exists() {
+        say!("using cached {}...", name);
+        return Ok(cached);
+    }
+    say!("lemme check the repo...");
+    let index = fetch_index().await?;
+    match index.packages.get(name) {
+        Some(meta) if meta.version == version => fetch_package(name, meta).await,
+        Some(meta) => Err(fail(
+            NOT_FOUND,
+            format!("the repo only has {} v{} and v{} isn't cached, grab its .pls and install that", name, meta.version, version),
+        )),
+        None => Err(fail(NOT_FOUND, format!("'{}' not found in repo", name))),
+    }
+}
+
+async fn install_side_by_side(inputs: &[String], force: bool) -> Result<(), Failure> {
+    let staged_root = format!("{}/pls-side-{}", tmp_dir(), process::id());
+    let mut result = Ok(());
+    for (i, input) in inputs.iter().enumerate() {
+        result = install_one_side_by_side(input, &format!("{}/{}", staged_root, i), force).await;
+        if result.is_err() {
+            break;
+        }
+    }
+    let _ = remove_tree(&staged_root);
+    result
+}
 
+async fn install_one_side_by_side(input: &str, stage_dir: &str, force: bool) -> Result<(), Failure> {
+    let path = side_by_side_path(input).await?;
+    let staged = stage_package(&path, stage_dir)?;
+    let pkg = &staged.info;
+    match check_compatible(&staged) {
+        Err(e) if force => say!("{} installing anyway: {}", yellow("heads up,"), e),
+        Err(e) => return Err(fail(e.code, format!("{} (use --force to install anyway)", e))),
+        Ok(()) => {}
+    }
     if is_installed(&pkg.name) {
-        println!("yo {} is already installed, reinstalling...", pkg.name);
+        return Err(fail(CONFLICT, format!("{} is already installed the normal way, pls remove {} first", pkg.name, pkg.name)));
+    }
+
+    let activated = versions::install(&staged.dir, pkg)?;
+    brief!("installed {} {}", pkg.name, pkg.version);
+    say!("{} {} v{} installed side by side", green("got ya!"), bold(&pkg.name), pkg.version);
+    match (activated, versions::active(&pkg.name)) {
+        (true, _) => say!("  it's the active one now"),
+        (false, Some(active)) => say!("  {} still on v{}, pls use {}@{} to switch", dim("tip:"), active, pkg.name, pkg.version),
+        (false, None) => {}
     }
+    for dep in &pkg.depend {
+        let (name, _) = split_dep(dep);
+        if !is_installed(name) && versions::installed(name).is_empty() {
+            say!("{} {} wants {}, side by side installs don't pull deps, pls install {}", yellow("heads up,"), pkg.name, name, name);
+        }
+    }
+    Ok(())
+}
+
+pub fn cmd_use(reference: &str) -> Result<(), Failure> {
+    let Some((name, version)) = versions::parse(reference) else {
+        let installed = versions::installed(reference);
+        if installed.is_empty() {
+            return Err(fail(NOT_FOUND, format!("{} isn't installed side by side, pls install --side-by-side {}", reference, reference)));
+        }
+        let active = versions::active(reference);
+        for version in installed {
+            match active.as_deref() == Some(version.as_str()) {
+                true => println!("{} {}", green(format!("* {}", version)), dim("(active)")),
+                false => println!("  {}", version),
+            }
+        }
+        return Ok(());
+    };
 
-    fs::create_dir_all(format!("{}/usr/bin", ROOT))
-        .map_err(|e| format!("couldn't create bin dir: {}", e))?;
+    if versions::active(name).as_deref() == Some(version) {
+        say!("{} is already on v{}", name, version);
+        return Ok(());
+    }
+    versions::activate(name, version)?;
+    say!("{} {} now runs v{}", green("got ya!"), bold(name), version);
+    brief!("using {} {}", name, version);
+    Ok(())
+}
 
-    let bin_dir = format!("{}/bin", temp_dir);
-    let entries = fs::read_dir(&bin_dir)
-        .map_err(|e| format!("couldn't read bin dir: {}", e))?;
+struct StagedPackage {
+    info: PackageInfo,
+    dir: String,
+    reason: Option<InstallReason>,
+}
 
-    for entry in entries.flatten() {
-        let src = entry.path();
-        if src.is_file() {
-            let filename = entry.file_name();
-            let dest = format!("{}/usr/bin/{}", ROOT, filename.to_string_lossy());
-            let _ = fs::remove_file(&dest);
-            fs::copy(&src, &dest)
-                .map_err(|e| format!("couldn't copy {}: {}", filename.to_string_lossy(), e))?;
+fn stage_package(package_path: &str, dir: &str) -> Result<StagedPackage, String> {
+    extract_package(package_path, dir)
+        .map_err(|e| format!("couldn't unpack {}: {}", package_path, e))?;
+
+    let info = PackageInfo::from_file(&format!("{}/info", dir))
+        .map_err(|_| format!("{} seems broken, no info file found", package_path))?;
+    if !valid_package_name(&info.name) || !valid_version(&info.version) {
+        return Err(format!("{} has a bad name or version ('{}' v'{}'), not touching it", package_path, info.name, info.version));
+    }
+
+    Ok(StagedPackage { info, dir: dir.to_string(), reason: None })
+}
+
+fn apply_package(txn: &mut Transaction, staged: &StagedPackage) -> Result<Change, Failure> {
+    if !versions::installed(&staged.info.name).is_empty() {
+        return Err(fail(
+            CONFLICT,
+            format!("{} is installed side by side, use pls install --side-by-side {}", staged.info.name, staged.info.name),
+        ));
+    }
+    let db_path = format!("{}/{}", db_dir(), staged.info.name);
+    let change = Change {
+        name: staged.info.name.clone(),
+        version: staged.info.version.clone(),
+        old_version: PackageInfo::from_file(&format!("{}/info", db_path)).ok().map(|pkg| pkg.version),
+    };
+    hooks::pre(change.action(), &change)?;
+    let old_entries = installed_files(&staged.info.name);
+    let reason = match staged.reason {
+        reason if !is_installed(&staged.info.name) => reason.unwrap_or(InstallReason::Explicit),
+        Some(InstallReason::Explicit) => InstallReason::Explicit,
+        _ => install_reason(&staged.info.name),
+    };
+
+    let config = Config::load();
+    let mut planned: Vec<(PathBuf, String)> = Vec::new();
+    for (src_dir, dest_dir) in config.install_dirs() {
+        let src_root = format!("{}/{}", staged.dir, src_dir);
+        if src_dir == "lib" && Path::new(&src_root).is_dir() {
+            hooks::libs_changed();
+        }
+        for rel in walk_files(&src_root) {
+            let dest = Path::new(ROOT).join(dest_dir.trim_start_matches('/')).join(&rel);
+            planned.push((Path::new(&src_root).join(&rel), dest.to_string_lossy().to_string()));
+        }
+    }
+    let skipped = settle_conflicts(txn, &staged.info.name, &planned)?;
+    planned.retain(|(_, dest)| !skipped.contains(dest));
+
+    let regular: Vec<String> = planned
+        .iter()
+        .filter(|(src, _)| fs::symlink_metadata(src).is_ok_and(|m| m.is_file()))
+        .map(|(src, _)| src.to_string_lossy().to_string())
+        .collect();
+    let staged_sums: HashMap<String, String> =
+        regular.iter().cloned().zip(hash_files(&regular)).filter_map(|(src, hash)| Some((src, hash.ok()?))).collect();
+
+    let mut files = Vec::new();
+    let mut sums = String::new();
+    let mut dirs: Vec<String> = old_entries.iter().filter(|e| e.ends_with('/')).cloned().collect();
+    for (src, dest) in &planned {
+        if let Some(parent) = Path::new(dest).parent() {
+            for dir in txn.create_dir(&parent.to_string_lossy())? {
+                dirs.push(format!("{}/", dir));
+            }
+        }
+        if let Ok(target) = fs::read_link(src) {
+            txn.symlink(&target, dest)?;
+        } else if let Some(content) = relocate_pkgconfig(src, &config) {
+            txn.write_file(dest, &content)?;
+            sums.push_str(&format!("{}  {}\n", hex::encode(Sha256::digest(content.as_bytes())), dest));
+        } else {
+            let sha = staged_sums.get(src.to_string_lossy().as_ref());
+            match sha.map(|sha| store::add(src, sha, Path::new(dest).parent().unwrap_or(Path::new("/")))) {
+                Some(Ok(Some(stored))) => txn.link_file(&stored, dest)?,
+                _ => txn.copy_file(src, dest)?,
+            }
+            if let Some(sha) = sha {
+                sums.push_str(&format!("{}  {}\n", sha, dest));
+            }
+        }
+        files.push(dest.clone());
+    }
+
+    for old in old_entries.iter().filter(|e| !e.ends_with('/')) {
+        if !files.contains(old) {
+            txn.remove_file(old)?;
+        }
+    }
+
+    txn.create_dir(&db_path)?;
+    txn.copy_file(Path::new(&format!("{}/info", staged.dir)), &format!("{}/info", db_path))?;
+
+    for extra in ["notes", provenance::FILE] {
+        let src = format!("{}/{}", staged.dir, extra);
+        if Path::new(&src).exists() {
+            txn.copy_file(Path::new(&src), &format!("{}/{}", db_path, extra))?;
+        } else {
+            txn.remove_file(&format!("{}/{}", db_path, extra))?;
+        }
+    }
+
+    txn.write_file(&format!("{}/sums", db_path), &sums)?;
+
+    files.extend(dirs);
+    let mut listing = files.join("\n");
+    listing.push('\n');
+    txn.write_file(&format!("{}/files", db_path), &listing)?;
+    txn.write_file(&format!("{}/reason", db_path), &format!("{}\n", reason.as_str()))?;
+    Ok(change)
+}
+
+fn relocate_pkgconfig(src: &Path, config: &Config) -> Option<String> {
+    if src.extension().is_none_or(|ext| ext != "pc") || src.parent().is_none_or(|dir| !dir.ends_with("pkgconfig")) {
+        return None;
+    }
+    let content = fs::read_to_string(src).ok()?;
+    let mut relocated = String::new();
+    for line in content.lines() {
+        let line = match line.split_once('=').map(|(key, _)| key.trim()) {
+            Some("prefix") => format!("prefix={}", config.prefix),
+            Some("libdir") => format!("libdir={}", config.libdir()),
+            Some("includedir") => format!("includedir={}", config.includedir()),
+            _ => line.to_string(),
+        };
+        relocated.push_str(&line);
+        relocated.push('\n');
+    }
+    Some(relocated)
+}
+
+fn settle_conflicts(txn: &mut Transaction, name: &str, planned: &[(PathBuf, String)]) -> Result<Vec<String>, Failure> {
+    let owners = conflicts::owners();
+    let mut clashes: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for (_, dest) in planned {
+        if let Some(owner) = owners.get(dest).filter(|owner| *owner != name) {
+            clashes.entry(owner.as_str()).or_default().push(dest.clone());
+        }
+    }
+
+    let mut skipped = Vec::new();
+    for (owner, files) in clashes {
+        match conflicts::resolve(name, owner, &files) {
+            Choice::Keep => {
+                say!("{} keeping {}'s {}", yellow("heads up,"), owner, files.join(", "));
+                skipped.extend(files);
+            }
+            Choice::Replace => {
+                say!("{} {} takes over {} from {}", yellow("heads up,"), name, files.join(", "), owner);
+                let rest: Vec<String> = installed_files(owner).into_iter().filter(|f| !files.contains(f)).collect();
+                let mut listing = rest.join("\n");
+                listing.push('\n');
+                txn.write_file(&format!("{}/{}/files", db_dir(), owner), &listing)?;
+            }
+            Choice::Abort => {
+                return Err(fail(
+                    CONFLICT,
+                    format!("{} and {} both ship {}, pick with --on-conflict keep|replace", name, owner, files[0]),
+                ));
+            }
+        }
+    }
+    Ok(skipped)
+}
+
+fn install_serialized(
+    package_path: &str,
+    stage_dir: &str,
+    reason: InstallReason,
+    db_lock: &Mutex<()>,
+    force: bool,
+) -> Result<Change, Failure> {
+    let mut staged = stage_package(package_path, stage_dir)?;
+    staged.reason = Some(reason);
+    match check_compatible(&staged) {
+        Err(e) if force => say!("{} installing anyway: {}", yellow("heads up,"), e),
+        Err(e) => return Err(fail(e.code, format!("{} (use --force to install anyway)", e))),
+        Ok(()) => {}
+    }
+
+    let change = {
+        let _guard = db_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut txn = Transaction::begin()?;
+        match apply_package(&mut txn, &staged) {
+            Ok(change) => {
+                txn.commit();
+                change
+            }
+            Err(e) => {
+                txn.rollback();
+                return Err(e);
+            }
+        }
+    };
+    hooks::post(change.action(), &change);
+    Ok(change)
+}
+
+fn install_files(package_paths: &[String], reasons: &[Option<InstallReason>], force: bool) -> Result<Vec<Change>, Failure> {
+    let staged_root = format!("{}/pls-extract-{}", tmp_dir(), process::id());
+    let result = stage_and_apply(package_paths, reasons, &staged_root, force);
+    let _ = remove_tree(&staged_root);
+    result
+}
+
+fn check_compatible(staged: &StagedPackage) -> Result<(), Failure> {
+    let arch = &staged.info.arch;
+    if !arch.is_empty() && arch != "any" && arch != host_arch() {
+        return Err(fail(CONFLICT, format!("{} is built for {} but this box is {}", staged.info.name, arch, host_arch())));
+    }
+
+    let bin_dir = format!("{}/bin", staged.dir);
+    for rel in walk_files(&bin_dir) {
+        check_binary(&Path::new(&bin_dir).join(&rel))
+            .map_err(|e| fail(CONFLICT, format!("{}: bin/{} is {}", staged.info.name, rel, e)))?;
+    }
+    Ok(())
+}
+
+fn stage_and_apply(
+    package_paths: &[String],
+    reasons: &[Option<InstallReason>],
+    staged_root: &str,
+    force: bool,
+) -> Result<Vec<Change>, Failure> {
+    let mut staged = Vec::new();
+    for (i, path) in package_paths.iter().enumerate() {
+        let mut pkg = stage_package(path, &format!("{}/{}", staged_root, i))?;
+        pkg.reason = reasons.get(i).copied().flatten();
+        staged.push(pkg);
+    }
+
+    for pkg in &staged {
+        match check_compatible(pkg) {
+            Err(e) if force => say!("{} installing anyway: {}", yellow("heads up,"), e),
+            Err(e) => return Err(fail(e.code, format!("{} (use --force to install anyway)", e))),
+            Ok(()) => {}
+        }
+    }
+
+    let names: Vec<String> = staged.iter().map(|p| p.info.name.clone()).collect();
+    let order = topo_order(&names, |name| {
+        staged
+            .iter()
+            .find(|p| p.info.name == name)
+            .map(|p| p.info.depend.clone())
+            .unwrap_or_default()
+    })?;
+    staged.sort_by_key(|p| order.iter().position(|name| *name == p.info.name));
+
+    if staged.len() > 1 {
+        say!("installing {} package(s):", staged.len());
+        for pkg in &staged {
+            say!("  {} v{}{}", pkg.info.name, pkg.info.version, installed_note(&pkg.info.name));
+        }
+    } else if let Some(pkg) = staged.first()
+        && is_installed(&pkg.info.name)
+    {
+        say!("yo {} is already installed, reinstalling...", pkg.info.name);
+    }
+
+    let mut txn = Transaction::begin()?;
+    let mut changes = Vec::new();
+    for pkg in &staged {
+        match apply_package(&mut txn, pkg) {
+            Ok(change) => changes.push(change),
+            Err(e) => {
+                txn.rollback();
+                return Err(fail(e.code, format!("{} failed ({}), rolled everything back", pkg.info.name, e)));
+            }
+        }
+    }
+    txn.commit();
+    for change in &changes {
+        hooks::post(change.action(), change);
+    }
+
+    for pkg in &staged {
+        brief!("installed {} {}", pkg.info.name, pkg.info.version);
+    }
+    if let [pkg] = staged.as_slice() {
+        say!("{} {} v{} installed", green("got ya!"), bold(&pkg.info.name), pkg.info.version);
+    } else {
+        let names: Vec<String> = staged
+            .iter()
+            .map(|p| format!("{} v{}", p.info.name, p.info.version))
+            .collect();
+        say!("{} {} package(s) installed: {}", green("got ya!"), staged.len(), names.join(", "));
+    }
+
+    for pkg in &staged {
+        if let Ok(notes) = fs::read_to_string(format!("{}/notes", pkg.dir)) {
+            say!("\nnotes from {}:", bold(&pkg.info.name));
+            for line in notes.trim_end().lines() {
+                say!("  {}", line);
+            }
+        }
+    }
+
+    for pkg in &staged {
+        for opt in &pkg.info.optional {
+            if is_installed(&opt.name) {
+                continue;
+            }
+            if opt.reason.is_empty() {
+                say!("  {} {} works with {}, pls install {}", dim("tip:"), pkg.info.name, opt.name, opt.name);
+            } else {
+                say!("  {} install {} for {} ({})", dim("tip:"), opt.name, opt.reason, pkg.info.name);
+            }
+        }
+    }
+    Ok(changes)
+}
+
+fn installed_note(name: &str) -> String {
+    match PackageInfo::from_file(&format!("{}/{}/info", db_dir(), name)) {
+        Ok(old) => format!(" (replacing v{})", old.version),
+        Err(_) => String::new(),
+    }
+}
+
+pub fn cmd_remove(package_name: &str) -> Result<(), Failure> {
+    let side_by_side = match versions::parse(package_name) {
+        Some((name, version)) => Some((name, Some(version))),
+        None if !is_installed(package_name) && !versions::installed(package_name).is_empty() => Some((package_name, None)),
+        None => None,
+    };
+    if let Some((name, version)) = side_by_side {
+        let removed = versions::remove(name, version)?;
+        say!("{} {} v{} has been removed", green("gone!"), name, removed.join(", v"));
+        brief!("removed {} {}", name, removed.join(" "));
+        if let Some(active) = versions::active(name) {
+            say!("  {} is on v{} now", name, active);
+        }
+        return Ok(());
+    }
+    let change = remove_package(package_name)?;
+    post_transaction("remove", &[change]);
+    Ok(())
+}
+
+fn remove_package(package_name: &str) -> Result<Change, Failure> {
+    let Ok(pkg) = PackageInfo::from_file(&format!("{}/{}/info", db_dir(), package_name)) else {
+        return Err(fail(NOT_FOUND, format!("'{}' isn't even installed bro", package_name)));
+    };
+    let change = Change { name: pkg.name, version: pkg.version, old_version: None };
+    hooks::pre("remove", &change)?;
+
+    let entries = installed_files(package_name);
+    if entries.is_empty() {
+        let bin_path = Path::new(ROOT).join(Config::load().prefix.trim_start_matches('/')).join("bin").join(package_name);
+        if bin_path.exists() {
+            fs::remove_file(&bin_path).map_err(|e| format!("couldn't delete binary: {}", e))?;
+        }
+    }
+
+    let (mut dirs, files): (Vec<&String>, Vec<&String>) = entries.iter().partition(|e| e.ends_with('/'));
+    let libdir = Config::load().libdir();
+    if files.iter().any(|file| file.starts_with(&format!("{}/", libdir))) {
+        hooks::libs_changed();
+    }
+    for file in files {
+        if fs::symlink_metadata(file).is_ok() {
+            fs::remove_file(file).map_err(|e| format!("couldn't delete {}: {}", file, e))?;
+        }
+    }
+    dirs.sort_by_key(|d| std::cmp::Reverse(d.len()));
+    for dir in dirs {
+        let _ = fs::remove_dir(dir);
+    }
+
+    let db_path = format!("{}/{}", db_dir(), package_name);
+    fs::remove_dir_all(&db_path).map_err(|e| format!("couldn't remove from db: {}", e))?;
+
+    say!("{} {} has been removed", green("gone!"), package_name);
+    brief!("removed {}", package_name);
+    hooks::post("remove", &change);
+    Ok(change)
+}
+
+pub async fn cmd_info(package_input: &str, notes: bool, provenance: bool, files: bool) -> Result<(), Failure> {
+    if notes {
+        return print_notes(package_input);
+    }
+    if files {
+        return print_archive_files(package_input);
+    }
+    if provenance {
+        return print_provenance(package_input).await;
+    }
+
+    let Some(package_path) = crate::utils::resolve_package_path(package_input) else {
+        return print_installed_info(package_input).await;
+    };
+
+    let temp_dir = format!("{}/pls-info-{}", tmp_dir(), process::id());
+    extract_package(&package_path, &temp_dir).map_err(|e| format!("couldn't unpack: {}", e))?;
+
+    let pkg = PackageInfo::from_file(&format!("{}/info", &temp_dir))
+        .map_err(|_| "no info file in package")?;
+
+    println!("name: {}", pkg.name);
+    if !pkg.desc.is_empty() {
+        println!("description: {}", pkg.desc);
+    }
+    println!("version: {}", pkg.version);
+    if let Some(revision) = pkg.revision() {
+        println!("revision: {}", revision);
+    }
+    if !pkg.keywords.is_empty() {
+        println!("keywords: {}", pkg.keywords.join(", "));
+    }
+    if !pkg.maintainer.is_empty() {
+        println!("maintainer: {}", pkg.maintainer);
+    }
+    if !pkg.homepage.is_empty() {
+        println!("homepage: {}", pkg.homepage);
+    }
+    if !pkg.split_from.is_empty() {
+        println!("split from: {}", pkg.split_from);
+    }
+    if !pkg.depend.is_empty() {
+        println!("depends: {}", pkg.depend.join(", "));
+    }
+    if !pkg.provides.is_empty() {
+        println!("provides: {}", pkg.provides.join(", "));
+    }
+    print_alternatives(&pkg);
+
+    let _ = remove_tree(&temp_dir);
+    Ok(())
+}
+
+async fn print_installed_info(reference: &str) -> Result<(), Failure> {
+    let fetched = fetch_index().await;
+    let index_error = fetched.as_ref().err().cloned();
+    let mut index = fetched.ok();
+    let name = match &mut index {
+        Some(index) => index.pin(reference),
+        None => reference.to_string(),
+    };
+    let name = name.as_str();
+    let origin = installed_origin(name);
+    if let (Some(index), Some(repo)) = (&mut index, &origin)
+        && !reference.contains('/')
+        && Config::load().pin_for(name).is_none()
+    {
+        index.pin(&format!("{}/{}", repo, name));
+    }
+    let local = PackageInfo::from_file(&format!("{}/{}/info", db_dir(), name)).ok();
+    let remote = index.as_ref().and_then(|index| index.packages.get(name));
+
+    if local.is_none() && remote.is_none() {
+        if let Some(e) = index_error {
+            return Err(e.context(format!("'{}' isn't installed and the repo couldn't be checked", name)));
+        }
+        return Err(fail(NOT_FOUND, format!("'{}' isn't installed, isn't in the repo and isn't a package file", name)));
+    }
+
+    println!("name: {}", name);
+    if let Some(meta) = remote {
+        println!("description: {}", meta.desc);
+    }
+    if let Some(repo) = origin.as_deref().or(remote.map(|meta| meta.repo.as_str())) {
+        println!("repo: {}", repo);
+    }
+    if let Some(parent) = local.as_ref().map(|pkg| pkg.split_from.as_str()).or(remote.map(|meta| meta.split_from.as_str()))
+        && !parent.is_empty()
+    {
+        println!("split from: {}", parent);
+    }
+
+    match (&local, remote) {
+        (Some(pkg), Some(meta)) if meta.version != pkg.version => {
+            println!("version: {} {}", pkg.version, yellow(format!("(v{} available, pls update)", meta.version)))
+        }
+        (Some(pkg), Some(_)) => println!("version: {} {}", pkg.version, green("(up to date)")),
+        (Some(pkg), None) => println!("version: {} (not in the repo)", pkg.version),
+        (None, Some(meta)) => println!("version: {} (not installed)", meta.version),
+        (None, None) => {}
+    }
+    if let Some(revision) = local.as_ref().and_then(|pkg| pkg.revision()) {
+        println!("revision: {}", revision);
+    }
+    let contact = |pick: fn(&PackageMeta) -> &String, fallback: fn(&PackageInfo) -> &String| {
+        remote.map(pick).or(local.as_ref().map(fallback)).filter(|value| !value.is_empty()).cloned()
+    };
+    if let Some(maintainer) = contact(|meta| &meta.maintainer, |pkg| &pkg.maintainer) {
+        println!("maintainer: {}", maintainer);
+    }
+    if let Some(homepage) = contact(|meta| &meta.homepage, |pkg| &pkg.homepage) {
+        println!("homepage: {}", homepage);
+    }
+
+    let depend = match (&local, remote) {
+        (Some(pkg), _) => &pkg.depend,
+        (None, Some(meta)) => &meta.deps,
+        (None, None) => return Ok(()),
+    };
+    if !depend.is_empty() {
+        println!("depends: {}", depend.join(", "));
+    }
+    let provides = match (&local, remote) {
+        (Some(pkg), _) => &pkg.provides,
+        (None, Some(meta)) => &meta.provides,
+        (None, None) => return Ok(()),
+    };
+    if !provides.is_empty() {
+        println!("provides: {}", provides.join(", "));
+    }
+    if let Some(pkg) = &local {
+        print_alternatives(pkg);
+    }
+    if let Some(meta) = remote {
+        println!("download size: {}", format_size(meta.size));
+        if meta.installed_size > 0 {
+            println!("installed size: {}", format_size(meta.installed_size));
+        }
+    }
+
+    if local.is_some() {
+        let installed_at = fs::metadata(format!("{}/{}/info", db_dir(), name))
+            .and_then(|m| m.modified())
+            .map(|t| chrono::DateTime::<chrono::Local>::from(t).format("%Y-%m-%d %H:%M").to_string());
+        if let Ok(installed_at) = installed_at {
+            println!("installed: {}", installed_at);
         }
+        let files = installed_files(name).iter().filter(|e| !e.ends_with('/')).count();
+        println!("files: {}", files);
+    }
+    Ok(())
+}
+
+fn print_alternatives(pkg: &PackageInfo) {
+    if pkg.alternatives.is_empty() {
+        return;
+    }
+    let offered: Vec<String> = pkg
+        .alternatives
+        .iter()
+        .map(|alt| format!("{} ({}, priority {})", alt.name, alt.path, alt.priority))
+        .collect();
+    println!("alternatives: {}", offered.join(", "));
+}
+
+fn search_rank(name: &str, meta: &PackageMeta, term: &str, desc: bool) -> Option<u8> {
+    let name = name.to_lowercase();
+    if name == term {
+        Some(0)
+    } else if name.starts_with(term) {
+        Some(1)
+    } else if name.contains(term) {
+        Some(2)
+    } else if meta.keywords.iter().any(|keyword| keyword.to_lowercase() == term) {
+        Some(3)
+    } else if desc && meta.desc.to_lowercase().contains(term) {
+        Some(4)
+    } else {
+        None
+    }
+}
+
+pub async fn cmd_search(term: &str, desc: bool) -> Result<(), Failure> {
+    let term = term.to_lowercase();
+    let index = fetch_index().await?;
+    let mut hits: Vec<(u8, &String, &PackageMeta)> = index
+        .packages
+        .iter()
+        .filter_map(|(name, meta)| Some((search_rank(name, meta, &term, desc)?, name, meta)))
+        .collect();
+    hits.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(b.1)));
+
+    if hits.is_empty() {
+        let hint = if desc { String::new() } else { ", --desc looks in descriptions too".to_string() };
+        return Err(fail(NOT_FOUND, format!("nothing in the repo matches '{}'{}", term, hint)));
+    }
+    let installed = installed_packages();
+    for (_, name, meta) in &hits {
+        let marker = match installed.iter().find(|pkg| pkg.name == **name) {
+            Some(pkg) if pkg.version == meta.version => format!(" {}", green("[installed]")),
+            Some(pkg) => format!(" {}", yellow(format!("[installed v{}]", pkg.version))),
+            None => String::new(),
+        };
+        println!("{} v{}{}", bold(name), meta.version, marker);
+        if !meta.desc.is_empty() && meta.desc != format!("{} package", name) {
+            println!("  {}", dim(&meta.desc));
+        }
+    }
+    say!("\n{} match(es)", hits.len());
+    Ok(())
+}
+
+fn via(dep: &str, provider: &str) -> String {
+    if dep == provider { dep.to_string() } else { format!("{} {}", dep, dim(format!("(from {})", provider))) }
+}
+
+pub async fn cmd_deps(name: &str, tree: bool) -> Result<(), Failure> {
+    let fetched = fetch_index().await;
+    let index_error = fetched.as_ref().err().cloned();
+    let index = fetched.ok();
+    let installed = installed_packages();
+
+    let lookup = |dep: &str| -> Option<PackageInfo> {
+        let name = index
+            .as_ref()
+            .and_then(|index| dep_provider(index, dep))
+            .or_else(|| installed_provider(&installed, dep))?;
+        if let Some(meta) = index.as_ref().and_then(|index| index.packages.get(name)) {
+            return Some(PackageInfo {
+                name: name.to_string(),
+                version: meta.version.clone(),
+                depend: meta.deps.clone(),
+                ..Default::default()
+            });
+        }
+        PackageInfo::from_file(&format!("{}/{}/info", db_dir(), name)).ok()
+    };
+    let marker = |name: &str, version: &str| match installed.iter().find(|pkg| pkg.name == name) {
+        Some(pkg) if pkg.version == version => format!(" {}", green("[installed]")),
+        Some(pkg) => format!(" {}", yellow(format!("[installed v{}]", pkg.version))),
+        None => String::new(),
+    };
+
+    let root = lookup(name).ok_or_else(|| match &index_error {
+        Some(e) => e.clone().context(format!("'{}' isn't installed and the repo couldn't be checked", name)),
+        None => fail(NOT_FOUND, format!("'{}' isn't installed and isn't in the repo", name)),
+    })?;
+    println!("{} v{}{}", name, root.version, marker(name, &root.version));
+
+    if !tree {
+        for dep in &root.depend {
+            let (dep_name, req) = split_dep(dep);
+            let req = if req.is_empty() { String::new() } else { format!(" ({})", req) };
+            match lookup(dep_name) {
+                Some(pkg) => println!("  {}{} v{}{}", via(dep_name, &pkg.name), req, pkg.version, marker(&pkg.name, &pkg.version)),
+                None => println!("  {}{} {}", dep_name, req, red("(missing)")),
+            }
+        }
+        return Ok(());
+    }
+
+    fn walk(
+        deps: &[String],
+        prefix: &str,
+        path: &mut Vec<String>,
+        shown: &mut Vec<String>,
+        lookup: &dyn Fn(&str) -> Option<PackageInfo>,
+        marker: &dyn Fn(&str, &str) -> String,
+    ) {
+        for (i, dep) in deps.iter().enumerate() {
+            let last = i + 1 == deps.len();
+            let (branch, indent) = if last { ("└── ", "    ") } else { ("├── ", "│   ") };
+            let (dep_name, req) = split_dep(dep);
+            let req = if req.is_empty() { String::new() } else { format!(" ({})", req) };
+
+            let Some(pkg) = lookup(dep_name) else {
+                println!("{}{}{}{} {}", prefix, branch, dep_name, req, red("(missing)"));
+                continue;
+            };
+            let label = via(dep_name, &pkg.name);
+            let dep_name = pkg.name.as_str();
+            let mut line = format!("{}{}{}{} v{}{}", prefix, branch, label, req, pkg.version, marker(dep_name, &pkg.version));
+
+            if path.iter().any(|p| p == dep_name) {
+                println!("{} {}", line, yellow("(cycle)"));
+                continue;
+            }
+            if shown.iter().any(|s| s == dep_name) && !pkg.depend.is_empty() {
+                line.push_str(&format!(" {}", dim("(*)")));
+                println!("{}", line);
+                continue;
+            }
+            println!("{}", line);
+            shown.push(dep_name.to_string());
+
+            path.push(dep_name.to_string());
+            walk(&pkg.depend, &format!("{}{}", prefix, indent), path, shown, lookup, marker);
+            path.pop();
+        }
+    }
+
+    walk(&root.depend, "", &mut vec![name.to_string()], &mut Vec::new(), &lookup, &marker);
+    Ok(())
+}
+
+fn print_archive_files(package_input: &str) -> Result<(), Failure> {
+    let package_path = crate::utils::resolve_package_path(package_input)
+        .ok_or_else(|| fail(NOT_FOUND, format!("'{}' isn't a package file, --files wants a .pls", package_input)))?;
+
+    let (mut count, mut total) = (0, 0);
+    list_package(&package_path, |entry| {
+        if entry.path.is_empty() || entry.path == "." {
+            return;
+        }
+        let size = if entry.mode.starts_with('-') { format_size(entry.size) } else { "-".to_string() };
+        match &entry.link {
+            Some(target) => println!("{} {:>10}  {} -> {}", entry.mode, size, entry.path, target),
+            None => println!("{} {:>10}  {}", entry.mode, size, entry.path),
+        }
+        if entry.mode.starts_with('-') {
+            count += 1;
+            total += entry.size;
+        }
+    })
+    .map_err(|e| format!("couldn't read {}: {}", package_path, e))?;
+    say!("\n{} file(s), {} unpacked", count, format_size(total));
+    Ok(())
+}
+
+fn print_notes(package_input: &str) -> Result<(), Failure> {
+    let content = if is_installed(package_input) {
+        fs::read_to_string(format!("{}/{}/notes", db_dir(), package_input)).ok()
+    } else {
+        let package_path = crate::utils::resolve_package_path(package_input)
+            .ok_or_else(|| fail(NOT_FOUND, format!("'{}' isn't installed and isn't a package file", package_input)))?;
+        read_package_file(&package_path, "notes").ok()
+    };
+
+    match content {
+        Some(notes) => print!("{}", notes),
+        None => println!("{} didn't come with any notes", package_input),
+    }
+    Ok(())
+}
+
+async fn print_provenance(package_input: &str) -> Result<(), Failure> {
+    let stamp = if is_installed(package_input) {
+        fs::read_to_string(format!("{}/{}/{}", db_dir(), package_input, provenance::FILE)).ok().map(|p| provenance::parse(&p))
+    } else if let Some(package_path) = crate::utils::resolve_package_path(package_input) {
+        read_package_file(&package_path, provenance::FILE).ok().map(|p| provenance::parse(&p))
+    } else {
+        let mut index = fetch_index().await?;
+        let name = index.pin(package_input);
+        let meta = index
+            .packages
+            .get(&name)
+            .ok_or_else(|| fail(NOT_FOUND, format!("'{}' isn't installed, isn't in the repo and isn't a package file", name)))?;
+        Some(meta.provenance.clone()).filter(|p| !p.is_empty())
+    };
+
+    match stamp {
+        Some(stamp) => {
+            for (key, value) in stamp {
+                println!("{}: {}", key, value);
+            }
+        }
+        None => println!("{} didn't come with provenance, it was built before pls recorded it", package_input),
+    }
+    Ok(())
+}
+
+pub fn cmd_extract(package_input: &str, dest: Option<&str>) -> Result<(), Failure> {
+    let package_path = crate::utils::resolve_package_path(package_input)
+        .ok_or_else(|| fail(NOT_FOUND, format!("couldn't find '{}'", package_input)))?;
+    let pkg = read_package_info(&package_path).map_err(|_| "package seems broken, no info file found")?;
+
+    let dest = dest
+        .map(|d| d.to_string())
+        .unwrap_or_else(|| format!("{}-{}", pkg.name, pkg.version));
+    let occupied = fs::read_dir(&dest).is_ok_and(|mut entries| entries.next().is_some());
+    if occupied || Path::new(&dest).is_file() {
+        return Err(fail(CONFLICT, format!("{} already exists and isn't empty, pick another dir", dest)));
+    }
+
+    extract_package(&package_path, &dest).map_err(|e| format!("couldn't unpack: {}", e))?;
+
+    say!("unpacked {} v{} into {}/", pkg.name, pkg.version, dest);
+    brief!("extracted {} {} {}", pkg.name, pkg.version, dest);
+    Ok(())
+}
+
+async fn fetch_with_missing_deps(package_input: &str) -> Result<Vec<String>, Failure> {
+    if !is_repo_name(package_input) {
+        return Ok(vec![resolve_or_download(package_input).await?]);
+    }
+    say!("lemme check the repo...");
+    let index = fetch_index().await?;
+    let names: Vec<String> = closure(&index, &[package_input.to_string()])?
+        .into_iter()
+        .filter(|name| name == package_input || !is_installed(name))
+        .collect();
+    resolve_all(&names, Some(&index)).await
+}
+
+fn unpack_all(paths: &[String], dir: &str) -> Result<Vec<String>, String> {
+    let mut dirs = Vec::new();
+    for (i, path) in paths.iter().enumerate() {
+        let dest = format!("{}/{}", dir, i);
+        extract_package(path, &dest).map_err(|e| format!("couldn't unpack {}: {}", path, e))?;
+        dirs.push(dest);
+    }
+    dirs.reverse();
+    Ok(dirs)
+}
+
+fn prefixed_env(var: &str, dirs: &[String], sub: &str, fallback: &str) -> String {
+    let mut parts: Vec<String> = dirs.iter().map(|d| format!("{}/{}", d, sub)).collect();
+    parts.push(env::var(var).unwrap_or_else(|_| fallback.to_string()));
+    parts.join(":")
+}
+
+pub fn cmd_convert(package_input: &str, to: &str, output: Option<&str>) -> Result<(), Failure> {
+    if to != "deb" {
+        return Err(format!("can't convert to '{}', only deb for now", to).into());
+    }
+    let package_path = crate::utils::resolve_package_path(package_input)
+        .ok_or_else(|| fail(NOT_FOUND, format!("couldn't find '{}'", package_input)))?;
+    let pkg = read_package_info(&package_path).map_err(|_| "package seems broken, no info file found")?;
+
+    let output = output.map(|o| o.to_string()).unwrap_or_else(|| deb::file_name(&pkg));
+    deb::convert(&package_path, &pkg, &output)?;
+    say!("{} {}, files land in /usr/local, hand it over with: apt install ./{}", green("converted!"), output, output);
+    brief!("converted {}", output);
+    Ok(())
+}
+
+pub async fn cmd_run(package_input: &str, args: &[String]) -> Result<(), Failure> {
+    let paths = fetch_with_missing_deps(package_input).await?;
+
+    let run_dir = format!("{}/pls-run-{}", tmp_dir(), process::id());
+    let result = run_unpacked(&paths, &run_dir, args);
+    let _ = fs::remove_dir_all(&run_dir);
+
+    let code = result?;
+    if code != 0 {
+        process::exit(code);
+    }
+    Ok(())
+}
+
+fn run_unpacked(paths: &[String], run_dir: &str, args: &[String]) -> Result<i32, String> {
+    let dirs = unpack_all(paths, run_dir)?;
+    let main_dir = &dirs[0];
+    let pkg = PackageInfo::from_file(&format!("{}/info", main_dir))
+        .map_err(|_| "package seems broken, no info file found")?;
+    let bins = walk_files(&format!("{}/bin", main_dir));
+    let bin = match bins.as_slice() {
+        [] => return Err(format!("{} has no binaries to run", pkg.name)),
+        [only] => only.clone(),
+        _ => bins
+            .iter()
+            .find(|b| **b == pkg.name)
+            .cloned()
+            .ok_or_else(|| format!("{} has several binaries, dunno which one: {}", pkg.name, bins.join(", ")))?,
+    };
+
+    let status = Command::new(format!("{}/bin/{}", main_dir, bin))
+        .args(args)
+        .env("PATH", prefixed_env("PATH", &dirs, "bin", "/usr/bin:/bin"))
+        .status()
+        .map_err(|e| format!("couldn't run {}: {}", bin, e))?;
+    Ok(status.code().unwrap_or(1))
+}
+
+pub async fn cmd_try(package_input: &str) -> Result<(), Failure> {
+    let paths = fetch_with_missing_deps(package_input).await?;
+
+    let try_dir = format!("{}/pls-try-{}", tmp_dir(), process::id());
+    let result = try_in_shell(&paths, &try_dir);
+    let _ = fs::remove_dir_all(&try_dir);
+    say!("{} trial over, {} is gone", green("done!"), package_input);
+    Ok(result?)
+}
+
+fn try_in_shell(paths: &[String], try_dir: &str) -> Result<(), String> {
+    let dirs = unpack_all(paths, try_dir)?;
+    let pkg = PackageInfo::from_file(&format!("{}/info", dirs[0]))
+        .map_err(|_| "package seems broken, no info file found")?;
+
+    let shell = env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+    say!("trying {} v{}, it's on your PATH until you exit this shell", pkg.name, pkg.version);
+    Command::new(&shell)
+        .env("PATH", prefixed_env("PATH", &dirs, "bin", "/usr/bin:/bin"))
+        .env("MANPATH", prefixed_env("MANPATH", &dirs, "share/man", ""))
+        .env("XDG_DATA_DIRS", prefixed_env("XDG_DATA_DIRS", &dirs, "share", "/usr/local/share:/usr/share"))
+        .env("PLS_TRY", format!("{}-{}", pkg.name, pkg.version))
+        .status()
+        .map_err(|e| format!("couldn't start {}: {}", shell, e))?;
+    Ok(())
+}
+
+pub fn cmd_list(only: Option<InstallReason>, tree: bool) -> Result<(), Failure> {
+    if !Path::new(db_dir()).exists() {
+        println!("nothing installed yet");
+        return Ok(());
+    }
+    if tree {
+        return list_tree();
+    }
+
+    let entries = fs::read_dir(db_dir()).map_err(|_| "couldn't read package database")?;
+
+    let mut count = 0;
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let info_path = format!("{}/{}/info", db_dir(), name.to_string_lossy());
+
+        if let Ok(pkg) = PackageInfo::from_file(&info_path) {
+            let reason = install_reason(&pkg.name);
+            if only.is_some_and(|only| only != reason) {
+                continue;
+            }
+            if reason == InstallReason::Dependency && only.is_none() {
+                println!("{} v{} {}", pkg.name, pkg.version, dim("(dependency)"));
+            } else {
+                println!("{} v{}", pkg.name, pkg.version);
+            }
+            count += 1;
+        }
+    }
+
+    if only.is_none() {
+        for name in versions::managed() {
+            let active = versions::active(&name);
+            let listed: Vec<String> = versions::installed(&name)
+                .into_iter()
+                .map(|v| if active.as_deref() == Some(v.as_str()) { format!("v{}*", v) } else { format!("v{}", v) })
+                .collect();
+            println!("{} {} {}", name, listed.join(" "), dim("(side by side)"));
+            count += 1;
+        }
+    }
+
+    if count == 0 {
+        say!("nothing installed yet");
+    } else {
+        say!("\n{} package(s) installed", count);
+    }
+    Ok(())
+}
+
+fn list_tree() -> Result<(), Failure> {
+    let mut installed = installed_packages();
+    installed.sort_by(|a, b| a.name.cmp(&b.name));
+    if installed.is_empty() {
+        say!("nothing installed yet");
+        return Ok(());
+    }
+    let by_name: HashMap<&str, &PackageInfo> = installed.iter().map(|pkg| (pkg.name.as_str(), pkg)).collect();
+    let children = |pkg: &PackageInfo| -> Vec<&PackageInfo> {
+        pkg.depend.iter().filter_map(|dep| by_name.get(installed_provider(&installed, dep)?).copied()).collect()
+    };
+    let needed: Vec<&str> = installed.iter().flat_map(&children).map(|dep| dep.name.as_str()).collect();
+
+    fn walk<'a>(
+        deps: &[&'a PackageInfo],
+        prefix: &str,
+        shown: &mut Vec<&'a str>,
+        children: &dyn Fn(&'a PackageInfo) -> Vec<&'a PackageInfo>,
+    ) {
+        for (i, pkg) in deps.iter().enumerate() {
+            let (branch, indent) = if i + 1 == deps.len() { ("└── ", "    ") } else { ("├── ", "│   ") };
+            let line = format!("{}{}{} v{}", prefix, branch, pkg.name, pkg.version);
+            let below = children(pkg);
+            if install_reason(&pkg.name) == InstallReason::Explicit {
+                println!("{} {}", line, dim("(explicit)"));
+            } else if shown.contains(&pkg.name.as_str()) && !below.is_empty() {
+                println!("{} {}", line, dim("(*)"));
+            } else if shown.contains(&pkg.name.as_str()) {
+                println!("{}", line);
+            } else {
+                println!("{}", line);
+                shown.push(&pkg.name);
+                walk(&below, &format!("{}{}", prefix, indent), shown, children);
+            }
+        }
+    }
+
+    let mut shown = Vec::new();
+    let mut roots = 0;
+    for pkg in &installed {
+        let orphan = install_reason(&pkg.name) == InstallReason::Dependency && !needed.contains(&pkg.name.as_str());
+        if install_reason(&pkg.name) != InstallReason::Explicit && !orphan {
+            continue;
+        }
+        match orphan {
+            true => println!("{} v{} {}", bold(&pkg.name), pkg.version, dim("(dependency nothing needs, pls autoremove)")),
+            false => println!("{} v{}", bold(&pkg.name), pkg.version),
+        }
+        walk(&children(pkg), "", &mut shown, &children);
+        roots += 1;
+    }
+    say!("\n{} package(s) installed, {} at the top", installed.len(), roots);
+    Ok(())
+}
+
+pub async fn cmd_list_deprecated() -> Result<(), Failure> {
+    let mut installed = installed_packages();
+    if installed.is_empty() {
+        say!("nothing installed yet");
+        return Ok(());
+    }
+    say!("lemme check the repo...");
+    let index = fetch_index().await?;
+    installed.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut found = 0;
+    for pkg in &installed {
+        let Some(deprecation) = index.packages.get(&pkg.name).and_then(|meta| meta.deprecated.as_ref()) else {
+            continue;
+        };
+        let note = deprecation.describe(&pkg.name);
+        say!("{} {}  {}", bold(&pkg.name), pkg.version, if deprecation.is_eol() { red(note) } else { yellow(note) });
+        brief!("{}", pkg.name);
+        found += 1;
+    }
+    if found == 0 {
+        say!("{} none of your packages are deprecated", green("all good!"));
+    }
+    Ok(())
+}
+
+pub fn cmd_mark(names: &[String], reason: InstallReason) -> Result<(), Failure> {
+    for name in names {
+        if !is_installed(name) {
+            return Err(fail(NOT_FOUND, format!("'{}' isn't even installed bro", name)));
+        }
+    }
+
+    for name in names {
+        if install_reason(name) == reason {
+            say!("{} is already marked {}", name, reason.as_str());
+            continue;
+        }
+        fs::write(format!("{}/{}/reason", db_dir(), name), format!("{}\n", reason.as_str()))
+            .map_err(|e| format!("couldn't mark {}: {}", name, e))?;
+        match reason {
+            InstallReason::Explicit => say!("{} {} is explicit now, autoremove won't touch it", green("got ya!"), name),
+            InstallReason::Dependency => say!("{} {} is auto now, autoremove takes it once nothing needs it", green("got ya!"), name),
+        }
+        brief!("marked {} {}", name, reason.as_str());
+    }
+    Ok(())
+}
+
+pub async fn cmd_deps_remote(name: &str) -> Result<(), Failure> {
+    let mut index = fetch_index().await?;
+    let name = index.pin(name);
+    let order = closure(&index, std::slice::from_ref(&name))?;
+    let installed = installed_packages();
+
+    let mut download = 0;
+    let mut skipped = 0;
+    say!("{} would bring in {} package(s):", bold(&name), order.len());
+    for dep in &order {
+        let meta = &index.packages[dep];
+        let state = match installed.iter().find(|pkg| pkg.name == *dep) {
+            Some(pkg) if pkg.version == meta.version => Some(green("[installed]")),
+            _ if Path::new(&cache_path(dep, &meta.version)).exists() => Some(dim("[cached]")),
+            _ => None,
+        };
+        match state {
+            Some(state) => {
+                skipped += 1;
+                say!("  {:<24} v{:<12} {:>10}  {}", dep, meta.version, format_size(meta.size), state);
+            }
+            None => {
+                download += meta.size;
+                say!("  {:<24} v{:<12} {:>10}", dep, meta.version, format_size(meta.size));
+            }
+        }
+    }
+    say!();
+    say!("download: {} ({} already here)", format_size(download), skipped);
+    brief!("{} package(s), {} to download", order.len(), format_size(download));
+    Ok(())
+}
+
+pub async fn cmd_why(name: &str) -> Result<(), Failure> {
+    let installed = installed_packages();
+    let Some(pkg) = installed.iter().find(|pkg| pkg.name == name) else {
+        return Err(fail(NOT_FOUND, format!("'{}' isn't installed, nothing to explain", name)));
+    };
+    let bundles: Vec<(String, Vec<String>)> = fetch_index()
+        .await
+        .map(|index| index.bundles.keys().filter_map(|bundle| Some((bundle.clone(), index.expand_bundle(bundle).ok()?))).collect())
+        .unwrap_or_default();
+
+    let dependents = |name: &str| -> Vec<(String, String)> {
+        installed
+            .iter()
+            .filter(|pkg| pkg.depend.iter().any(|dep| installed_provider(&installed, dep) == Some(name)))
+            .map(|pkg| (pkg.name.clone(), pkg.version.clone()))
+            .collect()
+    };
+    let why_explicit = |name: &str| -> String {
+        let mut in_bundles: Vec<&str> = bundles
+            .iter()
+            .filter(|(_, members)| members.iter().any(|m| split_dep(m).0 == name))
+            .map(|(bundle, _)| bundle.as_str())
+            .collect();
+        in_bundles.sort();
+        match in_bundles.as_slice() {
+            [] => "installed explicitly".to_string(),
+            [bundle] => format!("installed explicitly, part of bundle {}", bundle),
+            many => format!("installed explicitly, part of bundles {}", many.join(", ")),
+        }
+    };
+
+    fn walk(
+        name: &str,
+        depth: usize,
+        seen: &mut Vec<String>,
+        dependents: &dyn Fn(&str) -> Vec<(String, String)>,
+        why_explicit: &dyn Fn(&str) -> String,
+    ) {
+        for (parent, version) in dependents(name) {
+            let indent = "  ".repeat(depth);
+            if seen.contains(&parent) {
+                println!("{}needed by {} {}", indent, parent, dim("(see above)"));
+                continue;
+            }
+            seen.push(parent.clone());
+            let note = match install_reason(&parent) {
+                InstallReason::Explicit => format!(" ({})", why_explicit(&parent)),
+                InstallReason::Dependency => String::new(),
+            };
+            println!("{}needed by {} v{}{}", indent, parent, version, note);
+            walk(&parent, depth + 1, seen, dependents, why_explicit);
+        }
+    }
+
+    let reason = install_reason(name);
+    match reason {
+        InstallReason::Explicit => println!("{} v{} was {}", bold(name), pkg.version, why_explicit(name)),
+        InstallReason::Dependency => println!("{} v{} was installed as a dependency", bold(name), pkg.version),
+    }
+
+    let mut seen = vec![name.to_string()];
+    walk(name, 1, &mut seen, &dependents, &why_explicit);
+
+    if reason == InstallReason::Dependency && dependents(name).is_empty() {
+        println!("  but nothing needs it anymore, 'pls autoremove' will take it");
+    }
+    Ok(())
+}
+
+pub fn cmd_autoremove() -> Result<(), Failure> {
+    let installed = installed_packages();
+    let mut keep: Vec<String> = installed
+        .iter()
+        .filter(|pkg| install_reason(&pkg.name) == InstallReason::Explicit)
+        .map(|pkg| pkg.name.clone())
+        .collect();
+
+    let mut i = 0;
+    while i < keep.len() {
+        if let Some(pkg) = installed.iter().find(|pkg| pkg.name == keep[i]) {
+            for dep in &pkg.depend {
+                let Some(dep) = installed_provider(&installed, dep) else {
+                    continue;
+                };
+                if !keep.iter().any(|k| k == dep) {
+                    keep.push(dep.to_string());
+                }
+            }
+        }
+        i += 1;
+    }
+
+    let orphans: Vec<&PackageInfo> = installed.iter().filter(|pkg| !keep.contains(&pkg.name)).collect();
+    if orphans.is_empty() {
+        say!("nothing to autoremove, every dependency is still needed");
+        return Ok(());
+    }
+
+    say!("removing {} package(s) nothing needs anymore", orphans.len());
+    Plan::removing(&orphans).print();
+    let mut changes = Vec::new();
+    for pkg in &orphans {
+        match remove_package(&pkg.name) {
+            Ok(change) => changes.push(change),
+            Err(e) => {
+                post_transaction("remove", &changes);
+                return Err(e);
+            }
+        }
+    }
+    post_transaction("remove", &changes);
+    Ok(())
+}
+
+#[derive(Clone, Copy)]
+pub enum ProjectType {
+    Rust,
+    CMake,
+    Meson,
+    PlsToml,
+}
+
+fn detect_project(path: &str) -> Option<(ProjectType, PackageInfo)> {
+    let cargo_path = format!("{}/Cargo.toml", path);
+    if Path::new(&cargo_path).exists()
+        && let Ok(content) = fs::read_to_string(&cargo_path)
+    {
+        let pkg = PackageInfo::parse_cargo_toml(&content);
+        if !pkg.name.is_empty() {
+            return Some((ProjectType::Rust, pkg));
+        }
+    }
+
+    let cmake_path = format!("{}/CMakeLists.txt", path);
+    if Path::new(&cmake_path).exists()
+        && let Ok(content) = fs::read_to_string(&cmake_path)
+    {
+        let pkg = PackageInfo::parse_cmake(&content);
+        if !pkg.name.is_empty() {
+            return Some((ProjectType::CMake, pkg));
+        }
+    }
+
+    let meson_path = format!("{}/meson.build", path);
+    if Path::new(&meson_path).exists()
+        && let Ok(content) = fs::read_to_string(&meson_path)
+    {
+        let pkg = PackageInfo::parse_meson(&content);
+        if !pkg.name.is_empty() {
+            return Some((ProjectType::Meson, pkg));
+        }
+    }
+
+    let pls_path = format!("{}/pls.toml", path);
+    if Path::new(&pls_path).exists()
+        && let Ok(content) = fs::read_to_string(&pls_path)
+    {
+        let pkg = PackageInfo::parse_pls_toml(&content);
+        if !pkg.name.is_empty() {
+            return Some((ProjectType::PlsToml, pkg));
+        }
+    }
+
+    None
+}
+
+#[derive(Default)]
+pub struct AddOptions<'a> {
+    pub is_draft: bool,
+    pub output_dir: Option<&'a str>,
+    pub install: bool,
+    pub set_version: Option<&'a str>,
+    pub bump: Option<&'a str>,
+    pub no_man: bool,
+    pub all: bool,
+    pub test: bool,
+}
+
+fn build_output_dirs(project_type: &ProjectType, binary_path: &str, name: &str) -> Vec<String> {
+    let Some(out_dir) = Path::new(binary_path).parent() else {
+        return Vec::new();
+    };
+    match project_type {
+        ProjectType::Rust => {
+            let prefix = format!("{}-", name);
+            fs::read_dir(out_dir.join("build"))
+                .map(|entries| {
+                    entries
+                        .flatten()
+                        .filter(|e| e.file_name().to_string_lossy().starts_with(&prefix))
+                        .map(|e| e.path().join("out").to_string_lossy().to_string())
+                        .collect()
+                })
+                .unwrap_or_default()
+        }
+        ProjectType::CMake | ProjectType::Meson => vec![out_dir.to_string_lossy().to_string()],
+        ProjectType::PlsToml => Vec::new(),
+    }
+}
+
+fn stamp_version(pkg: &mut PackageInfo, opts: &AddOptions) -> Result<(), String> {
+    let project_version = pkg.version.clone();
+    match (opts.set_version, opts.bump) {
+        (Some(_), Some(_)) => return Err("pick one: --set-version or --bump".to_string()),
+        (Some(version), None) => pkg.version = version.to_string(),
+        (None, Some(part)) => pkg.version = bump_version(&pkg.version, part)?,
+        (None, None) => {}
+    }
+    if pkg.version != project_version {
+        say!("stamping {} as v{} (project says v{})", pkg.name, pkg.version, project_version);
+    }
+    Ok(())
+}
+
+fn cargo_build(project_path: &str, is_draft: bool) -> Result<(), String> {
+    let mut args = vec!["build"];
+    if !is_draft {
+        args.push("--release");
+    }
+    let status = Command::new("cargo")
+        .args(&args)
+        .current_dir(project_path)
+        .status()
+        .map_err(|e| format!("cargo failed: {}", e))?;
+    if !status.success() {
+        return Err("build failed, fix ur code first".to_string());
+    }
+    Ok(())
+}
+
+fn cmake_build(project_path: &str, is_draft: bool) -> Result<(), String> {
+    let build_dir = format!("{}/build", project_path);
+    fs::create_dir_all(&build_dir).map_err(|_| "couldn't create build dir")?;
+
+    let cmake_type = if is_draft { "Debug" } else { "Release" };
+    let status = Command::new("cmake")
+        .args(["..", &format!("-DCMAKE_BUILD_TYPE={}", cmake_type)])
+        .current_dir(&build_dir)
+        .status()
+        .map_err(|e| format!("cmake failed: {}", e))?;
+    if !status.success() {
+        return Err("cmake configure failed".to_string());
+    }
+
+    let status = Command::new("make")
+        .args(["-j4"])
+        .current_dir(&build_dir)
+        .status()
+        .map_err(|e| format!("make failed: {}", e))?;
+    if !status.success() {
+        return Err("build failed".to_string());
+    }
+    Ok(())
+}
+
+fn meson_build(project_path: &str, is_draft: bool) -> Result<(), String> {
+    let build_dir = format!("{}/builddir", project_path);
+    if !Path::new(&build_dir).exists() {
+        let build_type = if is_draft { "debug" } else { "release" };
+        let status = Command::new("meson")
+            .args(["setup", &build_dir, "--buildtype", build_type])
+            .current_dir(project_path)
+            .status()
+            .map_err(|e| format!("meson failed: {}", e))?;
+        if !status.success() {
+            return Err("meson setup failed".to_string());
+        }
+    }
+
+    let status = Command::new("ninja")
+        .args(["-C", &build_dir])
+        .status()
+        .map_err(|e| format!("ninja failed: {}", e))?;
+    if !status.success() {
+        return Err("build failed".to_string());
+    }
+    Ok(())
+}
+
+fn run_tests(project_path: &str, project_type: &ProjectType, name: &str, is_draft: bool) -> Result<(), String> {
+    let (tool, args, dir): (&str, Vec<String>, String) = match project_type {
+        ProjectType::Rust => {
+            let mut args = vec!["test".to_string()];
+            if !is_draft {
+                args.push("--release".to_string());
+            }
+            ("cargo", args, project_path.to_string())
+        }
+        ProjectType::CMake => ("ctest", vec!["--output-on-failure".to_string()], format!("{}/build", project_path)),
+        ProjectType::Meson => ("meson", vec!["test".to_string(), "-C".to_string(), "builddir".to_string()], project_path.to_string()),
+        ProjectType::PlsToml => {
+            let content = fs::read_to_string(format!("{}/pls.toml", project_path)).map_err(|_| "couldn't read pls.toml")?;
+            let command = content
+                .lines()
+                .filter_map(|line| line.split_once('='))
+                .find(|(key, _)| key.trim() == "test")
+                .map(|(_, value)| value.trim().trim_matches('"').trim_matches('\'').to_string())
+                .filter(|command| !command.is_empty())
+                .ok_or("--test wants a test = \"...\" command in pls.toml")?;
+            ("sh", vec!["-c".to_string(), command], project_path.to_string())
+        }
+    };
+
+    say!("testing {}...", name);
+    let status = Command::new(tool)
+        .args(&args)
+        .current_dir(&dir)
+        .status()
+        .map_err(|e| format!("couldn't run {}, is it installed? ({})", tool, e))?;
+    if !status.success() {
+        return Err(format!("tests failed ({}), not packing {}", status, name));
+    }
+    say!("{} tests pass", green("nice!"));
+    Ok(())
+}
+
+pub fn cmd_add(project_path: &str, opts: &AddOptions) -> Result<(), Failure> {
+    if opts.all {
+        return cmd_add_all(project_path, opts);
+    }
+
+    let (pkg, package_files) = build_project(project_path, opts)?;
+
+    say!("{} {} v{} is ready", green("got ya twin!"), bold(&pkg.name), pkg.version);
+    say!("share it: {}", package_files.join(" "));
+    brief!("built {} {} {}", pkg.name, pkg.version, package_files.join(" "));
+
+    if opts.install {
+        say!();
+        let changes = install_files(&package_files, &vec![Some(InstallReason::Explicit); package_files.len()], false)?;
+        post_transaction("install", &changes);
+    }
+    Ok(())
+}
+
+fn build_project(project_path: &str, opts: &AddOptions) -> Result<(PackageInfo, Vec<String>), String> {
+    let is_draft = opts.is_draft;
+    let (project_type, mut pkg) = detect_project(project_path)
+        .ok_or_else(|| "dunno what project this is, need Cargo.toml, CMakeLists.txt, meson.build, or pls.toml".to_string())?;
+    stamp_version(&mut pkg, opts)?;
+
+    let binary_path = match project_type {
+        ProjectType::Rust => {
+            let (build_type, bin_path) = if is_draft {
+                ("debug", format!("{}/target/debug/{}", project_path, pkg.name))
+            } else {
+                ("release", format!("{}/target/release/{}", project_path, pkg.name))
+            };
+
+            if !Path::new(&bin_path).exists() {
+                say!("building {} {}...", build_type, pkg.name);
+                cargo_build(project_path, is_draft)?;
+            }
+            bin_path
+        }
+        ProjectType::CMake => {
+            let bin_path = format!("{}/build/{}", project_path, pkg.name);
+
+            if !Path::new(&bin_path).exists() {
+                say!("building {} with cmake...", pkg.name);
+                cmake_build(project_path, is_draft)?;
+            }
+            bin_path
+        }
+        ProjectType::Meson => {
+            let bin_path = format!("{}/builddir/{}", project_path, pkg.name);
+
+            if !Path::new(&bin_path).exists() {
+                say!("building {} with meson...", pkg.name);
+                meson_build(project_path, is_draft)?;
+            }
+            bin_path
+        }
+        ProjectType::PlsToml => {
+            let pls_path = format!("{}/pls.toml", project_path);
+            let content = fs::read_to_string(&pls_path).map_err(|_| "couldn't read pls.toml")?;
+            let mut binary = String::new();
+            for line in content.lines() {
+                if let Some((key, value)) = line.split_once('=')
+                    && key.trim() == "binary"
+                {
+                    binary = value.trim().trim_matches('"').trim_matches('\'').to_string();
+                }
+            }
+            if binary.is_empty() {
+                return Err("pls.toml needs 'binary' field".to_string());
+            }
+            format!("{}/{}", project_path, binary)
+        }
+    };
+
+    if !matches!(project_type, ProjectType::PlsToml) {
+        pkg.depend.clear();
+    }
+
+    if opts.test {
+        run_tests(project_path, &project_type, &pkg.name, is_draft)?;
+    }
+
+    pkg.arch = host_arch().to_string();
+    let package_files = package_binary(project_path, &project_type, &pkg, &binary_path, opts)?;
+    Ok((pkg, package_files))
+}
+
+pub fn cmd_rebuild(name: &str, check_only: bool) -> Result<(), Failure> {
+    let installed = PackageInfo::from_file(&format!("{}/{}/info", db_dir(), name))
+        .map_err(|_| fail(NOT_FOUND, format!("'{}' isn't installed, nothing to rebuild", name)))?;
+    let stamp = fs::read_to_string(format!("{}/{}/{}", db_dir(), name, provenance::FILE))
+        .map(|p| provenance::parse(&p))
+        .unwrap_or_default();
+    let (Some(source), Some(commit)) = (stamp.get("source"), stamp.get("commit")) else {
+        return Err(format!("{} doesn't say where its source lives, can't rebuild it", name).into());
+    };
+    if stamp.get("flags").is_some_and(|f| f.starts_with("prebuilt")) {
+        return Err(format!("{} was packed from a prebuilt binary, there's nothing to rebuild", name).into());
+    }
+    let commit = match commit.strip_suffix("-dirty") {
+        Some(clean) => {
+            say!("{} {} was built from a dirty tree, rebuilding from {} won't match exactly", yellow("heads up,"), name, clean);
+            clean
+        }
+        None => commit.as_str(),
+    };
+    let schemes = ["https://", "http://", "ssh://", "git://", "file://"];
+    let scp_like = source.split_once(':').is_some_and(|(host, path)| {
+        !host.is_empty() && !host.starts_with('-') && !host.contains('/') && !path.starts_with(':') && !path.starts_with("//")
+    });
+    if !schemes.iter().any(|scheme| source.starts_with(scheme)) && !scp_like {
+        return Err(format!("{} records '{}' as its source, that's not a url pls will clone", name, source).into());
+    }
+    if commit.is_empty() || !commit.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("{} records '{}' as its commit, that's not a commit hash", name, commit).into());
+    }
+
+    let work = format!("{}/pls-rebuild-{}", tmp_dir(), process::id());
+    let _ = fs::remove_dir_all(&work);
+    let src = format!("{}/src", work);
+    say!("fetching {} at {}...", source, &commit[..commit.len().min(12)]);
+    run_tool("git", &["clone".into(), "--quiet".into(), "--".into(), source.clone(), src.clone()])?;
+    let result = changelog::git(&src, &["checkout", "--quiet", commit])
+        .ok_or_else(|| Failure::from(format!("{} doesn't have commit {} anymore", source, commit)))
+        .and_then(|_| {
+            let opts = AddOptions {
+                is_draft: stamp.get("flags").is_some_and(|f| f.starts_with("debug")),
+                output_dir: Some(&work),
+                install: false,
+                set_version: Some(&installed.version),
+                bump: None,
+                no_man: false,
+                all: false,
+                test: false,
+            };
+            Ok(build_project(&src, &opts)?)
+        })
+        .and_then(|(_, mut package_files)| {
+            let package_file = package_files.remove(0);
+            let (same, differ) = compare_with_installed(&package_file, &format!("{}/check", work))?;
+            if differ.is_empty() {
+                say!("{} rebuilt {} matches what's installed bit for bit ({} file(s))", green("nice!"), name, same);
+            } else {
+                say!("{} rebuilt {} differs from what's installed:", yellow("heads up,"), name);
+                for file in &differ {
+                    say!("  {}", file);
+                }
+            }
+            brief!("rebuilt {} {}: {} same, {} different", name, installed.version, same, differ.len());
+            if check_only {
+                return Ok(());
+            }
+            say!();
+            let changes = install_files(&[package_file], &[None], false)?;
+            post_transaction("install", &changes);
+            Ok(())
+        });
+    let _ = fs::remove_dir_all(&work);
+    result
+}
+
+fn compare_with_installed(package_file: &str, dir: &str) -> Result<(usize, Vec<String>), String> {
+    extract_package(package_file, dir).map_err(|e| format!("couldn't unpack {}: {}", package_file, e))?;
+    let mut same = 0;
+    let mut differ = Vec::new();
+    for (src_dir, dest_dir) in Config::load().install_dirs() {
+        for rel in walk_files(&format!("{}/{}", dir, src_dir)) {
+            let dest = Path::new(ROOT).join(dest_dir.trim_start_matches('/')).join(&rel);
+            let fresh = calculate_sha256(&format!("{}/{}/{}", dir, src_dir, rel)).ok();
+            let current = calculate_sha256(&dest.to_string_lossy()).ok();
+            if fresh.is_some() && fresh == current {
+                same += 1;
+            } else {
+                differ.push(format!("{}/{}", src_dir, rel));
+            }
+        }
+    }
+    Ok((same, differ))
+}
+
+fn package_binary(
+    project_path: &str,
+    project_type: &ProjectType,
+    pkg: &PackageInfo,
+    binary_path: &str,
+    opts: &AddOptions,
+) -> Result<Vec<String>, String> {
+    if !Path::new(binary_path).exists() {
+        return Err(format!("binary not found at {}", binary_path));
+    }
+
+    let build_dir = &format!("{}/pls-build", tmp_dir());
+    let _ = fs::remove_dir_all(build_dir);
+    fs::create_dir_all(format!("{}/bin", build_dir))
+        .map_err(|_| "couldn't create build directory")?;
+
+    fs::copy(binary_path, format!("{}/bin/{}", build_dir, pkg.name))
+        .map_err(|_| "couldn't copy binary")?;
+
+    let rev = provenance::revision(project_path);
+    let (maintainer, homepage) = find_contact(project_path);
+    let pkg = &PackageInfo {
+        alternatives: find_alternatives(project_path, &pkg.name),
+        maintainer: maintainer.unwrap_or_else(|| pkg.maintainer.clone()),
+        homepage: homepage.unwrap_or_else(|| pkg.homepage.clone()),
+        commit: rev.as_ref().map(|rev| rev.commit.clone()).unwrap_or_default(),
+        tag: rev.as_ref().map(|rev| rev.tag.clone()).unwrap_or_default(),
+        dirty: rev.as_ref().is_some_and(|rev| rev.dirty),
+        ..pkg.clone()
+    };
+    if let Some(revision) = pkg.revision() {
+        say!("built from {}", revision);
+    }
+    if pkg.maintainer.is_empty() {
+        say!("{} no maintainer, add maintainer = \"you <you@example.com>\" to pls.toml", dim("tip:"));
+    }
+
+    let build_dirs = build_output_dirs(project_type, binary_path, &pkg.name);
+    if !opts.no_man {
+        bundle_files(build_dir, "share", &find_man_pages(project_path, &build_dirs), "man page")?;
+    }
+    bundle_files(build_dir, "share", &find_completions(project_path, &pkg.name, &build_dirs), "completion")?;
+    let splits = split_rules(project_path)?;
+    if splits.iter().any(|(kind, _)| kind == "docs") {
+        bundle_files(build_dir, "share", &find_examples(project_path, &pkg.name), "example")?;
+    }
+    if splits.iter().any(|(kind, _)| kind == "dev") {
+        bundle_files(build_dir, ".", &find_dev_files(project_path, &build_dirs), "dev file")?;
+    }
+
+    let output_dir = opts.output_dir.unwrap_or(packages_dir());
+    let previous = previous_package(output_dir, pkg);
+    if let Some((version, commit, _)) = &previous {
+        say!("last packed v{} from {}, collecting commits since", version, &commit[..commit.len().min(12)]);
+    }
+    let previous = previous.as_ref().map(|(_, commit, changelog)| (commit.as_str(), changelog.as_str()));
+    if let Some(changelog) = changelog::find_or_generate(project_path, &pkg.version, previous) {
+        fs::write(format!("{}/changelog", build_dir), changelog)
+            .map_err(|_| "couldn't write changelog")?;
+    }
+
+    let notes_path = format!("{}/pls-notes.txt", project_path);
+    if Path::new(&notes_path).exists() {
+        fs::copy(&notes_path, format!("{}/notes", build_dir))
+            .map_err(|_| "couldn't copy pls-notes.txt")?;
+    }
+
+    let perms = fakeroot::from_pls_toml(project_path);
+    if !perms.is_empty() {
+        fs::write(format!("{}/{}", build_dir, fakeroot::FILE), perms).map_err(|_| "couldn't write perms")?;
+    }
+
+    let stamp = provenance::collect(project_path, project_type, opts.is_draft);
+    fs::write(format!("{}/{}", build_dir, provenance::FILE), provenance::render(&stamp))
+        .map_err(|_| "couldn't write provenance")?;
+
+    for alternative in &pkg.alternatives {
+        if !Path::new(build_dir).join(&alternative.path).exists() {
+            return Err(format!("pls.toml offers {} as {} but the package has no {}", alternative.path, alternative.name, alternative.path));
+        }
+        say!("{} can be {} (priority {})", alternative.path, alternative.name, alternative.priority);
+    }
+
+    let mut subpackages = Vec::new();
+    for (kind, patterns) in &splits {
+        subpackages.extend(split_off(build_dir, pkg, kind, patterns, output_dir)?);
+    }
+    let mut package_files = vec![pack_build_dir(build_dir, pkg, output_dir)?];
+    package_files.extend(subpackages);
+    Ok(package_files)
+}
+
+fn previous_package(output_dir: &str, pkg: &PackageInfo) -> Option<(String, String, String)> {
+    let (version, path) = fs::read_dir(output_dir)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let (version, _) = package_file_version(&entry.file_name().to_string_lossy(), &pkg.name)?;
+            (compare_versions(&version, &pkg.version) == Ordering::Less).then(|| (version, entry.path()))
+        })
+        .max_by(|a, b| compare_versions(&a.0, &b.0))?;
+    let path = path.to_string_lossy();
+    let info = read_package_info(&path).ok().filter(|info| !info.commit.is_empty())?;
+    Some((version, info.commit, read_package_file(&path, "changelog").unwrap_or_default()))
+}
+
+fn split_off(build_dir: &str, pkg: &PackageInfo, kind: &str, patterns: &[String], output_dir: &str) -> Result<Option<String>, String> {
+    let name = format!("{}-{}", pkg.name, kind);
+    let files: Vec<String> = walk_files(build_dir)
+        .into_iter()
+        .filter(|rel| rel.contains('/') && patterns.iter().any(|pattern| glob_match(pattern, rel)))
+        .collect();
+    if files.is_empty() {
+        say!("nothing goes into {}, not splitting it out", name);
+        return Ok(None);
+    }
+
+    let sub_dir = format!("{}-{}", build_dir, kind);
+    let _ = fs::remove_dir_all(&sub_dir);
+    for rel in &files {
+        let target = Path::new(&sub_dir).join(rel);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|_| format!("couldn't create {}", parent.display()))?;
+        }
+        let source = Path::new(build_dir).join(rel);
+        fs::rename(&source, &target).map_err(|e| format!("couldn't move {} into {}: {}", rel, name, e))?;
+        let mut dir = source.parent();
+        while let Some(parent) = dir.filter(|d| *d != Path::new(build_dir)) {
+            if fs::remove_dir(parent).is_err() {
+                break;
+            }
+            dir = parent.parent();
+        }
+    }
+    for extra in [provenance::FILE, fakeroot::FILE] {
+        let _ = fs::copy(format!("{}/{}", build_dir, extra), format!("{}/{}", sub_dir, extra));
+    }
+    say!("splitting {} file(s) into {}", files.len(), name);
+
+    let sub = PackageInfo {
+        name,
+        version: pkg.version.clone(),
+        depend: vec![format!("{} = {}", pkg.name, pkg.version)],
+        arch: if kind == "docs" { "any".to_string() } else { pkg.arch.clone() },
+        split_from: pkg.name.clone(),
+        ..Default::default()
+    };
+    pack_build_dir(&sub_dir, &sub, output_dir).map(Some)
+}
+
+struct BuildTarget {
+    project_type: ProjectType,
+    project_path: String,
+    pkg: PackageInfo,
+    binary_path: String,
+}
+
+fn cargo_targets(project_path: &str, is_draft: bool) -> Result<Vec<BuildTarget>, String> {
+    let output = Command::new("cargo")
+        .args(["metadata", "--no-deps", "--format-version", "1"])
+        .current_dir(project_path)
+        .output()
+        .map_err(|e| format!("cargo failed: {}", e))?;
+    if !output.status.success() {
+        return Err("cargo metadata failed, is this a cargo project?".to_string());
+    }
+    let metadata: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("couldn't read cargo metadata: {}", e))?;
+
+    let target_dir = metadata["target_directory"].as_str().unwrap_or("target");
+    let profile = if is_draft { "debug" } else { "release" };
+
+    let mut targets = Vec::new();
+    for package in metadata["packages"].as_array().into_iter().flatten() {
+        let version = package["version"].as_str().unwrap_or("0.1.0");
+        let member_dir = package["manifest_path"]
+            .as_str()
+            .and_then(|p| Path::new(p).parent())
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|| project_path.to_string());
+
+        for target in package["targets"].as_array().into_iter().flatten() {
+            let is_bin = target["kind"].as_array().is_some_and(|k| k.iter().any(|k| k == "bin"));
+            let Some(name) = target["name"].as_str().filter(|_| is_bin) else {
+                continue;
+            };
+            targets.push(BuildTarget {
+                project_type: ProjectType::Rust,
+                project_path: member_dir.clone(),
+                pkg: PackageInfo { name: name.to_string(), version: version.to_string(), ..Default::default() },
+                binary_path: format!("{}/{}/{}", target_dir, profile, name),
+            });
+        }
+    }
+    Ok(targets)
+}
+
+fn executables_in(content: &str, call: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    for line in content.lines() {
+        let line = line.trim();
+        let Some(pos) = line.to_lowercase().find(call) else {
+            continue;
+        };
+        if line[..pos].ends_with(|c: char| c.is_alphanumeric() || c == '_') {
+            continue;
+        }
+        let inner = line[pos + call.len()..].trim_start();
+        let name: String = inner
+            .trim_start_matches(['\'', '"'])
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+            .collect();
+        if !name.is_empty() && !inner.contains("IMPORTED") && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+fn find_built_binary(build_dir: &str, name: &str) -> Option<String> {
+    walk_files(build_dir)
+        .into_iter()
+        .filter(|rel| !rel.contains("CMakeFiles/") && !rel.contains(".p/"))
+        .filter(|rel| Path::new(rel).file_name().is_some_and(|f| f == name))
+        .map(|rel| format!("{}/{}", build_dir, rel))
+        .find(|path| fs::metadata(path).is_ok_and(|m| m.permissions().mode() & 0o111 != 0))
+}
+
+fn native_targets(project_path: &str, project_type: ProjectType, pkg: &PackageInfo) -> Vec<BuildTarget> {
+    let (build_file, call, build_dir) = match project_type {
+        ProjectType::CMake => ("CMakeLists.txt", "add_executable(", format!("{}/build", project_path)),
+        _ => ("meson.build", "executable(", format!("{}/builddir", project_path)),
+    };
+
+    let mut names = Vec::new();
+    for rel in walk_files(project_path) {
+        if rel.starts_with("build/") || rel.starts_with("builddir/") || !rel.ends_with(build_file) {
+            continue;
+        }
+        let content = fs::read_to_string(format!("{}/{}", project_path, rel)).unwrap_or_default();
+        for name in executables_in(&content, call) {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+
+    names
+        .into_iter()
+        .map(|name| BuildTarget {
+            project_type,
+            project_path: project_path.to_string(),
+            binary_path: find_built_binary(&build_dir, &name).unwrap_or_else(|| format!("{}/{}", build_dir, name)),
+            pkg: PackageInfo { name, version: pkg.version.clone(), ..Default::default() },
+        })
+        .collect()
+}
+
+fn cmd_add_all(project_path: &str, opts: &AddOptions) -> Result<(), Failure> {
+    let targets = if Path::new(&format!("{}/Cargo.toml", project_path)).exists() {
+        say!("building every binary in the workspace...");
+        cargo_build(project_path, opts.is_draft)?;
+        cargo_targets(project_path, opts.is_draft)?
+    } else {
+        match detect_project(project_path) {
+            Some((ProjectType::CMake, pkg)) => {
+                say!("building {} with cmake...", pkg.name);
+                cmake_build(project_path, opts.is_draft)?;
+                native_targets(project_path, ProjectType::CMake, &pkg)
+            }
+            Some((ProjectType::Meson, pkg)) => {
+                say!("building {} with meson...", pkg.name);
+                meson_build(project_path, opts.is_draft)?;
+                native_targets(project_path, ProjectType::Meson, &pkg)
+            }
+            _ => return Err("--all needs a cargo workspace, cmake or meson project".into()),
+        }
+    };
+
+    if targets.is_empty() {
+        return Err("no binaries found, nothing to pack".into());
+    }
+
+    let mut package_files = Vec::new();
+    let mut tested: Vec<(String, bool)> = Vec::new();
+    let mut failed = Vec::new();
+    for mut target in targets {
+        if opts.test {
+            let passed = match tested.iter().find(|(path, _)| *path == target.project_path) {
+                Some((_, passed)) => *passed,
+                None => {
+                    let result = run_tests(&target.project_path, &target.project_type, &target.pkg.name, opts.is_draft);
+                    if let Err(e) = &result {
+                        say!("{} {}", red("!!!"), e);
+                    }
+                    tested.push((target.project_path.clone(), result.is_ok()));
+                    result.is_ok()
+                }
+            };
+            if !passed {
+                failed.push(target.pkg.name.clone());
+                continue;
+            }
+        }
+        stamp_version(&mut target.pkg, opts)?;
+        target.pkg.arch = host_arch().to_string();
+        let built =
+            package_binary(&target.project_path, &target.project_type, &target.pkg, &target.binary_path, opts)?;
+        say!("{} {} v{} is ready", green("got ya twin!"), bold(&target.pkg.name), target.pkg.version);
+        brief!("built {} {} {}", target.pkg.name, target.pkg.version, built.join(" "));
+        package_files.extend(built);
+    }
+
+    say!(
+        "packed {} packages into {}",
+        package_files.len(),
+        opts.output_dir.unwrap_or(packages_dir())
+    );
+
+    if opts.install && !package_files.is_empty() {
+        say!();
+        let changes = install_files(&package_files, &vec![Some(InstallReason::Explicit); package_files.len()], false)?;
+        post_transaction("install", &changes);
+    }
+    if !failed.is_empty() {
+        return Err(fail(PARTIAL, format!("tests failed, didn't pack {}", failed.join(", "))));
+    }
+    Ok(())
+}
+
+pub fn cmd_create(
+    source_dir: &str,
+    name: Option<&str>,
+    version: Option<&str>,
+    output_dir: Option<&str>,
+) -> Result<(), Failure> {
+    if !Path::new(source_dir).is_dir() {
+        return Err(format!("{} is not a directory", source_dir).into());
+    }
+
+    let mut pkg = PackageInfo::from_file(&format!("{}/info", source_dir)).unwrap_or_default();
+    if let Some(name) = name {
+        pkg.name = name.to_string();
+    }
+    if let Some(version) = version {
+        pkg.version = version.to_string();
+    }
+    if pkg.name.is_empty() || pkg.version.is_empty() {
+        return Err("need a name and version: pass --name and --version or put an info file in there".into());
+    }
+    if !valid_package_name(&pkg.name) {
+        return Err(format!("'{}' isn't a valid package name, pass --name like my-tool", pkg.name).into());
+    }
+    if !valid_version(&pkg.version) {
+        return Err(format!("'{}' isn't a valid version, pass --version like 1.2.0", pkg.version).into());
+    }
+
+    if !PAYLOAD_DIRS.iter().any(|dir| Path::new(&format!("{}/{}", source_dir, dir)).is_dir()) {
+        return Err("nothing to package, need a bin/, lib/ or share/ directory".into());
+    }
+    if pkg.arch.is_empty() && ["bin", "lib"].iter().any(|dir| Path::new(&format!("{}/{}", source_dir, dir)).is_dir()) {
+        pkg.arch = host_arch().to_string();
+    }
+
+    let build_dir = format!("{}/pls-create-{}", tmp_dir(), process::id());
+    let _ = fs::remove_dir_all(&build_dir);
+    copy_tree(source_dir, &build_dir).map_err(|e| format!("couldn't stage {}: {}", source_dir, e))?;
+
+    let package_file = pack_build_dir(&build_dir, &pkg, output_dir.unwrap_or("."))?;
+
+    say!("{} {} v{} packed into {}", green("got ya!"), pkg.name, pkg.version, package_file);
+    brief!("built {} {} {}", pkg.name, pkg.version, package_file);
+    Ok(())
+}
+
+pub fn cmd_add_bin(
+    binary_path: &str,
+    name: Option<&str>,
+    version: Option<&str>,
+    output_dir: Option<&str>,
+    install: bool,
+) -> Result<(), Failure> {
+    let binary = Path::new(binary_path);
+    if !binary.is_file() {
+        return Err(format!("{} is not a file", binary_path).into());
+    }
+
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => binary
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .ok_or("couldn't figure out a name, pass --name")?,
+    };
+    let pkg = PackageInfo {
+        name,
+        version: version.unwrap_or("0.1.0").to_string(),
+        arch: host_arch().to_string(),
+        ..Default::default()
+    };
+    if !valid_package_name(&pkg.name) {
+        return Err(format!("'{}' isn't a valid package name, pass --name like my-tool", pkg.name).into());
+    }
+    if !valid_version(&pkg.version) {
+        return Err(format!("'{}' isn't a valid version, pass --version like 1.2.0", pkg.version).into());
+    }
+
+    let build_dir = format!("{}/pls-add-bin-{}", tmp_dir(), process::id());
+    let _ = fs::remove_dir_all(&build_dir);
+    if let Err(e) = stage_binary(binary_path, &format!("{}/bin/{}", build_dir, pkg.name)) {
+        let _ = fs::remove_dir_all(&build_dir);
+        return Err(e.into());
+    }
+
+    let package_file = pack_build_dir(&build_dir, &pkg, output_dir.unwrap_or(packages_dir()))?;
+
+    say!("{} {} v{} is ready", green("got ya twin!"), bold(&pkg.name), pkg.version);
+    say!("share it: {}", package_file);
+    brief!("built {} {} {}", pkg.name, pkg.version, package_file);
+
+    if install {
+        say!();
+        let changes = install_files(&[package_file], &[Some(InstallReason::Explicit)], false)?;
+        post_transaction("install", &changes);
+    }
+    Ok(())
+}
+
+fn stage_binary(binary_path: &str, dest: &str) -> Result<(), &'static str> {
+    if let Some(parent) = Path::new(dest).parent() {
+        fs::create_dir_all(parent).map_err(|_| "couldn't create build directory")?;
+    }
+    fs::copy(binary_path, dest).map_err(|_| "couldn't copy binary")?;
+    let mut perms = fs::metadata(dest).map_err(|_| "couldn't read binary permissions")?.permissions();
+    if perms.mode() & 0o111 == 0 {
+        say!("{} wasn't executable, marking it +x in the package", binary_path);
+        perms.set_mode(perms.mode() | 0o755);
+        fs::set_permissions(dest, perms).map_err(|_| "couldn't make binary executable")?;
+    }
+    Ok(())
+}
+
+fn bundle_files(build_dir: &str, under: &str, files: &[(String, String)], what: &str) -> Result<(), String> {
+    for (src, dest) in files {
+        let target = format!("{}/{}/{}", build_dir, under, dest);
+        if let Some(parent) = Path::new(&target).parent() {
+            fs::create_dir_all(parent).map_err(|_| format!("couldn't create {}", parent.display()))?;
+        }
+        fs::copy(src, &target).map_err(|_| format!("couldn't copy {} {}", what, src))?;
+        say!("bundling {} {}", what, dest);
+    }
+    Ok(())
+}
+
+fn pack_build_dir(build_dir: &str, pkg: &PackageInfo, output_path: &str) -> Result<String, String> {
+    let result = pack_staged(build_dir, pkg, output_path);
+    let _ = fs::remove_dir_all(build_dir);
+    result
+}
+
+fn pack_staged(build_dir: &str, pkg: &PackageInfo, output_path: &str) -> Result<String, String> {
+    let lib_dir = format!("{}/lib", build_dir);
+    let mut provides = pkg.provides.clone();
+    for rel in walk_files(&lib_dir) {
+        let path = Path::new(&lib_dir).join(&rel);
+        if fs::read_link(&path).is_err()
+            && let Some(soname) = soname(&path)
+            && !provides.contains(&soname)
+        {
+            say!("{} provides {}", pkg.name, soname);
+            provides.push(soname);
+        }
+    }
+    let pkg = &PackageInfo { provides, ..pkg.clone() };
+    let perms = fakeroot::take(build_dir)?;
+
+    fs::write(format!("{}/info", build_dir), pkg.to_info())
+        .map_err(|_| "couldn't write info file")?;
+
+    fs::create_dir_all(output_path)
+        .map_err(|_| "couldn't create output directory (need sudo?)")?;
+
+    let package_file = format!("{}/{}", output_path, package_file_name(&pkg.name, &pkg.version, &pkg.arch));
+    create_package(build_dir, &package_file, &perms).map_err(|e| format!("couldn't create package: {}", e))?;
+
+    Ok(package_file)
+}
+
+fn read_bundles_toml(path: &Path) -> Result<Option<HashMap<String, Vec<String>>>, String> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Ok(None);
+    };
+    let mut bundles = HashMap::new();
+    let mut lines = content.lines().map(str::trim).enumerate();
+    while let Some((i, line)) = lines.next() {
+        if line.is_empty() || line.starts_with('#') || line.starts_with('[') {
+            continue;
+        }
+        let Some((name, value)) = line.split_once('=') else {
+            return Err(format!("bundles.toml line {}: expected name = [\"pkg\", ...]", i + 1));
+        };
+        let mut value = value.trim().to_string();
+        while value.starts_with('[') && !value.contains(']') {
+            let Some((_, more)) = lines.next() else {
+                return Err(format!("bundles.toml line {}: the list for {} never closes", i + 1, name.trim()));
+            };
+            value.push_str(more.split('#').next().unwrap_or(""));
+        }
+        let name = name.trim().trim_matches('"').to_string();
+        if bundles.insert(name.clone(), parse_list(&value)).is_some() {
+            return Err(format!("bundles.toml defines {} twice", name));
+        }
+    }
+    Ok(Some(bundles))
+}
+
+fn read_deprecations(path: &Path) -> Result<Option<HashMap<String, Deprecation>>, String> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Ok(None);
+    };
+    let mut deprecations: HashMap<String, Deprecation> = HashMap::new();
+    let mut current: Option<String> = None;
+    for (i, line) in content.lines().map(str::trim).enumerate() {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+            let name = name.trim().trim_matches('"').to_string();
+            if deprecations.insert(name.clone(), Deprecation::default()).is_some() {
+                return Err(format!("deprecated.toml lists {} twice", name));
+            }
+            current = Some(name);
+            continue;
+        }
+        let (Some(name), Some((key, value))) = (&current, line.split_once('=')) else {
+            return Err(format!("deprecated.toml line {}: expected [pkg] and then reason/replaced_by/eol = \"...\"", i + 1));
+        };
+        let value = value.trim().trim_matches('"').to_string();
+        let deprecation = deprecations.entry(name.clone()).or_default();
+        match key.trim() {
+            "reason" => deprecation.reason = value,
+            "replaced_by" => deprecation.replaced_by = value,
+            "eol" => deprecation.eol = value,
+            other => return Err(format!("deprecated.toml line {}: no idea what '{}' is, try reason, replaced_by or eol", i + 1, other)),
+        }
+    }
+    Ok(Some(deprecations))
+}
+
+fn check_deprecations(deprecations: &HashMap<String, Deprecation>, packages: &HashMap<String, PackageMeta>) -> Result<(), String> {
+    let mut problems = Vec::new();
+    for (name, deprecation) in deprecations {
+        if !packages.contains_key(name) {
+            problems.push(format!("{} is deprecated but packages/ doesn't have it", name));
+        }
+        if !deprecation.replaced_by.is_empty() && !packages.contains_key(&deprecation.replaced_by) {
+            problems.push(format!("{} is replaced by {} but packages/ doesn't have it", name, deprecation.replaced_by));
+        }
+        if deprecation.replaced_by == *name {
+            problems.push(format!("{} can't replace itself", name));
+        }
+        if !deprecation.eol.is_empty() && chrono::NaiveDate::parse_from_str(&deprecation.eol, "%Y-%m-%d").is_err() {
+            problems.push(format!("eol for {} should look like 2027-01-31, not '{}'", name, deprecation.eol));
+        }
+    }
+    if problems.is_empty() {
+        return Ok(());
+    }
+    problems.sort();
+    Err(format!("deprecated.toml has problems:\n  {}", problems.join("\n  ")))
+}
+
+fn check_bundles(bundles: &HashMap<String, Vec<String>>, packages: &HashMap<String, PackageMeta>) -> Result<(), String> {
+    let mut problems = Vec::new();
+    for (bundle, members) in bundles {
+        if members.is_empty() {
+            problems.push(format!("{} is empty", bundle));
+        }
+        for member in members {
+            match member.strip_prefix('@') {
+                Some(inner) if !bundles.contains_key(inner) => problems.push(format!("{} includes @{} but there's no such bundle", bundle, inner)),
+                Some(_) => {}
+                None if !packages.contains_key(split_dep(member).0) => {
+                    problems.push(format!("{} wants {} but packages/ doesn't have it", bundle, split_dep(member).0))
+                }
+                None => {}
+            }
+        }
+    }
+    if problems.is_empty() {
+        let index = RepoIndex { version: 1, updated: String::new(), packages: HashMap::new(), bundles: bundles.clone(), by_repo: HashMap::new() };
+        problems.extend(bundles.keys().filter_map(|bundle| index.expand_bundle(bundle).err()));
+    }
+    if problems.is_empty() {
+        return Ok(());
+    }
+    problems.sort();
+    problems.dedup();
+    Err(format!("bundles.toml has {} problem(s), index not touched:\n  {}", problems.len(), problems.join("\n  ")))
+}
+
+pub async fn cmd_repo_update(changes_file: Option<&str>, webhook: Option<&str>) -> Result<(), Failure> {
+    let current_dir = env::current_dir().map_err(|_| "couldn't get current directory")?;
+
+    let packages_dir = current_dir.join("packages");
+    let index_path = current_dir.join("index.json");
+
+    if !packages_dir.exists() {
+        return Err("no packages/ folder here, are you in a repo?".into());
+    }
+
+    say!("scanning packages/...");
+
+    let mut packages: HashMap<String, PackageMeta> = HashMap::new();
+    let mut found: HashMap<String, Vec<(PackageInfo, ArchBuild, String, Provenance)>> = HashMap::new();
+    let temp_dir = format!("{}/pls-repo-scan-{}", tmp_dir(), process::id());
+
+    let entries = fs::read_dir(&packages_dir)
+        .map_err(|e| format!("couldn't read packages/: {}", e))?;
+    let mut problems: Vec<String> = Vec::new();
+    let mut file_lists: HashMap<String, Vec<String>> = HashMap::new();
+
+    let files: Vec<String> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|e| e.to_str()) == Some("pls"))
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    let hashes = hash_files(&files);
+
+    for (path_str, sha256) in files.iter().zip(hashes) {
+        let path = Path::new(path_str);
+        let file = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+
+        let size = fs::metadata(path)
+            .map_err(|e| format!("couldn't get size of {}: {}", path_str, e))?
+            .len();
+        if size == 0 {
+            problems.push(format!("{}: empty file", file));
+            continue;
+        }
+
+        let sha256 = sha256.map_err(|e| format!("couldn't hash {}: {}", path_str, e))?;
+        let b3sum = calculate_b3sum(path_str).map_err(|e| format!("couldn't hash {}: {}", path_str, e))?;
+
+        let _ = remove_tree(&temp_dir);
+        if let Err(e) = extract_package(path_str, &temp_dir) {
+            problems.push(format!("{}: not a valid package ({})", file, e));
+            continue;
+        }
+
+        let Ok(pkg) = PackageInfo::from_file(&format!("{}/info", &temp_dir)) else {
+            problems.push(format!("{}: no info file inside", file));
+            continue;
+        };
+        if !valid_package_name(&pkg.name) {
+            problems.push(format!("{}: bad name '{}', stick to a-z, 0-9, '.', '_', '+' and '-'", file, pkg.name));
+            continue;
+        }
+        if !valid_version(&pkg.version) {
+            problems.push(format!("{}: bad version '{}' for {}, needs to start with a digit", file, pkg.version, pkg.name));
+            continue;
+        }
+
+        let arch = match parse_package_file_name(&file) {
+            Some((_, _, arch)) if pkg.arch.is_empty() => arch,
+            _ => pkg.arch.clone(),
+        };
+        say!("  found {} v{} {}({} bytes)", pkg.name, pkg.version, if arch.is_empty() { String::new() } else { format!("for {} ", arch) }, size);
+
+        let changelog = read_package_file(path_str, "changelog")
+            .map(|c| changelog::latest_entries(&c, 3))
+            .unwrap_or_default();
+        let stamp = read_package_file(path_str, provenance::FILE).map(|p| provenance::parse(&p)).unwrap_or_default();
+        let contents: Vec<String> = PAYLOAD_DIRS
+            .iter()
+            .flat_map(|dir| walk_files(&format!("{}/{}", temp_dir, dir)).into_iter().map(move |rel| format!("{}/{}", dir, rel)))
+            .collect();
+        let builds = found.entry(pkg.name.clone()).or_default();
+        if let Some((_, dup, _, _)) = builds.iter().find(|(p, b, _, _)| p.version == pkg.version && b.arch == arch) {
+            problems.push(format!(
+                "{}: {} v{} {}is already in {}",
+                file,
+                pkg.name,
+                pkg.version,
+                if arch.is_empty() { String::new() } else { format!("for {} ", arch) },
+                dup.file
+            ));
+            continue;
+        }
+        let installed_size = contents
+            .iter()
+            .filter_map(|rel| fs::symlink_metadata(format!("{}/{}", temp_dir, rel)).ok())
+            .map(|meta| meta.len())
+            .sum();
+        file_lists.insert(file.clone(), contents);
+        builds.push((pkg, ArchBuild { arch, file, size, installed_size, sha256, b3sum }, changelog, stamp));
+    }
+
+    let _ = remove_tree(&temp_dir);
+
+    if !problems.is_empty() {
+        problems.sort();
+        return Err(format!(
+            "packages/ has {} problem(s), index not touched:\n  {}",
+            problems.len(),
+            problems.join("\n  ")
+        ).into());
+    }
+
+    for (name, mut builds) in found {
+        let latest = builds
+            .iter()
+            .map(|(pkg, _, _, _)| pkg.version.clone())
+            .max_by(|a, b| compare_versions(a, b))
+            .unwrap_or_default();
+        builds.retain(|(pkg, _, _, _)| pkg.version == latest);
+        builds.sort_by(|a, b| a.1.arch.cmp(&b.1.arch));
+
+        let preferred = builds.iter().position(|(_, b, _, _)| b.arch == host_arch()).unwrap_or(0);
+        let all: Vec<ArchBuild> = builds.iter().map(|(_, b, _, _)| b.clone()).collect();
+        let (pkg, build, changelog, stamp) = builds.swap_remove(preferred);
+
+        packages.insert(name.clone(), PackageMeta {
+            version: pkg.version,
+            size: build.size,
+            installed_size: build.installed_size,
+            sha256: build.sha256,
+            b3sum: build.b3sum,
+            deps: pkg.depend,
+            optional: pkg.optional,
+            desc: if pkg.desc.is_empty() { format!("{} package", name) } else { pkg.desc },
+            keywords: pkg.keywords,
+            maintainer: pkg.maintainer,
+            homepage: pkg.homepage,
+            changelog,
+            arch: build.arch,
+            file: build.file,
+            builds: if all.len() > 1 { all } else { Vec::new() },
+            provenance: stamp,
+            split_from: pkg.split_from,
+            provides: pkg.provides,
+            deprecated: None,
+            repo: String::new(),
+            repo_url: String::new(),
+        });
+    }
+
+    if packages.is_empty() && !index_path.exists() {
+        say!("no packages found in packages/");
+        return Ok(());
+    }
+
+    let previous: Option<RepoIndex> = fs::read_to_string(&index_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok());
+    let old_packages = previous.as_ref().map(|idx| idx.packages.clone()).unwrap_or_default();
+    let existing_bundles = previous.map(|idx| idx.bundles).unwrap_or_default();
+    let bundles = match read_bundles_toml(&current_dir.join("bundles.toml"))? {
+        Some(bundles) => {
+            check_bundles(&bundles, &packages)?;
+            say!("compiled {} bundle(s) from bundles.toml", bundles.len());
+            bundles
+        }
+        None => existing_bundles,
+    };
+    match read_deprecations(&current_dir.join("deprecated.toml"))? {
+        Some(deprecations) => {
+            check_deprecations(&deprecations, &packages)?;
+            say!("marked {} package(s) deprecated from deprecated.toml", deprecations.len());
+            for (name, deprecation) in deprecations {
+                if let Some(meta) = packages.get_mut(&name) {
+                    meta.deprecated = Some(deprecation);
+                }
+            }
+        }
+        None => {
+            for (name, meta) in packages.iter_mut() {
+                meta.deprecated = old_packages.get(name).and_then(|old| old.deprecated.clone());
+            }
+        }
+    }
+
+    let mut added: Vec<&String> = packages.keys().filter(|name| !old_packages.contains_key(*name)).collect();
+    let mut updated: Vec<(&String, &String, &String)> = packages
+        .iter()
+        .filter_map(|(name, meta)| {
+            let old = old_packages.get(name)?;
+            (old.version != meta.version).then_some((name, &old.version, &meta.version))
+        })
+        .collect();
+    let mut removed: Vec<&String> = old_packages.keys().filter(|name| !packages.contains_key(*name)).collect();
+    added.sort();
+    updated.sort();
+    removed.sort();
+
+    if !added.is_empty() || !updated.is_empty() || !removed.is_empty() {
+        say!();
+    }
+    for name in &added {
+        say!("  {} {} v{}", green("+"), name, packages[*name].version);
+    }
+    for (name, from, to) in &updated {
+        say!("  {} {} {} -> {}", yellow("~"), name, from, to);
+    }
+    for name in &removed {
+        say!("  {} {} (dropped, its .pls is gone)", red("-"), name);
+        for (bundle, members) in &bundles {
+            if members.contains(name) {
+                say!("    {} bundle '{}' still lists it", yellow("heads up,"), bundle);
+            }
+        }
+    }
+    say!("{} added, {} updated, {} removed", added.len(), updated.len(), removed.len());
+
+    let mut changes = serde_json::json!({
+        "added": added,
+        "updated": updated
+            .iter()
+            .map(|(name, from, to)| serde_json::json!({ "name": name, "from": from, "to": to }))
+            .collect::<Vec<_>>(),
+        "removed": removed,
+    });
+    if let Some(path) = changes_file {
+        let json = serde_json::to_string_pretty(&changes).map_err(|e| format!("couldn't serialize changes: {}", e))?;
+        fs::write(path, json).map_err(|e| format!("couldn't write {}: {}", path, e))?;
+    }
+
+    let mut announcement: Vec<String> = added
+        .iter()
+        .map(|name| format!("new: {} v{}", name, packages[*name].version))
+        .collect();
+    announcement.extend(updated.iter().map(|(name, from, to)| format!("updated: {} {} -> {}", name, from, to)));
+    let announcement = announcement.join("\n");
+
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+
+    let index = RepoIndex {
+        version: 1,
+        updated: today,
+        packages,
+        bundles,
+        by_repo: HashMap::new(),
+    };
+
+    write_index(&index_path.to_string_lossy(), &index)?;
+    let files_db: BTreeMap<&String, Vec<String>> = index
+        .packages
+        .iter()
+        .map(|(name, meta)| (name, file_lists.remove(&meta.file_name(name)).unwrap_or_default()))
+        .collect();
+    write_files_db(&current_dir.join(FILES_DB).to_string_lossy(), &files_db)?;
+    say!("{} index.json updated with {} package(s)", green("done!"), index.packages.len());
+    brief!("indexed {} package(s)", index.packages.len());
+
+    let webhook = webhook.map(|url| url.to_string()).or(Config::load().repo_webhook);
+    if let Some(url) = webhook
+        && !announcement.is_empty()
+    {
+        changes["content"] = serde_json::json!(announcement);
+        changes["text"] = serde_json::json!(announcement);
+        match post_json(&url, &changes).await {
+            Ok(()) => say!("announced the changes to the webhook"),
+            Err(e) => say!("{} webhook didn't take it: {}", yellow("heads up,"), e),
+        }
+    }
+
+    Ok(())
+}
+
+const INDEX_FILES: [&str; 5] = ["index.json", "index.json.zst", "index.bin", FILES_DB, "advisories.json"];
+
+fn run_tool(tool: &str, args: &[String]) -> Result<(), Failure> {
+    let status = Command::new(tool)
+        .args(args)
+        .status()
+        .map_err(|e| format!("couldn't run {}, is it installed? ({})", tool, e))?;
+    if !status.success() {
+        return Err(fail(NETWORK, format!("{} failed ({})", tool, status)));
+    }
+    Ok(())
+}
+
+pub async fn cmd_repo_publish(target: &str) -> Result<(), Failure> {
+    cmd_repo_update(None, None).await?;
+    let target = target.trim_end_matches('/');
+    let index_files: Vec<String> = INDEX_FILES
+        .iter()
+        .filter(|file| Path::new(file).exists())
+        .map(|file| file.to_string())
+        .collect();
+
+    say!("\npublishing to {}...", target);
+    if target.starts_with("http://") || target.starts_with("https://") {
+        let local: RepoIndex = fs::read_to_string("index.json")
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .ok_or("index.json is broken, can't publish")?;
+        let remote: Vec<(String, String)> = match fetch_index_from(target).await {
+            Ok(index) => index
+                .packages
+                .iter()
+                .flat_map(|(name, meta)| meta.artifacts(name))
+                .map(|build| (build.file, build.sha256))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+
+        let mut uploaded = 0;
+        for (name, meta) in &local.packages {
+            for build in meta.artifacts(name) {
+                if remote.iter().any(|(file, sha)| *file == build.file && *sha == build.sha256) {
+                    continue;
+                }
+                say!("  {}", build.file);
+                upload_file(&format!("{}/packages/{}", target, build.file), &format!("packages/{}", build.file)).await?;
+                for kind in SIGNATURES {
+                    let sig = format!("packages/{}.{}", build.file, kind);
+                    if Path::new(&sig).exists() {
+                        upload_file(&format!("{}/{}", target, sig), &sig).await?;
+                    }
+                }
+                uploaded += 1;
+            }
+        }
+        for file in &index_files {
+            upload_file(&format!("{}/{}", target, file), file).await?;
+        }
+        say!("{} {} package file(s) uploaded, index is live", green("done!"), uploaded);
+        brief!("published {} package file(s) to {}", uploaded, target);
+        return Ok(());
+    }
+
+    if target.starts_with("oci://") {
+        let local: RepoIndex = fs::read_to_string("index.json")
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .ok_or("index.json is broken, can't publish")?;
+        let files: Vec<(String, String)> = local
+            .packages
+            .iter()
+            .flat_map(|(name, meta)| meta.artifacts(name))
+            .map(|build| (build.file.clone(), format!("packages/{}", build.file)))
+            .collect();
+        let pushed = oci::publish(target, &files).await?;
+        say!("{} {} package(s) pushed, install with pls install {}:<name>-<version>", green("done!"), pushed, target);
+        brief!("published {} package file(s) to {}", pushed, target);
+        return Ok(());
+    }
+
+    if let Some(bucket) = target.strip_prefix("s3://") {
+        let dest = format!("s3://{}", bucket);
+        run_tool("aws", &["s3".into(), "sync".into(), "packages/".into(), format!("{}/packages/", dest)])?;
+        for file in &index_files {
+            run_tool("aws", &["s3".into(), "cp".into(), file.clone(), format!("{}/{}", dest, file)])?;
+        }
+    } else {
+        let dest = target.strip_prefix("rsync+ssh://").unwrap_or(target);
+        run_tool("rsync", &["-az".into(), "packages/".into(), format!("{}/packages/", dest)])?;
+        let mut args = vec!["-az".to_string()];
+        args.extend(index_files.iter().cloned());
+        args.push(format!("{}/", dest));
+        run_tool("rsync", &args)?;
+    }
+    say!("{} packages and index pushed to {}", green("done!"), target);
+    brief!("published {}", target);
+    Ok(())
+}
+
+fn age_of(path: &str) -> String {
+    let days = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.elapsed().ok())
+        .map(|e| e.as_secs() / 86400);
+    match days {
+        Some(0) => "today".to_string(),
+        Some(days) => format!("{}d old", days),
+        None => "file missing".to_string(),
+    }
+}
+
+async fn load_index(source: &str) -> Result<RepoIndex, Failure> {
+    if Path::new(source).is_file() {
+        let content = fs::read_to_string(source).map_err(|e| format!("couldn't read {}: {}", source, e))?;
+        return serde_json::from_str(&content).map_err(|e| format!("{} isn't a pls index: {}", source, e).into());
+    }
+    if source.contains("://") {
+        return fetch_index_from(source.trim_end_matches('/')).await;
+    }
+    Err(fail(NOT_FOUND, format!("{} isn't there, give me an index.json or a repo url", source)))
+}
+
+fn size_delta(from: u64, to: u64) -> String {
+    match to.cmp(&from) {
+        Ordering::Greater => format!("+{}", format_size(to - from)),
+        Ordering::Less => format!("-{}", format_size(from - to)),
+        Ordering::Equal => "same size".to_string(),
+    }
+}
+
+pub async fn cmd_repo_diff(sources: &[String]) -> Result<(), Failure> {
+    let remote = Config::load().repo;
+    let (old_source, new_source) = match sources {
+        [] => (remote.as_str(), "index.json"),
+        [new] => (remote.as_str(), new.as_str()),
+        [old, new, ..] => (old.as_str(), new.as_str()),
+    };
+    let old = load_index(old_source).await?;
+    let new = load_index(new_source).await?;
+    say!("{} -> {}", old_source, new_source);
+
+    let mut names: Vec<&String> = old.packages.keys().chain(new.packages.keys()).collect();
+    names.sort();
+    names.dedup();
+    let (mut added, mut updated, mut rebuilt, mut removed) = (0, 0, 0, 0);
+    for name in names {
+        match (old.packages.get(name), new.packages.get(name)) {
+            (None, Some(meta)) => {
+                added += 1;
+                println!("  {} {} {} {}", green("+"), name, meta.version, dim(format_size(meta.size)));
+            }
+            (Some(meta), None) => {
+                removed += 1;
+                println!("  {} {} {} {}", red("-"), name, meta.version, dim(format!("-{}", format_size(meta.size))));
+            }
+            (Some(from), Some(to)) if from.version != to.version => {
+                updated += 1;
+                let arrow = if compare_versions(&from.version, &to.version) == Ordering::Greater { red("downgrade") } else { "->".to_string() };
+                println!("  {} {} {} {} {} {}", yellow("~"), name, from.version, arrow, to.version, dim(size_delta(from.size, to.size)));
+            }
+            (Some(from), Some(to)) if from.sha256 != to.sha256 => {
+                rebuilt += 1;
+                println!("  {} {} {} rebuilt {}", yellow("!"), name, to.version, dim(size_delta(from.size, to.size)));
+            }
+            _ => {}
+        }
+    }
+
+    let total = |index: &RepoIndex| index.packages.values().map(|meta| meta.size).sum::<u64>();
+    let mut summary = format!("{} added, {} updated, {} removed", added, updated, removed);
+    if rebuilt > 0 {
+        summary.push_str(&format!(", {} rebuilt without a version bump", rebuilt));
+    }
+    if added + updated + rebuilt + removed == 0 {
+        say!("nothing changed");
+    } else {
+        say!("{}, {}", summary, size_delta(total(&old), total(&new)));
+    }
+    Ok(())
+}
+
+pub fn cmd_repo_stats() -> Result<(), Failure> {
+    let index: RepoIndex = fs::read_to_string("index.json")
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .ok_or("no index.json here, run 'pls repo update' in a repo first")?;
+
+    let mut names: Vec<&String> = index.packages.keys().collect();
+    names.sort_by_key(|name| std::cmp::Reverse(index.packages[*name].size));
+    let total: u64 = index
+        .packages
+        .iter()
+        .flat_map(|(name, meta)| meta.artifacts(name))
+        .map(|build| build.size)
+        .sum();
+
+    println!("{} package(s), {} total (updated {})", names.len(), format_size(total), index.updated);
+    println!();
+    println!("size per package:");
+    for name in &names {
+        let meta = &index.packages[*name];
+        let age = age_of(&format!("packages/{}", meta.file_name(name)));
+        println!("  {:<24} v{:<12} {:>10}  {}", name, meta.version, format_size(meta.size), age);
+    }
+
+    let no_desc: Vec<&str> = names
+        .iter()
+        .filter(|name| {
+            let desc = index.packages[**name].desc.trim();
+            desc.is_empty() || desc == format!("{} package", name)
+        })
+        .map(|name| name.as_str())
+        .collect();
+    if !no_desc.is_empty() {
+        println!();
+        println!("{} without a real description: {}", no_desc.len(), no_desc.join(", "));
+    }
+
+    let mut maintainers: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for name in &names {
+        maintainers.entry(index.packages[*name].maintainer.trim()).or_default().push(name.as_str());
+    }
+    let orphaned = maintainers.remove("").unwrap_or_default();
+    if !maintainers.is_empty() {
+        println!();
+        println!("maintainers:");
+        for (maintainer, packages) in &maintainers {
+            println!("  {:<32} {}", maintainer, packages.join(", "));
+        }
+    }
+    if !orphaned.is_empty() {
+        println!();
+        println!("{} orphaned, nobody maintains: {}", orphaned.len(), orphaned.join(", "));
+    }
+
+    let unbundled: Vec<&str> = names
+        .iter()
+        .filter(|name| !index.bundles.values().flatten().any(|member| member == **name))
+        .map(|name| name.as_str())
+        .collect();
+    if !unbundled.is_empty() {
+        println!();
+        println!("{} not in any bundle: {}", unbundled.len(), unbundled.join(", "));
+    }
+    Ok(())
+}
+
+pub async fn cmd_repo_gc(keep: usize, dry_run: bool) -> Result<(), Failure> {
+    if keep == 0 {
+        return Err("--keep needs to be at least 1, otherwise there's nothing left to install".into());
+    }
+    let packages_dir = Path::new("packages");
+    if !packages_dir.exists() {
+        return Err("no packages/ folder here, are you in a repo?".into());
+    }
+
+    let mut versions: HashMap<String, Vec<(String, String)>> = HashMap::new();
+    for entry in fs::read_dir(packages_dir).map_err(|e| format!("couldn't read packages/: {}", e))?.flatten() {
+        let file = entry.file_name().to_string_lossy().to_string();
+        let embedded = read_package_info(&entry.path().to_string_lossy()).ok().map(|info| (info.name, info.version));
+        if let Some((name, version)) = embedded.or_else(|| parse_package_file_name(&file).map(|(name, version, _)| (name, version))) {
+            versions.entry(name).or_default().push((version, file));
+        }
+    }
+
+    let index: Option<RepoIndex> = fs::read_to_string("index.json")
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok());
+    let mut pins: Vec<String> = Vec::new();
+    if let Some(index) = &index {
+        pins.extend(index.bundles.values().flatten().cloned());
+        pins.extend(index.packages.values().flat_map(|meta| meta.deps.iter().cloned()));
+    }
+
+    let mut doomed: Vec<String> = Vec::new();
+    let mut freed = 0;
+    for (name, files) in &mut versions {
+        let mut distinct: Vec<String> = files.iter().map(|(v, _)| v.clone()).collect();
+        distinct.sort_by(|a, b| compare_versions(b, a));
+        distinct.dedup();
+
+        let mut kept: Vec<&String> = distinct.iter().take(keep).collect();
+        for pin in &pins {
+            let (pinned, range) = split_dep(pin);
+            if pinned != name || range.is_empty() {
+                continue;
+            }
+            if let Some(version) = distinct.iter().find(|v| version_in_range(v, range))
+                && !kept.contains(&version)
+            {
+                say!("  keeping {} {} (wanted by '{}')", name, version, pin);
+                kept.push(version);
+            }
+        }
+
+        for (version, file) in files.iter() {
+            if !kept.contains(&version) {
+                freed += fs::metadata(packages_dir.join(file)).map(|m| m.len()).unwrap_or(0);
+                doomed.push(file.clone());
+            }
+        }
+    }
+
+    if doomed.is_empty() {
+        say!("nothing to clean, only the newest {} version(s) and pinned ones are here", keep);
+        return Ok(());
+    }
+
+    doomed.sort();
+    for file in &doomed {
+        say!("  {} {}", if dry_run { dim("would delete") } else { red("delete") }, file);
+    }
+    if dry_run {
+        say!("{} file(s), {} would be freed", doomed.len(), format_size(freed));
+        return Ok(());
+    }
+
+    for file in &doomed {
+        fs::remove_file(packages_dir.join(file)).map_err(|e| format!("couldn't delete {}: {}", file, e))?;
+        for kind in SIGNATURES {
+            let _ = fs::remove_file(packages_dir.join(format!("{}.{}", file, kind)));
+        }
+    }
+    say!("{} deleted {} file(s), freed {}", green("done!"), doomed.len(), format_size(freed));
+    brief!("deleted {} file(s)", doomed.len());
+
+    cmd_repo_update(None, None).await
+}
+
+pub async fn cmd_provides(query: &str) -> Result<(), Failure> {
+    let config = Config::load();
+    let wanted = query.strip_prefix(config.prefix.as_str()).unwrap_or(query).trim_start_matches('/');
+    let fits = |file: &str| {
+        if wanted.contains('/') {
+            file == wanted || file.ends_with(&format!("/{}", wanted))
+        } else {
+            file.rsplit('/').next() == Some(wanted)
+        }
+    };
+
+    let mut hits: Vec<(String, String, String)> = Vec::new();
+    for (repo, url) in config.repos() {
+        let files_db = match fetch_files_db(&url).await {
+            Ok(files_db) => files_db,
+            Err(e) => {
+                say!("{} couldn't get the file list of repo '{}': {}", yellow("heads up,"), repo, e);
+                continue;
+            }
+        };
+        for (name, files) in files_db {
+            for file in files.iter().filter(|file| fits(file)) {
+                hits.push((name.clone(), repo.clone(), file.clone()));
+            }
+        }
+    }
+
+    if hits.is_empty() {
+        return Err(fail(NOT_FOUND, format!("nothing in the repo provides {}", query)));
+    }
+    hits.sort();
+    for (name, repo, file) in &hits {
+        let installed = if is_installed(name) { format!(" {}", green("[installed]")) } else { String::new() };
+        say!("{} {}: {}{}", bold(name), dim(format!("({})", repo)), file, installed);
+        brief!("{}", name);
+    }
+    Ok(())
+}
+
+pub async fn cmd_update(patterns: &[String], exclude: &[String], allow_major: bool, force: bool) -> Result<(), Failure> {
+    if !Path::new(db_dir()).exists() {
+        say!("nothing installed yet, nothing to update");
+        return Ok(());
+    }
+
+    let mut installed: Vec<(String, String)> = installed_packages()
+        .into_iter()
+        .map(|pkg| (pkg.name, pkg.version))
+        .collect();
+
+    if installed.is_empty() {
+        say!("nothing installed yet");
+        return Ok(());
+    }
+
+    if !patterns.is_empty() {
+        for pattern in patterns {
+            if !installed.iter().any(|(name, _)| glob_match(pattern, name)) {
+                say!("nothing installed matches '{}'", pattern);
+            }
+        }
+        installed.retain(|(name, _)| patterns.iter().any(|pattern| glob_match(pattern, name)));
+        if installed.is_empty() {
+            return Ok(());
+        }
+    }
+
+    say!("checking for updates...");
+
+    let mut index = fetch_index().await?;
+    let config = Config::load();
+    for (name, _) in &installed {
+        if config.pin_for(name).is_none()
+            && let Some(repo) = installed_origin(name)
+        {
+            index.pin(&format!("{}/{}", repo, name));
+        }
+    }
+
+    let advisories = fetch_advisories().await.ok().flatten().unwrap_or_default();
+    let mut to_update: Vec<String> = Vec::new();
+    let mut majors: Vec<String> = Vec::new();
+    let mut skipped = 0;
+    let mut end_of_life = 0;
+
+    for (name, local_version) in &installed {
+        if let Some(remote) = index.packages.get(name)
+            && remote.version != *local_version
+        {
+            if config.is_ignored(name) {
+                say!("  {} {} -> {} {}", name, local_version, remote.version, dim("(ignored)"));
+                skipped += 1;
+                continue;
+            }
+            if exclude.iter().any(|pattern| glob_match(pattern, name)) {
+                say!("  {} {} -> {} {}", name, local_version, remote.version, dim("(excluded)"));
+                skipped += 1;
+                continue;
+            }
+            if !force && remote.deprecated.as_ref().is_some_and(|deprecation| deprecation.is_eol()) {
+                say!("  {} {} -> {} {}", name, local_version, remote.version, red("(end of life)"));
+                end_of_life += 1;
+                skipped += 1;
+                continue;
+            }
+            let major = is_major_jump(local_version, &remote.version);
+            if major {
+                say!("  {} {} -> {} {}", bold(name), local_version, yellow(&remote.version), red("(major, may break things)"));
+                majors.push(name.clone());
+            } else {
+                say!("  {} {} -> {}", bold(name), local_version, yellow(&remote.version));
+            }
+            for adv in advisories.get(name).into_iter().flatten() {
+                if adv.affects(local_version) && !adv.affects(&remote.version) {
+                    say!("    {} fixes {}", if adv.is_serious() { red("!!!") } else { "   ".to_string() }, adv);
+                }
+            }
+            to_update.push(name.clone());
+        }
+    }
+
+    for (name, _) in &installed {
+        if let Some(deprecation) = index.packages.get(name).and_then(|meta| meta.deprecated.as_ref()) {
+            say!("  {} {}", yellow("heads up,"), deprecation.describe(name));
+        }
+    }
+    if end_of_life > 0 {
+        say!("{} end of life packages stay put, move to their replacement or pass --force to update them anyway", dim("tip:"));
+    }
+
+    if !majors.is_empty()
+        && !allow_major
+        && !confirm(&format!("{} crosses a major version, update anyway?", majors.join(", ")))
+    {
+        say!("{} leaving {} alone, pass --allow-major to take them", yellow("heads up,"), majors.join(", "));
+        to_update.retain(|name| !majors.contains(name));
+        skipped += majors.len();
+    }
+
+    let everything = installed_packages();
+    for name in split_siblings(&index, &to_update, &everything) {
+        if let (Some(local), Some(remote)) = (everything.iter().find(|pkg| pkg.name == name), index.packages.get(&name)) {
+            say!("  {} {} -> {} {}", bold(&name), local.version, yellow(&remote.version), dim("(split package, moves with the rest)"));
+        }
+        to_update.push(name);
+    }
+
+    if to_update.is_empty() {
+        if skipped > 0 {
+            say!("nothing else to update, {} skipped", skipped);
+        } else {
+            say!("{}", green("everything up to date!"));
+        }
+        return Ok(());
+    }
+
+    let mut new_deps: Vec<String> = Vec::new();
+    for name in closure(&index, &to_update)? {
+        if !to_update.contains(&name) && !is_installed(&name) && !config.is_ignored(&name) {
+            new_deps.push(name.clone());
+            to_update.push(name);
+        }
+    }
+
+    let deps_of = |name: &str| {
+        index
+            .packages
+            .get(name)
+            .map(|m| m.deps.iter().filter_map(|d| dep_provider(&index, d)).map(str::to_string).collect())
+            .unwrap_or_default()
+    };
+    let order = topo_order(&to_update, deps_of)?;
+
+    say!();
+    let plan = Plan::build(&index, &order, &new_deps);
+    plan.print();
+    if !force {
+        plan.check_space()?;
+    }
+    say!("downloading {} package(s)...", order.len());
+    let paths = resolve_all(&order, Some(&index)).await?;
+    say!("\nall downloads verified, updating...\n");
+
+    for (name, version) in &installed {
+        if order.contains(name)
+            && let Err(e) = keep_installed_copy(name, version)
+        {
+            say!("{} can't roll {} back if it breaks: {}", yellow("heads up,"), name, e);
+        }
+    }
+
+    let mut changes = Vec::new();
+    let mut failed: Vec<String> = Vec::new();
+    let mut rolled_back: Vec<String> = Vec::new();
+
+    for (pkg, path) in order.iter().zip(&paths) {
+        if let Some(dep) = deps_of(pkg).into_iter().find(|d| failed.contains(d)) {
+            say!("{} skipping {}, its dependency {} failed\n", red("!!!"), pkg, dep);
+            brief!("skipped {} (dependency {} failed)", pkg, dep);
+            failed.push(pkg.clone());
+            continue;
+        }
+
+        say!("{} updating {}...", bold(">>>"), pkg);
+        let reason = new_deps.contains(pkg).then_some(InstallReason::Dependency);
+        match install_files(std::slice::from_ref(path), &[reason], false) {
+            Ok(applied) => {
+                for change in &applied {
+                    record_origin(&change.name, index.packages.get(&change.name));
+                }
+                changes.extend(applied);
+            }
+            Err(e) => {
+                say!("{} failed to update {}: {}", red("!!!"), pkg, e);
+                brief!("failed {}: {}", pkg, e);
+                failed.push(pkg.clone());
+                if let Some((_, old_version)) = installed.iter().find(|(name, _)| name == pkg) {
+                    match install_files(&[cache_path(pkg, old_version)], &[None], true) {
+                        Ok(_) => {
+                            say!("{} rolled {} back to v{}", yellow(">>>"), pkg, old_version);
+                            brief!("rolled back {} {}", pkg, old_version);
+                            rolled_back.push(format!("{} v{}", pkg, old_version));
+                        }
+                        Err(e) => say!("{} couldn't roll {} back either: {}", red("!!!"), pkg, e),
+                    }
+                }
+            }
+        }
+        say!();
+    }
+    auto_clean(&config);
+    post_transaction("update", &changes);
+
+    if failed.is_empty() {
+        say!("{} {} package(s) updated", green("nice!"), changes.len());
+        return Ok(());
+    }
+
+    say!("{} {}", red("failed:"), failed.join(", "));
+    if !rolled_back.is_empty() {
+        say!("{} {}", yellow("rolled back:"), rolled_back.join(", "));
+    }
+    Err(fail(PARTIAL, format!("{} updated, {} failed", changes.len(), failed.len())))
+}
+
+pub fn cmd_rollback(name: &str) -> Result<(), Failure> {
+    let current = PackageInfo::from_file(&format!("{}/{}/info", db_dir(), name))
+        .map_err(|_| fail(NOT_FOUND, format!("'{}' isn't even installed bro", name)))?;
+
+    let previous = scan()
+        .into_iter()
+        .filter(|c| c.name == name && compare_versions(&c.version, &current.version) == Ordering::Less)
+        .max_by(|a, b| compare_versions(&a.version, &b.version))
+        .ok_or_else(|| fail(NOT_FOUND, format!("no older version of {} in the cache to go back to", name)))?;
+
+    if let Err(e) = keep_installed_copy(name, &current.version) {
+        say!("{} couldn't keep v{} around: {}", yellow("heads up,"), current.version, e);
+    }
+    say!("rolling {} back from v{} to v{}...", name, current.version, previous.version);
+    touch(&previous.path);
+    let changes = install_files(&[previous.path], &[None], false)?;
+    post_transaction("rollback", &changes);
+    Ok(())
+}
+
+fn keep_installed_copy(name: &str, version: &str) -> Result<(), Failure> {
+    let cached = cache_path(name, version);
+    let _lock = lock_blocking(&cached)?;
+    if Path::new(&cached).exists() {
+        touch(&cached);
+        return Ok(());
+    }
+
+    let build_dir = format!("{}/pls-keep-{}-{}", tmp_dir(), process::id(), name);
+    let _ = fs::remove_dir_all(&build_dir);
+    let install_dirs = Config::load().install_dirs();
+    for file in installed_files(name).iter().filter(|e| !e.ends_with('/')) {
+        let Some((src_dir, rel)) = install_dirs.iter().find_map(|(src_dir, dest_dir)| {
+            let rel = Path::new(file).strip_prefix(Path::new(ROOT).join(dest_dir.trim_start_matches('/'))).ok()?;
+            Some((src_dir, rel.to_path_buf()))
+        }) else {
+            continue;
+        };
+        let target = Path::new(&build_dir).join(src_dir).join(rel);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        if let Ok(link) = fs::read_link(file) {
+            std::os::unix::fs::symlink(link, &target).map_err(|e| format!("couldn't copy {}: {}", file, e))?;
+            continue;
+        }
+        fs::copy(file, &target).map_err(|e| format!("couldn't copy {}: {}", file, e))?;
+        let _ = copy_attributes(Path::new(file), &target);
+    }
+
+    let db_path = format!("{}/{}", db_dir(), name);
+    fs::create_dir_all(&build_dir).map_err(|e| e.to_string())?;
+    fs::copy(format!("{}/info", db_path), format!("{}/info", build_dir)).map_err(|e| e.to_string())?;
+    let _ = fs::copy(format!("{}/notes", db_path), format!("{}/notes", build_dir));
+    let _ = fs::copy(format!("{}/{}", db_path, provenance::FILE), format!("{}/{}", build_dir, provenance::FILE));
+
+    fs::create_dir_all(cache_dir()).map_err(|e| e.to_string())?;
+    let result = create_package(&build_dir, &cached, &[]).map_err(|e| Failure::from(e.to_string()));
+    let _ = fs::remove_dir_all(&build_dir);
+    result
+}
+
+struct Damage {
+    name: String,
+    version: String,
+    missing: Vec<String>,
+    modified: Vec<String>,
+    stored: Vec<String>,
+}
+
+fn check_installed(pkg: &PackageInfo) -> (Damage, bool) {
+    let sums: HashMap<String, String> = fs::read_to_string(format!("{}/{}/sums", db_dir(), pkg.name))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_once("  "))
+        .map(|(hash, file)| (file.to_string(), hash.to_string()))
+        .collect();
+    let files: Vec<String> = installed_files(&pkg.name).into_iter().filter(|entry| !entry.ends_with('/')).collect();
+    let (present, missing): (Vec<String>, Vec<String>) = files.into_iter().partition(|file| fs::symlink_metadata(file).is_ok());
+    let checked: Vec<String> = present.into_iter().filter(|file| sums.contains_key(file)).collect();
+    let modified: Vec<String> = checked
+        .iter()
+        .zip(hash_files(&checked))
+        .filter(|(file, hash)| hash.as_ref().ok() != sums.get(*file))
+        .map(|(file, _)| file.clone())
+        .collect();
+    let stored = modified.iter().filter_map(|file| sums.get(file).cloned()).collect();
+    let damage = Damage { name: pkg.name.clone(), version: pkg.version.clone(), missing, modified, stored };
+    (damage, !sums.is_empty())
+}
+
+async fn repair_source(damage: &Damage, index: &mut Option<RepoIndex>) -> Result<String, Failure> {
+    let cached = cache_path(&damage.name, &damage.version);
+    if Path::new(&cached).exists() {
+        return Ok(cached);
+    }
+    if index.is_none() {
+        say!("lemme check the repo...");
+        *index = Some(fetch_index().await?);
+    }
+    match index.as_ref().and_then(|index| index.packages.get(&damage.name)) {
+        Some(meta) if meta.version == damage.version => fetch_package(&damage.name, meta).await,
+        Some(meta) => Err(format!("v{} isn't cached and the repo moved on to v{}, pls update {} instead", damage.version, meta.version, damage.name).into()),
+        None => Err(format!("v{} isn't cached and the repo doesn't have it", damage.version).into()),
+    }
+}
+
+pub async fn cmd_verify(names: &[String], all: bool, repair: bool) -> Result<(), Failure> {
+    let packages: Vec<PackageInfo> = match all {
+        true => installed_packages(),
+        false => names
+            .iter()
+            .map(|name| {
+                PackageInfo::from_file(&format!("{}/{}/info", db_dir(), name))
+                    .map_err(|_| fail(NOT_FOUND, format!("'{}' isn't even installed bro", name)))
+            })
+            .collect::<Result<_, _>>()?,
+    };
+    if packages.is_empty() {
+        say!("nothing installed yet, nothing to verify");
+        return Ok(());
+    }
+
+    let mut damaged = Vec::new();
+    let mut unsummed = 0;
+    for pkg in &packages {
+        let (damage, summed) = check_installed(pkg);
+        if !summed {
+            unsummed += 1;
+        }
+        if damage.missing.is_empty() && damage.modified.is_empty() {
+            continue;
+        }
+        let mut problems = Vec::new();
+        if !damage.missing.is_empty() {
+            problems.push(format!("{} missing", damage.missing.len()));
+        }
+        if !damage.modified.is_empty() {
+            problems.push(format!("{} modified", damage.modified.len()));
+        }
+        println!("{} v{}: {}", bold(&damage.name), damage.version, red(problems.join(", ")));
+        for file in &damage.missing {
+            println!("  {} {}", red("missing "), file);
+        }
+        for file in &damage.modified {
+            println!("  {} {}", yellow("modified"), file);
+        }
+        damaged.push(damage);
+    }
+    if unsummed > 0 {
+        say!("{} {} package(s) were installed without checksums, only checked their files are there", dim("tip:"), unsummed);
     }
 
-    let db_path = format!("{}/{}", DB_DIR, pkg.name);
-    fs::create_dir_all(&db_path).map_err(|e| format!("couldn't create db entry: {}", e))?;
-    fs::copy(format!("{}/info", temp_dir), format!("{}/info", db_path))
-        .map_err(|e| format!("couldn't save package info: {}", e))?;
-
-    let _ = fs::remove_dir_all(&temp_dir);
-
-    println!("got ya! {} v{} installed", pkg.name, pkg.version);
-    Ok(())
-}
-
-pub fn cmd_remove(package_name: &str) -> Result<(), String> {
-    if !is_installed(package_name) {
-        return Err(format!("'{}' isn't even installed bro", package_name));
+    if damaged.is_empty() {
+        say!("{} {} package(s) check out", green("all good,"), packages.len());
+        return Ok(());
     }
-
-    let bin_path = format!("{}/usr/bin/{}", ROOT, package_name);
-    if Path::new(&bin_path).exists() {
-        fs::remove_file(&bin_path).map_err(|e| format!("couldn't delete binary: {}", e))?;
+    if !repair {
+        let names: Vec<&str> = damaged.iter().map(|d| d.name.as_str()).collect();
+        say!("\n{} pls verify --repair {} puts them back", dim("tip:"), names.join(" "));
+        return Err(fail(CHECKSUM, format!("{} of {} package(s) don't match what was installed", damaged.len(), packages.len())));
     }
 
-    let db_path = format!("{}/{}", DB_DIR, package_name);
-    fs::remove_dir_all(&db_path).map_err(|e| format!("couldn't remove from db: {}", e))?;
-
-    println!("gone! {} has been removed", package_name);
-    Ok(())
-}
-
-pub fn cmd_info(package_input: &str) -> Result<(), String> {
-    let package_path = crate::utils::resolve_package_path(package_input)
-        .ok_or_else(|| format!("couldn't find '{}'", package_input))?;
-
-    let temp_dir = format!("/tmp/pls-info-{}", process::id());
-    extract_package(&package_path, &temp_dir).map_err(|e| format!("couldn't unpack: {}", e))?;
-
-    let pkg = PackageInfo::from_file(&format!("{}/info", &temp_dir))
-        .map_err(|_| "no info file in package")?;
-
-    println!("name: {}", pkg.name);
-    println!("version: {}", pkg.version);
-    if !pkg.depend.is_empty() {
-        println!("depends: {}", pkg.depend.join(", "));
+    let mut index = None;
+    let mut paths = Vec::new();
+    let mut stuck = Vec::new();
+    for damage in &damaged {
+        match repair_source(damage, &mut index).await {
+            Ok(path) => {
+                // modified files share their inode with the store, so those entries are bad too
+                for sha in &damage.stored {
+                    store::evict(sha);
+                }
+                paths.push(path);
+            }
+            Err(e) => {
+                say!("{} can't repair {}: {}", yellow("heads up,"), damage.name, e);
+                stuck.push(damage.name.clone());
+            }
+        }
+    }
+    if !paths.is_empty() {
+        say!("reinstalling {} package(s)...", paths.len());
+        let reasons = vec![None; paths.len()];
+        let changes = install_files(&paths, &reasons, false)?;
+        post_transaction("install", &changes);
+    }
+    if !stuck.is_empty() {
+        return Err(fail(PARTIAL, format!("couldn't repair {}", stuck.join(", "))));
     }
-
-    let _ = fs::remove_dir_all(&temp_dir);
     Ok(())
 }
 
-pub fn cmd_list() -> Result<(), String> {
-    if !Path::new(DB_DIR).exists() {
-        println!("nothing installed yet");
+pub async fn cmd_audit() -> Result<(), Failure> {
+    let installed = installed_packages();
+    if installed.is_empty() {
+        say!("nothing installed yet, nothing to audit");
         return Ok(());
     }
 
-    let entries = fs::read_dir(DB_DIR).map_err(|_| "couldn't read package database")?;
+    say!("checking advisories...");
+    let Some(advisories) = fetch_advisories().await? else {
+        say!("the repo doesn't publish advisories, nothing to check against");
+        return Ok(());
+    };
 
-    let mut count = 0;
-    for entry in entries.flatten() {
-        let name = entry.file_name();
-        let info_path = format!("{}/{}/info", DB_DIR, name.to_string_lossy());
+    let mut vulnerable = 0;
+    for pkg in &installed {
+        let hits: Vec<_> = advisories
+            .get(&pkg.name)
+            .into_iter()
+            .flatten()
+            .filter(|adv| adv.affects(&pkg.version))
+            .collect();
+        if hits.is_empty() {
+            continue;
+        }
 
-        if let Ok(pkg) = PackageInfo::from_file(&info_path) {
-            println!("{} v{}", pkg.name, pkg.version);
-            count += 1;
+        vulnerable += 1;
+        println!("\n{} v{}", bold(&pkg.name), pkg.version);
+        for adv in hits {
+            let fix = if adv.fixed.is_empty() { "no fix yet".to_string() } else { format!("fixed in {}", adv.fixed) };
+            println!("  {} {} ({})", if adv.is_serious() { red("!!!") } else { "   ".to_string() }, adv, fix);
         }
     }
 
-    if count == 0 {
-        println!("nothing installed yet");
-    } else {
-        println!("\n{} package(s) installed", count);
+    if vulnerable > 0 {
+        return Err(format!("{} installed package(s) have known advisories, pls update", vulnerable).into());
     }
+    say!("{} no known advisories for {} installed package(s)", green("all clear,"), installed.len());
     Ok(())
 }
 
-pub enum ProjectType {
-    Rust,
-    CMake,
-    Meson,
-    PlsToml,
-}
+pub async fn cmd_bundle(bundle_name: &str, force: bool) -> Result<(), Failure> {
+    say!("checking repo for bundle '{}'...", bundle_name);
 
-fn detect_project(path: &str) -> Option<(ProjectType, PackageInfo)> {
-    let cargo_path = format!("{}/Cargo.toml", path);
-    if Path::new(&cargo_path).exists() {
-        if let Ok(content) = fs::read_to_string(&cargo_path) {
-            let pkg = PackageInfo::parse_cargo_toml(&content);
-            if !pkg.name.is_empty() {
-                return Some((ProjectType::Rust, pkg));
-            }
-        }
+    let index = fetch_index().await?;
+
+    let nested: Vec<&str> = index
+        .bundles
+        .get(bundle_name)
+        .ok_or_else(|| fail(NOT_FOUND, format!("bundle '{}' not found in repo", bundle_name)))?
+        .iter()
+        .filter_map(|member| member.strip_prefix('@'))
+        .collect();
+    let packages = index.expand_bundle(bundle_name)?;
+    if !nested.is_empty() {
+        say!("'{}' builds on {}", bundle_name, nested.iter().map(|b| format!("@{}", b)).collect::<Vec<_>>().join(", "));
     }
 
-    let cmake_path = format!("{}/CMakeLists.txt", path);
-    if Path::new(&cmake_path).exists() {
-        if let Ok(content) = fs::read_to_string(&cmake_path) {
-            let pkg = PackageInfo::parse_cmake(&content);
-            if !pkg.name.is_empty() {
-                return Some((ProjectType::CMake, pkg));
-            }
-        }
+    if packages.is_empty() {
+        return Err(format!("bundle '{}' is empty", bundle_name).into());
     }
 
-    let meson_path = format!("{}/meson.build", path);
-    if Path::new(&meson_path).exists() {
-        if let Ok(content) = fs::read_to_string(&meson_path) {
-            let pkg = PackageInfo::parse_meson(&content);
-            if !pkg.name.is_empty() {
-                return Some((ProjectType::Meson, pkg));
-            }
-        }
+    let (available, missing): (Vec<String>, Vec<String>) = packages
+        .iter()
+        .cloned()
+        .partition(|name| index.packages.contains_key(name));
+    for name in &missing {
+        say!("{} {} is in the bundle but not in the repo, skipping", red("!!!"), name);
     }
 
-    let pls_path = format!("{}/pls.toml", path);
-    if Path::new(&pls_path).exists() {
-        if let Ok(content) = fs::read_to_string(&pls_path) {
-            let pkg = PackageInfo::parse_pls_toml(&content);
-            if !pkg.name.is_empty() {
-                return Some((ProjectType::PlsToml, pkg));
+    let config = Config::load();
+    let (mut levels, unresolved) = levels(&index, &available)?;
+    let mut failed: Vec<String> = missing.into_iter().chain(unresolved).collect();
+    for level in &mut levels {
+        level.retain(|name| {
+            if config.is_ignored(name) {
+                say!("skipping {}, it's on the ignore list", name);
+                return false;
             }
-        }
+            let mut deps = index.packages[name].deps.iter().map(|d| dep_provider(&index, d).unwrap_or(split_dep(d).0));
+            if let Some(dep) = deps.find(|d| failed.iter().any(|f| f == d)) {
+                say!("{} skipping {}, it needs {} and that can't be installed", red("!!!"), name, dep);
+                failed.push(name.clone());
+                return false;
+            }
+            available.contains(name) || !is_installed(name)
+        });
     }
+    let total: usize = levels.iter().map(|l| l.len()).sum();
 
-    None
-}
+    say!("installing {} package(s) from bundle '{}':", total, bundle_name);
+    let order: Vec<String> = levels.iter().flatten().cloned().collect();
+    let deps: Vec<String> = order.iter().filter(|name| !available.contains(name)).cloned().collect();
+    let plan = Plan::build(&index, &order, &deps);
+    plan.print();
+    if !force {
+        plan.check_space()?;
+    }
 
-pub fn cmd_add(project_path: &str, is_draft: bool, output_dir: Option<&str>) -> Result<(), String> {
-    let (project_type, pkg) = detect_project(project_path)
-        .ok_or_else(|| "dunno what project this is, need Cargo.toml, CMakeLists.txt, meson.build, or pls.toml".to_string())?;
+    let mut changes = Vec::new();
 
-    let binary_path = match project_type {
-        ProjectType::Rust => {
-            let (build_type, bin_path) = if is_draft {
-                ("debug", format!("{}/target/debug/{}", project_path, pkg.name))
-            } else {
-                ("release", format!("{}/target/release/{}", project_path, pkg.name))
-            };
+    let stage_root = format!("{}/pls-bundle-{}", tmp_dir(), process::id());
+    let db_lock = Arc::new(Mutex::new(()));
+    let slots = Arc::new(Semaphore::new(BUNDLE_JOBS));
 
-            if !Path::new(&bin_path).exists() {
-                println!("building {} {}...", build_type, pkg.name);
-                let mut args = vec!["build"];
-                if !is_draft {
-                    args.push("--release");
-                }
-                let status = Command::new("cargo")
-                    .args(&args)
-                    .current_dir(project_path)
-                    .status()
-                    .map_err(|e| format!("cargo failed: {}", e))?;
-                if !status.success() {
-                    return Err("build failed, fix ur code first".to_string());
-                }
+    for level in levels {
+        let mut tasks = Vec::new();
+        for name in level {
+            let meta = index.packages[&name].clone();
+            let mut deps = meta.deps.iter().map(|d| dep_provider(&index, d).unwrap_or(split_dep(d).0));
+            if let Some(dep) = deps.find(|d| failed.iter().any(|f| f == d)) {
+                say!("{} skipping {}, its dependency {} failed", red("!!!"), name, dep);
+                brief!("skipped {} (dependency {} failed)", name, dep);
+                failed.push(name);
+                continue;
             }
-            bin_path
+
+            let reason = if available.contains(&name) { InstallReason::Explicit } else { InstallReason::Dependency };
+            let stage_dir = format!("{}/{}", stage_root, name);
+            let db_lock = db_lock.clone();
+            let slots = slots.clone();
+            let task_name = name.clone();
+            let task = tokio::spawn(async move {
+                let _slot = slots.acquire_owned().await.map_err(|e| e.to_string())?;
+                let path = fetch_package(&task_name, &meta).await?;
+                tokio::task::spawn_blocking(move || install_serialized(&path, &stage_dir, reason, &db_lock, force))
+                    .await
+                    .map_err(|e| e.to_string())?
+            });
+            tasks.push((name, task));
         }
-        ProjectType::CMake => {
-            let build_dir = format!("{}/build", project_path);
-            let bin_path = format!("{}/{}", build_dir, pkg.name);
 
-            if !Path::new(&bin_path).exists() {
-                println!("building {} with cmake...", pkg.name);
-                fs::create_dir_all(&build_dir).map_err(|_| "couldn't create build dir")?;
-
-                let cmake_type = if is_draft { "Debug" } else { "Release" };
-                let status = Command::new("cmake")
-                    .args(["..", &format!("-DCMAKE_BUILD_TYPE={}", cmake_type)])
-                    .current_dir(&build_dir)
-                    .status()
-                    .map_err(|e| format!("cmake failed: {}", e))?;
-                if !status.success() {
-                    return Err("cmake configure failed".to_string());
+        for (name, task) in tasks {
+            match task.await.map_err(|e| Failure::from(e.to_string())).and_then(|r| r) {
+                Ok(change) => {
+                    say!("{} {} v{} installed", green(">>>"), change.name, change.version);
+                    brief!("installed {} {}", change.name, change.version);
+                    changes.push(change);
                 }
-
-                let status = Command::new("make")
-                    .args(["-j4"])
-                    .current_dir(&build_dir)
-                    .status()
-                    .map_err(|e| format!("make failed: {}", e))?;
-                if !status.success() {
-                    return Err("build failed".to_string());
+                Err(e) => {
+                    say!("{} failed to install {}: {}", red("!!!"), name, e);
+                    brief!("failed {}: {}", name, e);
+                    failed.push(name);
                 }
             }
-            bin_path
         }
-        ProjectType::Meson => {
-            let build_dir = format!("{}/builddir", project_path);
-            let bin_path = format!("{}/{}", build_dir, pkg.name);
+    }
 
-            if !Path::new(&bin_path).exists() {
-                println!("building {} with meson...", pkg.name);
-
-                let build_type = if is_draft { "debug" } else { "release" };
-                let status = Command::new("meson")
-                    .args(["setup", &build_dir, "--buildtype", build_type])
-                    .current_dir(project_path)
-                    .status()
-                    .map_err(|e| format!("meson failed: {}", e))?;
-                if !status.success() {
-                    return Err("meson setup failed".to_string());
-                }
+    let _ = remove_tree(&stage_root);
+    say!();
+    post_transaction("install", &changes);
 
-                let status = Command::new("ninja")
-                    .args(["-C", &build_dir])
-                    .status()
-                    .map_err(|e| format!("ninja failed: {}", e))?;
-                if !status.success() {
-                    return Err("build failed".to_string());
-                }
-            }
-            bin_path
-        }
-        ProjectType::PlsToml => {
-            let pls_path = format!("{}/pls.toml", project_path);
-            let content = fs::read_to_string(&pls_path).map_err(|_| "couldn't read pls.toml")?;
-            let mut binary = String::new();
-            for line in content.lines() {
-                if let Some((key, value)) = line.split_once('=') {
-                    if key.trim() == "binary" {
-                        binary = value.trim().trim_matches('"').trim_matches('\'').to_string();
-                    }
-                }
+    if failed.is_empty() {
+        say!("{} bundle '{}' installed ({} packages)", green("nice!"), bundle_name, changes.len());
+        return Ok(());
+    }
+
+    say!("{} {}", red("failed packages:"), failed.join(", "));
+    Err(fail(
+        PARTIAL,
+        format!("bundle '{}' partially installed: {} ok, {} failed", bundle_name, changes.len(), failed.len()),
+    ))
+}
+
+pub async fn cmd_mirrors(test: bool) -> Result<(), Failure> {
+    let config = Config::load();
+    let repos: Vec<(String, String, Vec<String>)> = config
+        .repos()
+        .into_iter()
+        .map(|(name, url)| {
+            let mirrors = config.mirrors_for(&name);
+            (name, url, mirrors)
+        })
+        .filter(|(_, _, mirrors)| !mirrors.is_empty())
+        .collect();
+    if repos.is_empty() {
+        say!("no mirrors set");
+        say!("{} add 'mirror = https://...' (or mirror.<repo> = ...) to {}", dim("tip:"), config_path());
+        return Ok(());
+    }
+
+    for (repo, url, mirrors) in repos {
+        let record = match test {
+            true => {
+                say!("timing {} and its {} mirror(s)...", repo, mirrors.len());
+                Some(mirrors::Record { probed: 0, probes: mirrors::probe_all(&repo, &url, &mirrors).await })
             }
-            if binary.is_empty() {
-                return Err("pls.toml needs 'binary' field".to_string());
+            false => mirrors::last(&repo),
+        };
+        let Some(record) = record else {
+            println!("{} {}", bold(&repo), dim("(never tested)"));
+            println!("  {}", url);
+            for mirror in &mirrors {
+                println!("  {}", mirror);
             }
-            format!("{}/{}", project_path, binary)
-        }
-    };
+            continue;
+        };
 
-    if !Path::new(&binary_path).exists() {
-        return Err(format!("binary not found at {}", binary_path));
+        let age = if test { String::new() } else { format!(" {}", dim(format!("(tested {} min ago)", mirrors::age(&record) / 60))) };
+        println!("{}{}", bold(&repo), age);
+        let chosen = mirrors::fastest(&record.probes).map(|probe| probe.url.clone()).unwrap_or_else(|| url.clone());
+        for probe in &record.probes {
+            let marker = if probe.url == chosen { green("*") } else { " ".to_string() };
+            let origin = if probe.url == url { dim(" (origin)") } else { String::new() };
+            let status = match (&probe.error, probe.latency_ms) {
+                (Some(e), _) => red(e),
+                (None, latency) if probe.behind > 0 => {
+                    yellow(format!("{}ms, behind by {} package(s)", latency.unwrap_or(0), probe.behind))
+                }
+                (None, latency) => format!("{}ms", latency.unwrap_or(0)),
+            };
+            println!("{} {}{}  {}", marker, probe.url, origin, status);
+        }
     }
+    if !test {
+        say!("{} pls mirrors --test to time them again", dim("tip:"));
+    }
+    Ok(())
+}
 
-    let build_dir = "/tmp/pls-build";
-    let _ = fs::remove_dir_all(build_dir);
-    fs::create_dir_all(format!("{}/bin", build_dir))
-        .map_err(|_| "couldn't create build directory")?;
+pub async fn cmd_mirror(repo_url: &str, dest_dir: &str) -> Result<(), Failure> {
+    let repo_url = repo_url.trim_end_matches('/');
+    say!("fetching index from {}...", repo_url);
+    let index = fetch_index_from(repo_url).await?;
 
-    fs::copy(&binary_path, format!("{}/bin/{}", build_dir, pkg.name))
-        .map_err(|_| "couldn't copy binary")?;
+    let packages_dir = format!("{}/packages", dest_dir);
+    fs::create_dir_all(&packages_dir)
+        .map_err(|e| format!("couldn't create {}: {}", packages_dir, e))?;
 
-    let info_content = format!("name = {}\nversion = {}\n", pkg.name, pkg.version);
-    fs::write(format!("{}/info", build_dir), info_content)
-        .map_err(|_| "couldn't write info file")?;
+    let mut names: Vec<&String> = index.packages.keys().collect();
+    names.sort();
 
-    let output_path = output_dir.unwrap_or(PACKAGES_DIR);
-    fs::create_dir_all(output_path)
-        .map_err(|_| "couldn't create output directory (need sudo?)")?;
+    let mut fetched = 0;
+    let mut skipped = 0;
+    let mut failed: Vec<String> = Vec::new();
 
-    let package_file = format!("{}/{}.pls", output_path, pkg.name);
-    create_package(build_dir, &package_file)
-        .map_err(|e| format!("couldn't create package: {}", e))?;
+    for name in names {
+        let meta = &index.packages[name];
+        for build in meta.artifacts(name) {
+            let dest = format!("{}/{}", packages_dir, build.file);
 
-    let _ = fs::remove_dir_all(build_dir);
+            if Path::new(&dest).exists() && build.matches(&dest) {
+                skipped += 1;
+                continue;
+            }
 
-    println!("got ya twin! {} v{} is ready", pkg.name, pkg.version);
-    println!("share it: {}", package_file);
-    Ok(())
-}
+            say!("  {} ({} bytes)", build.file, build.size);
+            let url = format!("{}/packages/{}", repo_url, build.file);
+            if let Err(e) = download_resumable(&url, &dest).await {
+                say!("{} failed to fetch {}: {}", red("!!!"), build.file, e);
+                brief!("failed {}: {}", build.file, e);
+                failed.push(build.file);
+                continue;
+            }
 
-pub fn cmd_repo_update() -> Result<(), String> {
-    let current_dir = env::current_dir().map_err(|_| "couldn't get current directory")?;
+            if build.matches(&dest) {
+                for kind in SIGNATURES {
+                    let _ = fetch_signature(&url, &dest, kind).await;
+                }
+                brief!("fetched {}", build.file);
+                fetched += 1;
+            } else {
+                say!("{} checksum mismatch for {}, dropping it", red("!!!"), build.file);
+                brief!("failed {}: checksum mismatch", build.file);
+                let _ = fs::remove_file(&dest);
+                let _ = fs::remove_file(format!("{}.part", dest));
+                failed.push(build.file);
+            }
+        }
+    }
 
-    let packages_dir = current_dir.join("packages");
-    let index_path = current_dir.join("index.json");
+    write_index(&format!("{}/index.json", dest_dir), &index)?;
+    if let Ok(files_db) = fetch_files_db(repo_url).await {
+        write_files_db(&format!("{}/{}", dest_dir, FILES_DB), &files_db)?;
+    }
 
-    if !packages_dir.exists() {
-        return Err("no packages/ folder here, are you in a repo?".to_string());
+    say!("\n{} fetched, {} already up to date", fetched, skipped);
+    if !failed.is_empty() {
+        return Err(fail(PARTIAL, format!("mirror incomplete, failed: {} (run again to resume)", failed.join(", "))));
     }
 
-    println!("scanning packages/...");
+    let abs_dir = fs::canonicalize(dest_dir)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| dest_dir.to_string());
+    say!("{} point pls at it with: repo = file://{}", green("done!"), abs_dir);
+    Ok(())
+}
 
-    let mut packages: HashMap<String, PackageMeta> = HashMap::new();
-    let temp_dir = format!("/tmp/pls-repo-scan-{}", process::id());
+pub async fn cmd_export_bundle(names: &[String], output: &str) -> Result<(), Failure> {
+    say!("lemme check the repo...");
+    let index = fetch_index().await?;
+    let order = closure(&index, names)?;
 
-    let entries = fs::read_dir(&packages_dir)
-        .map_err(|e| format!("couldn't read packages/: {}", e))?;
+    let stage_dir = format!("{}/pls-export-{}", tmp_dir(), process::id());
+    let _ = fs::remove_dir_all(&stage_dir);
+    fs::create_dir_all(format!("{}/packages", stage_dir))
+        .map_err(|e| format!("couldn't create staging dir: {}", e))?;
 
-    for entry in entries.flatten() {
-        let path = entry.path();
+    say!("packing {} package(s): {}", order.len(), order.join(", "));
 
-        if path.extension().and_then(|e| e.to_str()) != Some("pls") {
-            continue;
+    let mut packages: HashMap<String, PackageMeta> = HashMap::new();
+    for name in &order {
+        let meta = &index.packages[name];
+        let path = fetch_package(name, meta).await?;
+        if !meta.matches(&path) {
+            let _ = fs::remove_dir_all(&stage_dir);
+            return Err(fail(CHECKSUM, format!("checksum mismatch for {}, not packing it", name)));
         }
+        fs::copy(&path, format!("{}/packages/{}.pls", stage_dir, name))
+            .map_err(|e| format!("couldn't stage {}: {}", name, e))?;
+        packages.insert(name.clone(), meta.clone());
+    }
 
-        let path_str = path.to_string_lossy().to_string();
-
-        let sha256 = calculate_sha256(&path_str)
-            .map_err(|e| format!("couldn't hash {}: {}", path_str, e))?;
+    let bundle_name = Path::new(output).file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    let mini_index = RepoIndex {
+        version: index.version,
+        updated: index.updated,
+        packages,
+        bundles: HashMap::from([(bundle_name, names.to_vec())]),
+        by_repo: HashMap::new(),
+    };
+    let json = serde_json::to_string_pretty(&mini_index)
+        .map_err(|e| format!("couldn't serialize index: {}", e))?;
+    fs::write(format!("{}/index.json", stage_dir), json)
+        .map_err(|e| format!("couldn't write index: {}", e))?;
 
-        let size = fs::metadata(&path)
-            .map_err(|e| format!("couldn't get size of {}: {}", path_str, e))?
-            .len();
+    let result = create_package(&stage_dir, output, &[]);
+    let _ = fs::remove_dir_all(&stage_dir);
+    result.map_err(|e| format!("couldn't create {}: {}", output, e))?;
 
-        extract_package(&path_str, &temp_dir)
-            .map_err(|e| format!("couldn't extract {}: {}", path_str, e))?;
+    say!("{} carry {} over and run: pls install {}", green("packed!"), output, output);
+    brief!("packed {}", output);
+    Ok(())
+}
 
-        let pkg = PackageInfo::from_file(&format!("{}/info", &temp_dir))
-            .map_err(|e| format!("couldn't read info from {}: {}", path_str, e))?;
+async fn fetch_export_set(inputs: &[String]) -> Result<(RepoIndex, Vec<String>, Vec<(String, String)>), Failure> {
+    say!("lemme check the repo...");
+    let index = fetch_index().await?;
+    let mut names: Vec<String> = Vec::new();
+    for input in inputs {
+        let members = match input.strip_prefix('@') {
+            Some(bundle) => index.expand_bundle(bundle)?,
+            None if !index.packages.contains_key(input) && index.bundles.contains_key(input) => index.expand_bundle(input)?,
+            None => vec![input.clone()],
+        };
+        for member in members {
+            if !names.contains(&member) {
+                names.push(member);
+            }
+        }
+    }
+    let order = closure(&index, &names)?;
 
-        println!("  found {} v{} ({} bytes)", pkg.name, pkg.version, size);
+    say!("taking {} package(s): {}", order.len(), order.join(", "));
+    let mut packages = Vec::new();
+    for name in &order {
+        let meta = &index.packages[name];
+        let path = fetch_package(name, meta).await?;
+        if !meta.matches(&path) {
+            return Err(fail(CHECKSUM, format!("checksum mismatch for {}, not exporting it", name)));
+        }
+        packages.push((format!("{}-{}", name, meta.version), path));
+    }
+    Ok((index, names, packages))
+}
 
-        packages.insert(pkg.name.clone(), PackageMeta {
-            version: pkg.version,
-            size,
-            sha256,
-            deps: pkg.depend,
-            desc: format!("{} package", pkg.name),
+pub async fn cmd_export_oci(inputs: &[String], output: &str, image: Option<&str>) -> Result<(), Failure> {
+    let (index, names, layers) = fetch_export_set(inputs).await?;
+    let first = inputs[0].trim_start_matches('@');
+    let single = inputs.len() == 1 && names.len() == 1 && names[0] == first;
+    let image = match image {
+        Some(image) if image.contains(':') => image.to_string(),
+        Some(image) => format!("{}:latest", image),
+        None if single => format!("{}:{}", first, index.packages[first].version),
+        None => format!("{}:latest", first),
+    };
+    let mut entrypoint = None;
+    if single && let Some((_, path)) = layers.iter().find(|(label, _)| *label == format!("{}-{}", first, index.packages[first].version)) {
+        let _ = list_package(path, |entry| {
+            if entry.path == format!("bin/{}", first) {
+                entrypoint = Some(first.to_string());
+            }
         });
     }
 
-    let _ = fs::remove_dir_all(&temp_dir);
+    oci::write_image(&layers, &image, entrypoint.as_deref(), output)?;
+    say!("{} {} is {}, load it with: docker load -i {}", green("exported!"), image, output, output);
+    brief!("exported {}", output);
+    Ok(())
+}
 
-    if packages.is_empty() {
-        println!("no packages found in packages/");
-        return Ok(());
-    }
+pub async fn cmd_export_sysext(inputs: &[String], output: Option<&str>) -> Result<(), Failure> {
+    let (index, names, packages) = fetch_export_set(inputs).await?;
+    let output = output.map(|o| o.to_string()).unwrap_or_else(|| format!("{}.raw", inputs[0].trim_start_matches('@')));
+    let arch_specific = names.iter().any(|name| !matches!(index.packages[name].arch.as_str(), "" | "any"));
 
-    let existing_bundles: HashMap<String, Vec<String>> = fs::read_to_string(&index_path)
+    let tool = sysext::build(&packages, arch_specific, &output)?;
+    say!("{} {} ({}), drop it in /var/lib/extensions and run: systemd-sysext refresh", green("exported!"), output, tool);
+    brief!("exported {}", output);
+    Ok(())
+}
+
+fn install_bundle_file(bundle_path: &str) -> Result<(), Failure> {
+    let temp_dir = format!("{}/pls-plsx-{}", tmp_dir(), process::id());
+    extract_package(bundle_path, &temp_dir)
+        .map_err(|e| format!("couldn't unpack {}: {}", bundle_path, e))?;
+
+    let index: RepoIndex = fs::read_to_string(format!("{}/index.json", temp_dir))
         .ok()
-        .and_then(|content| serde_json::from_str::<RepoIndex>(&content).ok())
-        .map(|idx| idx.bundles)
-        .unwrap_or_default();
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .ok_or("bundle seems broken, no index.json inside")?;
 
-    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let mut names: Vec<String> = index.packages.keys().cloned().collect();
+    names.sort();
+    let order = closure(&index, &names)?;
 
-    let index = RepoIndex {
-        version: 1,
-        updated: today,
-        packages,
-        bundles: existing_bundles,
+    let needed: Vec<&str> = index.packages.values().flat_map(|meta| meta.deps.iter().filter_map(|dep| dep_provider(&index, dep))).collect();
+    let members: Vec<&String> = match index.bundles.is_empty() {
+        true => names.iter().filter(|name| !needed.contains(&name.as_str())).collect(),
+        false => index.bundles.values().flatten().collect(),
     };
+    let reasons: Vec<Option<InstallReason>> = order
+        .iter()
+        .map(|name| match members.contains(&name) {
+            true => Some(InstallReason::Explicit),
+            false => Some(InstallReason::Dependency),
+        })
+        .collect();
 
-    let json = serde_json::to_string_pretty(&index)
-        .map_err(|e| format!("couldn't serialize index: {}", e))?;
+    say!("installing {} package(s) from {}", order.len(), bundle_path);
 
-    fs::write(&index_path, json)
-        .map_err(|e| format!("couldn't write index.json: {}", e))?;
+    let result = order
+        .iter()
+        .map(|name| {
+            let path = format!("{}/packages/{}.pls", temp_dir, name);
+            if index.packages[name].matches(&path) {
+                Ok(path)
+            } else {
+                Err(fail(CHECKSUM, format!("{} in bundle is corrupted, not installing anything", name)))
+            }
+        })
+        .collect::<Result<Vec<String>, Failure>>()
+        .and_then(|paths| install_files(&paths, &reasons, false));
 
-    println!("done! index.json updated with {} package(s)", index.packages.len());
+    let _ = remove_tree(&temp_dir);
+    post_transaction("install", &result?);
     Ok(())
 }
 
-pub async fn cmd_update() -> Result<(), String> {
-    if !Path::new(DB_DIR).exists() {
-        println!("nothing installed yet, nothing to update");
+pub async fn cmd_clean(auto: bool, unused: bool) -> Result<(), Failure> {
+    let config = Config::load();
+    if unused {
+        say!("lemme check the repo...");
+        let index = fetch_index().await?;
+        let mut removed = remove_unused(&index, config.keep_versions);
+        if let Some(keep) = config.keep_versions {
+            removed.extend(apply_retention(keep));
+        }
+        if removed.is_empty() {
+            say!("nothing unused in the cache");
+            return Ok(());
+        }
+
+        for pkg in &removed {
+            say!("  removed {} v{} ({})", pkg.name, pkg.version, format_size(pkg.size));
+            brief!("dropped {} {}", pkg.name, pkg.version);
+        }
+        let freed: u64 = removed.iter().map(|c| c.size).sum();
+        say!("removed {} unused package(s), {} freed", removed.len(), format_size(freed));
         return Ok(());
     }
 
-    println!("checking for updates...");
-
-    let index = fetch_index().await?;
+    if auto {
+        if config.cache_max_size.is_none() && config.keep_versions.is_none() {
+            return Err(format!("no cache_max_size or keep_versions set in {}, nothing to enforce", config_path()).into());
+        }
 
-    let entries = fs::read_dir(DB_DIR).map_err(|_| "couldn't read package database")?;
+        let mut evicted = config.keep_versions.map(apply_retention).unwrap_or_default();
+        evicted.extend(config.cache_max_size.map(evict_to_limit).unwrap_or_default());
+        if evicted.is_empty() {
+            say!("cache is within limits, nothing to evict");
+            return Ok(());
+        }
 
-    let mut installed: Vec<(String, String)> = Vec::new();
-    for entry in entries.flatten() {
-        let name = entry.file_name().to_string_lossy().to_string();
-        let info_path = format!("{}/{}/info", DB_DIR, name);
-        if let Ok(pkg) = PackageInfo::from_file(&info_path) {
-            installed.push((pkg.name, pkg.version));
+        for pkg in &evicted {
+            say!("  evicted {} v{} ({})", pkg.name, pkg.version, format_size(pkg.size));
+            brief!("dropped {} {}", pkg.name, pkg.version);
         }
+        let freed: u64 = evicted.iter().map(|c| c.size).sum();
+        say!("evicted {} package(s), {} freed", evicted.len(), format_size(freed));
+        return Ok(());
     }
 
-    if installed.is_empty() {
-        println!("nothing installed yet");
+    let cached = scan();
+    if cached.is_empty() {
+        say!("cache is already empty");
         return Ok(());
     }
 
-    let mut to_update: Vec<String> = Vec::new();
-
-    for (name, local_version) in &installed {
-        if let Some(remote) = index.packages.get(name) {
-            if remote.version != *local_version {
-                println!("  {} {} -> {}", name, local_version, remote.version);
-                to_update.push(name.clone());
-            }
-        }
+    let mut freed = 0;
+    for pkg in &cached {
+        remove_cached(&pkg.path).map_err(|e| format!("couldn't delete {}: {}", pkg.path, e))?;
+        freed += pkg.size;
     }
+    say!("cleared {} cached package(s) from {}, {} freed", cached.len(), cache_dir(), format_size(freed));
+    brief!("cleared {} cached package(s)", cached.len());
+    Ok(())
+}
 
-    if to_update.is_empty() {
-        println!("everything up to date!");
+pub fn cmd_clean_store() -> Result<(), Failure> {
+    let (count, freed) = store::prune();
+    if count == 0 {
+        say!("store is clean, every file in there is still in use");
         return Ok(());
     }
+    say!("dropped {} unused file(s) from {}, {} freed", count, store_dir(), format_size(freed));
+    brief!("dropped {} store file(s)", count);
+    Ok(())
+}
 
-    println!("\nupdating {} package(s)...\n", to_update.len());
-
-    let mut updated = 0;
-    let mut failed: Vec<String> = Vec::new();
+pub fn cmd_env() -> Result<(), Failure> {
+    let config = Config::load();
+    let cached = scan();
 
-    for pkg in &to_update {
-        println!(">>> updating {}...", pkg);
-        match cmd_install(pkg).await {
-            Ok(_) => updated += 1,
-            Err(e) => {
-                println!("!!! failed to update {}: {}", pkg, e);
-                failed.push(pkg.clone());
-            }
-        }
-        println!();
+    println!("pls {}", VERSION);
+    println!("arch: {}", host_arch());
+    println!();
+    println!("paths ({}):", if is_user() { "per-user" } else { "system" });
+    println!("  root: {}", ROOT);
+    println!("  prefix: {}", config.prefix);
+    println!("  libdir: {}", config.libdir());
+    println!("  includedir: {}", config.includedir());
+    println!("  versions: {}", versions_dir());
+    println!("  db: {} ({} package(s) installed)", db_dir(), installed_packages().len());
+    println!(
+        "  cache: {} ({} package(s), {})",
+        cache_dir(),
+        cached.len(),
+        format_size(cached.iter().map(|c| c.size).sum())
+    );
+    println!("  packages: {}", packages_dir());
+    let stored = store::usage();
+    println!(
+        "  store: {} ({} file(s), {}, {} unused)",
+        store_dir(),
+        stored.files,
+        format_size(stored.size),
+        format_size(stored.unused_size)
+    );
+    let config_state = if Path::new(config_path()).exists() { "found" } else { "missing, using defaults" };
+    println!("  config: {} ({})", config_path(), config_state);
+    println!();
+    println!("config:");
+    println!("  repo = {}", config.repo);
+    for (name, url) in &config.extra_repos {
+        println!("  repo.{} = {}", name, url);
     }
-
-    if failed.is_empty() {
-        println!("nice! {} package(s) updated", updated);
-    } else {
-        println!("{} updated, {} failed", updated, failed.len());
-        println!("failed: {}", failed.join(", "));
+    for (pattern, repo) in &config.pins {
+        println!("  pin.{} = {}", pattern, repo);
     }
-
+    for (repo, priority) in &config.priorities {
+        println!("  priority.{} = {}", repo, priority);
+    }
+    println!("  lan = {}", config.lan);
+    println!(
+        "  limit_rate = {}",
+        throttle::rate().map(|rate| format!("{}/s", format_size(rate))).unwrap_or_else(|| "unlimited".to_string())
+    );
+    println!(
+        "  cache_max_size = {}",
+        config.cache_max_size.map(format_size).unwrap_or_else(|| "unlimited".to_string())
+    );
+    println!(
+        "  keep_versions = {}",
+        config.keep_versions.map(|n| n.to_string()).unwrap_or_else(|| "all".to_string())
+    );
+    println!("  ignore = [{}]", config.ignore.join(", "));
     Ok(())
 }
 
-pub async fn cmd_bundle(bundle_name: &str) -> Result<(), String> {
-    println!("checking repo for bundle '{}'...", bundle_name);
-
-    let index = fetch_index().await?;
-
-    let packages = index
-        .bundles
-        .get(bundle_name)
-        .ok_or_else(|| format!("bundle '{}' not found in repo", bundle_name))?;
-
-    if packages.is_empty() {
-        return Err(format!("bundle '{}' is empty", bundle_name));
-    }
-
-    println!("installing {} package(s) from bundle '{}':", packages.len(), bundle_name);
-    for pkg in packages {
-        println!("  - {}", pkg);
+pub async fn cmd_changelog(package_input: &str) -> Result<(), Failure> {
+    if let Some(package_path) = crate::utils::resolve_package_path(package_input) {
+        let content = read_package_file(&package_path, "changelog")
+            .map_err(|_| format!("{} has no changelog inside", package_input))?;
+        print!("{}", content);
+        return Ok(());
     }
-    println!();
 
-    let mut failed: Vec<String> = Vec::new();
-    let mut installed = 0;
+    let index = fetch_index().await?;
+    let meta = index
+        .packages
+        .get(package_input)
+        .ok_or_else(|| fail(NOT_FOUND, format!("'{}' not found in repo", package_input)))?;
 
-    for pkg in packages {
-        println!(">>> installing {}...", pkg);
-        match cmd_install(pkg).await {
-            Ok(_) => installed += 1,
-            Err(e) => {
-                println!("!!! failed to install {}: {}", pkg, e);
-                failed.push(pkg.clone());
-            }
+    if let Ok(local) = PackageInfo::from_file(&format!("{}/{}/info", db_dir(), package_input)) {
+        if local.version == meta.version {
+            println!("{} v{} (installed, up to date)", package_input, meta.version);
+        } else {
+            println!("{} v{} -> v{}", package_input, local.version, meta.version);
         }
-        println!();
+    } else {
+        println!("{} v{}", package_input, meta.version);
     }
+    println!();
 
-    if failed.is_empty() {
-        println!("nice! bundle '{}' installed ({} packages)", bundle_name, installed);
+    if meta.changelog.is_empty() {
+        println!("no changelog published for {}", package_input);
     } else {
-        println!(
-            "bundle '{}' partially installed: {} ok, {} failed",
-            bundle_name,
-            installed,
-            failed.len()
-        );
-        println!("failed packages: {}", failed.join(", "));
+        println!("{}", meta.changelog);
     }
-
     Ok(())
 }
@@ -0,0 +1,103 @@
+use crate::alternatives;
+use crate::cache::known_names;
+use crate::exit::Failure;
+use crate::utils::installed_packages;
+use crate::versions;
+
+const COMMANDS: &[&str] = &[
+    "install", "remove", "rm", "use", "alternatives", "search", "info", "deps", "why", "provides", "changelog", "extract", "convert", "run",
+    "try", "list", "ls", "autoremove", "mark", "update", "rollback", "downgrade", "rebuild", "audit", "verify", "add", "add-bin", "create",
+    "repo", "bundle", "export-bundle", "export", "mirror", "mirrors", "clean", "serve", "env", "completions", "help", "version",
+    "--version", "--help", "--user", "--quiet", "--brief", "--no-color", "--limit-rate", "--on-conflict",
+];
+
+const BASH: &str = r#"_pls() {
+    local cur=${COMP_WORDS[COMP_CWORD]}
+    local cmd=""
+    [ "$COMP_CWORD" -gt 1 ] && cmd=${COMP_WORDS[1]}
+    COMPREPLY=($(pls _complete "$cmd" "$cur" 2>/dev/null))
+}
+complete -o default -F _pls pls
+"#;
+
+const ZSH: &str = r#"#compdef pls
+_pls() {
+    local cmd=""
+    (( CURRENT > 2 )) && cmd=$words[2]
+    local -a names
+    names=(${(f)"$(pls _complete "$cmd" "$words[CURRENT]" 2>/dev/null)"})
+    if (( ${#names} )); then
+        compadd -a names
+    else
+        _files
+    fi
+}
+compdef _pls pls
+"#;
+
+const FISH: &str = r#"complete -c pls -f -n '__fish_use_subcommand' -a '(pls _complete "" (commandline -ct) 2>/dev/null)'
+complete -c pls -n 'not __fish_use_subcommand' -a '(pls _complete (commandline -opc)[2] (commandline -ct) 2>/dev/null)'
+"#;
+
+pub fn print(shell: &str) -> Result<(), Failure> {
+    let script = match shell {
+        "bash" => BASH,
+        "zsh" => ZSH,
+        "fish" => FISH,
+        _ => return Err(format!("no completions for '{}', try bash, zsh or fish", shell).into()),
+    };
+    print!("{}", script);
+    Ok(())
+}
+
+pub fn complete(command: &str, prefix: &str) {
+    let candidates: Vec<String> = match command {
+        "" => COMMANDS.iter().map(|c| c.to_string()).collect(),
+        "install" | "run" | "try" | "deps" | "changelog" | "export-bundle" | "export" => known_names(),
+        "remove" | "rm" | "info" | "why" | "mark" | "update" | "rollback" | "downgrade" | "verify" | "rebuild" => {
+            installed_packages().into_iter().map(|pkg| pkg.name).collect()
+        }
+        "use" => versions::managed(),
+        "alternatives" => alternatives::names(),
+        _ => Vec::new(),
+    };
+    let mut candidates: Vec<String> = candidates.into_iter().filter(|c| c.starts_with(prefix)).collect();
+    candidates.sort();
+    candidates.dedup();
+    for candidate in candidates {
+        println!("{}", candidate);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::COMMANDS;
+
+    const MAIN: &str = include_str!("main.rs");
+
+    #[test]
+    fn commands_match_main_dispatch() {
+        let dispatch = &MAIN[MAIN.find("let result").unwrap()..];
+        let arms = dispatch.lines().filter(|line| line.starts_with("        \"") && line.contains("=>"));
+        for arm in arms {
+            let patterns = arm.split("=>").next().unwrap().split(" if ").next().unwrap();
+            for name in patterns.split('|').map(|p| p.trim().trim_matches('"')) {
+                if name.starts_with('_') || (name.starts_with('-') && !name.starts_with("--")) {
+                    continue;
+                }
+                assert!(COMMANDS.contains(&name), "pls {} isn't in the completions", name);
+            }
+        }
+    }
+
+    #[test]
+    fn global_flags_complete() {
+        let setup = &MAIN[MAIN.find("async fn main").unwrap()..MAIN.find("let result").unwrap()];
+        for flag in setup.split("a == \"").skip(1).chain(setup.split("a != \"").skip(1)) {
+            let flag = flag.split('"').next().unwrap();
+            if flag.starts_with("--") {
+                assert!(COMMANDS.contains(&flag), "{} isn't in the completions", flag);
+            }
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use std::fs;
+
+use crate::paths::{config_path, default_prefix};
+use crate::utils::{glob_match, parse_list, parse_size};
+use crate::REPO_URL;
+
+pub const MAIN_REPO: &str = "main";
+
+fn lookup<'a>(list: &'a [(String, String)], repo: &str) -> Option<&'a str> {
+    list.iter().find(|(name, _)| name == repo).map(|(_, value)| value.as_str())
+}
+
+pub struct Config {
+    pub repo: String,
+    pub lan: bool,
+    pub cache_max_size: Option<u64>,
+    pub limit_rate: Option<u64>,
+    pub ignore: Vec<String>,
+    pub keep_versions: Option<usize>,
+    pub post_transaction: Option<String>,
+    pub extra_repos: Vec<(String, String)>,
+    pub mirrors: Vec<(String, String)>,
+    pub pins: Vec<(String, String)>,
+    pub priorities: Vec<(String, i32)>,
+    pub repo_webhook: Option<String>,
+    pub prefix: String,
+    pub libdir: Option<String>,
+    pub includedir: Option<String>,
+    pub pubkeys: Vec<(String, String)>,
+    pub cosign_identities: Vec<(String, String)>,
+    pub cosign_issuers: Vec<(String, String)>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            repo: REPO_URL.to_string(),
+            lan: false,
+            cache_max_size: None,
+            limit_rate: None,
+            ignore: Vec::new(),
+            keep_versions: None,
+            post_transaction: None,
+            extra_repos: Vec::new(),
+            mirrors: Vec::new(),
+            pins: Vec::new(),
+            priorities: Vec::new(),
+            repo_webhook: None,
+            prefix: default_prefix().to_string(),
+            libdir: None,
+            includedir: None,
+            pubkeys: Vec::new(),
+            cosign_identities: Vec::new(),
+            cosign_issuers: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> Self {
+        fs::read_to_string(config_path())
+            .map(|content| Self::parse(&content))
+            .unwrap_or_default()
+    }
+
+    pub fn parse(content: &str) -> Self {
+        let mut config = Self::default();
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.starts_with('#') {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once('=') {
+                let key = key.trim();
+                let value = value.trim().trim_matches('"').trim_matches('\'');
+                match key {
+                    "repo" => config.repo = value.trim_end_matches('/').to_string(),
+                    "prefix" if value.starts_with('/') => config.prefix = value.trim_end_matches('/').to_string(),
+                    "libdir" if value.starts_with('/') => config.libdir = Some(value.trim_end_matches('/').to_string()),
+                    "includedir" if value.starts_with('/') => config.includedir = Some(value.trim_end_matches('/').to_string()),
+                    "mirror" => config.mirrors.push((MAIN_REPO.to_string(), value.trim_end_matches('/').to_string())),
+                    "pubkey" => config.pubkeys.push((MAIN_REPO.to_string(), value.to_string())),
+                    "cosign_identity" => config.cosign_identities.push((MAIN_REPO.to_string(), value.to_string())),
+                    "cosign_issuer" => config.cosign_issuers.push((MAIN_REPO.to_string(), value.to_string())),
+                    "lan" => config.lan = value == "true",
+                    "cache_max_size" => config.cache_max_size = parse_size(value),
+                    "limit_rate" => config.limit_rate = parse_size(value).filter(|rate| *rate > 0),
+                    "ignore" => config.ignore = parse_list(value),
+                    "keep_versions" => config.keep_versions = value.parse().ok().filter(|n| *n > 0),
+                    "repo_webhook" => config.repo_webhook = Some(value.to_string()).filter(|v| !v.is_empty()),
+                    "post_transaction" => config.post_transaction = Some(value.to_string()).filter(|v| !v.is_empty()),
+                    _ => {
+                        if let Some(name) = key.strip_prefix("repo.") {
+                            config.extra_repos.push((name.to_string(), value.trim_end_matches('/').to_string()));
+                        } else if let Some(repo) = key.strip_prefix("mirror.") {
+                            config.mirrors.push((repo.to_string(), value.trim_end_matches('/').to_string()));
+                        } else if let Some(repo) = key.strip_prefix("pubkey.") {
+                            config.pubkeys.push((repo.to_string(), value.to_string()));
+                        } else if let Some(repo) = key.strip_prefix("cosign_identity.") {
+                            config.cosign_identities.push((repo.to_string(), value.to_string()));
+                        } else if let Some(repo) = key.strip_prefix("cosign_issuer.") {
+                            config.cosign_issuers.push((repo.to_string(), value.to_string()));
+                        } else if let Some(pattern) = key.strip_prefix("pin.") {
+                            config.pins.push((pattern.to_string(), value.to_string()));
+                        } else if let Some(repo) = key.strip_prefix("priority.")
+                            && let Ok(priority) = value.parse()
+                        {
+                            config.priorities.push((repo.to_string(), priority));
+                        }
+                    }
+                }
+            }
+        }
+
+        config
+    }
+
+    pub fn libdir(&self) -> String {
+        self.libdir.clone().unwrap_or_else(|| format!("{}/lib", self.prefix))
+    }
+
+    pub fn includedir(&self) -> String {
+        self.includedir.clone().unwrap_or_else(|| format!("{}/include", self.prefix))
+    }
+
+    pub fn install_dirs(&self) -> [(&'static str, String); 4] {
+        [
+            ("bin", format!("{}/bin", self.prefix)),
+            ("include", self.includedir()),
+            ("lib", self.libdir()),
+            ("share", format!("{}/share", self.prefix)),
+        ]
+    }
+
+    pub fn repos(&self) -> Vec<(String, String)> {
+        let mut repos = vec![(MAIN_REPO.to_string(), self.repo.clone())];
+        repos.extend(self.extra_repos.iter().cloned());
+        repos
+    }
+
+    pub fn mirrors_for(&self, repo: &str) -> Vec<String> {
+        self.mirrors.iter().filter(|(name, _)| name == repo).map(|(_, url)| url.clone()).collect()
+    }
+
+    pub fn pin_for(&self, name: &str) -> Option<&str> {
+        self.pins
+            .iter()
+            .find(|(pattern, _)| glob_match(pattern, name))
+            .map(|(_, repo)| repo.as_str())
+    }
+
+    pub fn pubkey_for(&self, repo: &str) -> Option<&str> {
+        self.pubkeys
+            .iter()
+            .find(|(name, _)| name == repo)
+            .map(|(_, key)| key.as_str())
+    }
+
+    pub fn cosign_for(&self, repo: &str) -> Option<(&str, &str)> {
+        Some((lookup(&self.cosign_identities, repo)?, lookup(&self.cosign_issuers, repo)?))
+    }
+
+    pub fn priority(&self, repo: &str) -> i32 {
+        self.priorities
+            .iter()
+            .find(|(name, _)| name == repo)
+            .map(|(_, priority)| *priority)
+            .unwrap_or(0)
+    }
+
+    pub fn pick_repo<'a>(&self, name: &str, available: &[&'a str]) -> Option<&'a str> {
+        if let Some(pinned) = self.pin_for(name) {
+            return available.iter().find(|repo| **repo == pinned).copied();
+        }
+        available
+            .iter()
+            .rev()
+            .filter(|repo| self.priority(repo) >= 0)
+            .max_by_key(|repo| self.priority(repo))
+            .copied()
+    }
+
+    pub fn tied_repos<'a>(&self, name: &str, available: &[&'a str]) -> Vec<&'a str> {
+        if self.pin_for(name).is_some() {
+            return Vec::new();
+        }
+        let Some(top) = available.iter().map(|repo| self.priority(repo)).filter(|priority| *priority >= 0).max() else {
+            return Vec::new();
+        };
+        available.iter().filter(|repo| self.priority(repo) == top).copied().collect()
+    }
+
+    pub fn is_ignored(&self, name: &str) -> bool {
+        self.ignore.iter().any(|pattern| glob_match(pattern, name))
+    }
+}
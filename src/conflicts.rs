@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::OnceLock;
+
+use crate::paths::db_dir;
+use crate::term::pick;
+use crate::utils::installed_files;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Choice {
+    Keep,
+    Replace,
+    Abort,
+}
+
+static PRESET: OnceLock<Choice> = OnceLock::new();
+
+pub fn parse(value: &str) -> Option<Choice> {
+    match value {
+        "keep" => Some(Choice::Keep),
+        "replace" => Some(Choice::Replace),
+        "abort" => Some(Choice::Abort),
+        _ => None,
+    }
+}
+
+pub fn init(choice: Choice) {
+    let _ = PRESET.set(choice);
+}
+
+pub fn owners() -> HashMap<String, String> {
+    let Ok(entries) = fs::read_dir(db_dir()) else {
+        return HashMap::new();
+    };
+    let mut owners = HashMap::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        for file in installed_files(&name).into_iter().filter(|f| !f.ends_with('/')) {
+            owners.insert(file, name.clone());
+        }
+    }
+    owners
+}
+
+pub fn resolve(name: &str, owner: &str, files: &[String]) -> Choice {
+    if let Some(choice) = PRESET.get() {
+        return *choice;
+    }
+    let more = if files.len() > 1 { format!(" (+{} more)", files.len() - 1) } else { String::new() };
+    let question = format!(
+        "{} wants {}{} but {} already put it there. [k]eep {}'s, [r]eplace with {}'s or [a]bort?",
+        name, files[0], more, owner, owner, name
+    );
+    match pick(&question, &['k', 'r', 'a']) {
+        Some('k') => Choice::Keep,
+        Some('r') => Choice::Replace,
+        _ => Choice::Abort,
+    }
+}
+
+pub fn choose_repo<'a>(name: &str, repos: &[&'a str]) -> Option<&'a str> {
+    if repos.len() < 2 || repos.len() > 9 || PRESET.get().is_some() {
+        return None;
+    }
+    let listed: Vec<String> = repos.iter().enumerate().map(|(i, repo)| format!("[{}] {}", i + 1, repo)).collect();
+    let keys: Vec<char> = (1..=repos.len() as u32).filter_map(|i| char::from_digit(i, 10)).collect();
+    let question = format!("{} is in {}, which one?", name, listed.join(", "));
+    let choice = pick(&question, &keys)?.to_digit(10)?;
+    repos.get(choice as usize - 1).copied()
+}
@@ -0,0 +1,125 @@
+use std::fs;
+
+use tar::EntryType;
+
+use crate::types::PackageInfo;
+use crate::utils::{relocate_payload, split_dep};
+use crate::say;
+use crate::term::yellow;
+
+const PREFIX: &str = "./usr/local";
+
+fn deb_arch(arch: &str) -> &str {
+    match arch {
+        "" | "any" => "all",
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        "x86" | "i686" => "i386",
+        "arm" => "armhf",
+        "powerpc64" => "ppc64el",
+        other => other,
+    }
+}
+
+pub fn deb_name(name: &str) -> String {
+    name.to_lowercase().replace('_', "-")
+}
+
+pub fn file_name(pkg: &PackageInfo) -> String {
+    format!("{}_{}_{}.deb", deb_name(&pkg.name), pkg.version, deb_arch(&pkg.arch))
+}
+
+fn depends(pkg: &PackageInfo) -> Vec<String> {
+    let mut out = Vec::new();
+    for dep in &pkg.depend {
+        let (name, range) = split_dep(dep);
+        if name.contains(".so") {
+            say!("{} dropping {} from Depends, apt has no idea which package ships it", yellow("heads up,"), name);
+            continue;
+        }
+        let mut alternatives = Vec::new();
+        for constraint in range.split(',').map(str::trim).filter(|c| !c.is_empty() && *c != "*") {
+            let split = constraint.find(|c: char| !"<>=!".contains(c)).unwrap_or(constraint.len());
+            let (op, version) = constraint.split_at(split);
+            let op = match op {
+                "<" => "<<",
+                ">" => ">>",
+                "<=" | ">=" => op,
+                "" | "=" | "==" => "=",
+                _ => {
+                    say!("{} dropping '{}' on {}, debs can't say that", yellow("heads up,"), constraint, name);
+                    continue;
+                }
+            };
+            alternatives.push(format!("{} ({} {})", deb_name(name), op, version.trim()));
+        }
+        if alternatives.is_empty() {
+            alternatives.push(deb_name(name));
+        }
+        out.extend(alternatives);
+    }
+    out
+}
+
+fn ar_member(out: &mut Vec<u8>, name: &str, data: &[u8]) {
+    out.extend(format!("{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n", name, 0, 0, 0, 100644, data.len()).as_bytes());
+    out.extend(data);
+    if data.len() % 2 == 1 {
+        out.push(b'\n');
+    }
+}
+
+fn directory(tar: &mut tar::Builder<Vec<u8>>, path: &str) -> std::io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(EntryType::Directory);
+    header.set_mode(0o755);
+    header.set_size(0);
+    tar.append_data(&mut header, path, std::io::empty())
+}
+
+pub fn convert(archive_path: &str, pkg: &PackageInfo, output: &str) -> Result<(), String> {
+    let mut data = tar::Builder::new(Vec::new());
+    let size = ["./", "./usr", PREFIX]
+        .iter()
+        .try_for_each(|dir| directory(&mut data, dir))
+        .and_then(|_| relocate_payload(archive_path, PREFIX, &mut data))
+        .map_err(|e| format!("couldn't repack {}: {}", archive_path, e))?;
+    let data = data.into_inner().map_err(|e| e.to_string())?;
+
+    let summary = if pkg.desc.is_empty() { format!("{}, converted from a .pls", pkg.name) } else { pkg.desc.clone() };
+    let maintainer = if pkg.maintainer.is_empty() { "pls <pls@localhost>" } else { pkg.maintainer.as_str() };
+    let mut control = format!(
+        "Package: {}\nVersion: {}\nArchitecture: {}\nMaintainer: {}\nInstalled-Size: {}\n",
+        deb_name(&pkg.name),
+        pkg.version,
+        deb_arch(&pkg.arch),
+        maintainer,
+        size.div_ceil(1024)
+    );
+    if !pkg.homepage.is_empty() {
+        control.push_str(&format!("Homepage: {}\n", pkg.homepage));
+    }
+    let depends = depends(pkg);
+    if !depends.is_empty() {
+        control.push_str(&format!("Depends: {}\n", depends.join(", ")));
+    }
+    control.push_str(&format!("Section: misc\nPriority: optional\nDescription: {}\n", summary.lines().next().unwrap_or("")));
+    if let Some(revision) = pkg.revision() {
+        control.push_str(&format!(" Built from {}.\n", revision));
+    }
+
+    let mut control_tar = tar::Builder::new(Vec::new());
+    let mut header = tar::Header::new_gnu();
+    header.set_mode(0o644);
+    header.set_size(control.len() as u64);
+    directory(&mut control_tar, "./")
+        .and_then(|_| control_tar.append_data(&mut header, "./control", control.as_bytes()))
+        .map_err(|e| e.to_string())?;
+    let control_tar = control_tar.into_inner().map_err(|e| e.to_string())?;
+
+    let mut deb = b"!<arch>\n".to_vec();
+    ar_member(&mut deb, "debian-binary", b"2.0\n");
+    ar_member(&mut deb, "control.tar", &control_tar);
+    ar_member(&mut deb, "data.tar", &data);
+    fs::write(output, deb).map_err(|e| format!("couldn't write {}: {}", output, e))
+}
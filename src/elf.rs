@@ -0,0 +1,194 @@
+use std::fs;
+use std::path::Path;
+
+use crate::ROOT;
+use crate::utils::host_arch;
+
+const PT_LOAD: u32 = 1;
+const PT_DYNAMIC: u32 = 2;
+const PT_INTERP: u32 = 3;
+const DT_NULL: usize = 0;
+const DT_STRTAB: usize = 5;
+const DT_SONAME: usize = 14;
+const MACHINES: [(u16, &str); 7] = [
+    (0x03, "x86"),
+    (0x28, "arm"),
+    (0x3e, "x86_64"),
+    (0xb7, "aarch64"),
+    (0xf3, "riscv64"),
+    (0x15, "powerpc64"),
+    (0x16, "s390x"),
+];
+
+pub struct ElfInfo {
+    pub machine: u16,
+    pub interpreter: Option<String>,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    is_64: bool,
+    le: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        if data.len() < 52 || &data[..4] != b"\x7fELF" {
+            return None;
+        }
+        Some(Self { data, is_64: data[4] == 2, le: data[5] == 1 })
+    }
+
+    fn bytes(&self, off: usize, len: usize) -> Option<&'a [u8]> {
+        self.data.get(off..off.checked_add(len)?)
+    }
+
+    fn u16_at(&self, off: usize) -> Option<u16> {
+        let b: [u8; 2] = self.bytes(off, 2)?.try_into().ok()?;
+        Some(if self.le { u16::from_le_bytes(b) } else { u16::from_be_bytes(b) })
+    }
+
+    fn u32_at(&self, off: usize) -> Option<u32> {
+        let b: [u8; 4] = self.bytes(off, 4)?.try_into().ok()?;
+        Some(if self.le { u32::from_le_bytes(b) } else { u32::from_be_bytes(b) })
+    }
+
+    fn u64_at(&self, off: usize) -> Option<u64> {
+        let b: [u8; 8] = self.bytes(off, 8)?.try_into().ok()?;
+        Some(if self.le { u64::from_le_bytes(b) } else { u64::from_be_bytes(b) })
+    }
+
+    fn word_at(&self, off: usize) -> Option<usize> {
+        if self.is_64 { self.u64_at(off).map(|v| v as usize) } else { self.u32_at(off).map(|v| v as usize) }
+    }
+
+    fn string_at(&self, off: usize) -> Option<String> {
+        let rest = self.data.get(off..)?;
+        let end = rest.iter().position(|b| *b == 0)?;
+        Some(String::from_utf8_lossy(&rest[..end]).to_string())
+    }
+
+    // (type, offset, vaddr, filesz) of every program header
+    fn segments(&self) -> Option<Vec<(u32, usize, usize, usize)>> {
+        let (phoff, phentsize, phnum) = if self.is_64 {
+            (self.u64_at(32)? as usize, self.u16_at(54)? as usize, self.u16_at(56)? as usize)
+        } else {
+            (self.u32_at(28)? as usize, self.u16_at(42)? as usize, self.u16_at(44)? as usize)
+        };
+        let mut segments = Vec::new();
+        for i in 0..phnum {
+            let ph = phoff + i * phentsize;
+            let kind = self.u32_at(ph)?;
+            let segment = if self.is_64 {
+                (kind, self.word_at(ph + 8)?, self.word_at(ph + 16)?, self.word_at(ph + 32)?)
+            } else {
+                (kind, self.word_at(ph + 4)?, self.word_at(ph + 8)?, self.word_at(ph + 16)?)
+            };
+            segments.push(segment);
+        }
+        Some(segments)
+    }
+}
+
+pub fn read_elf(path: &Path) -> Option<ElfInfo> {
+    parse_elf(&fs::read(path).ok()?)
+}
+
+pub fn parse_elf(data: &[u8]) -> Option<ElfInfo> {
+    let elf = Reader::new(data)?;
+    let machine = elf.u16_at(18)?;
+
+    let interpreter = elf
+        .segments()?
+        .into_iter()
+        .filter(|(kind, _, _, _)| *kind == PT_INTERP)
+        .filter_map(|(_, offset, _, size)| elf.bytes(offset, size))
+        .map(|raw| String::from_utf8_lossy(raw).trim_end_matches('\0').to_string())
+        .next();
+
+    Some(ElfInfo { machine, interpreter })
+}
+
+pub fn soname(path: &Path) -> Option<String> {
+    let data = fs::read(path).ok()?;
+    let elf = Reader::new(&data)?;
+    let segments = elf.segments()?;
+    let (_, dynamic, _, dynamic_size) = segments.iter().find(|(kind, _, _, _)| *kind == PT_DYNAMIC)?;
+
+    let entry = if elf.is_64 { 16 } else { 8 };
+    let mut strtab = None;
+    let mut soname = None;
+    for off in (*dynamic..dynamic + dynamic_size).step_by(entry) {
+        let tag = elf.word_at(off)?;
+        let value = elf.word_at(off + entry / 2)?;
+        match tag {
+            DT_NULL => break,
+            DT_STRTAB => strtab = Some(value),
+            DT_SONAME => soname = Some(value),
+            _ => {}
+        }
+    }
+
+    let strtab = strtab?;
+    let (_, offset, vaddr, _) = segments
+        .iter()
+        .find(|(kind, _, vaddr, size)| *kind == PT_LOAD && (*vaddr..vaddr + size).contains(&strtab))?;
+    elf.string_at(strtab - vaddr + offset + soname?)
+}
+
+pub fn library_present(soname: &str, libdir: &str) -> bool {
+    let multiarch = format!("{}-linux-gnu", host_arch());
+    let dirs = [
+        libdir.to_string(),
+        "lib".to_string(),
+        "lib64".to_string(),
+        "usr/lib".to_string(),
+        "usr/lib64".to_string(),
+        "usr/local/lib".to_string(),
+        format!("lib/{}", multiarch),
+        format!("usr/lib/{}", multiarch),
+    ];
+    dirs.iter().any(|dir| Path::new(ROOT).join(dir.trim_start_matches('/')).join(soname).exists())
+}
+
+pub fn arch_name(machine: u16) -> String {
+    MACHINES
+        .iter()
+        .find(|(m, _)| *m == machine)
+        .map(|(_, name)| name.to_string())
+        .unwrap_or_else(|| format!("machine {:#x}", machine))
+}
+
+fn host_machine() -> Option<u16> {
+    MACHINES.iter().find(|(_, name)| *name == host_arch()).map(|(m, _)| *m)
+}
+
+fn host_is_musl() -> bool {
+    fs::read_dir(Path::new(ROOT).join("lib"))
+        .map(|entries| entries.flatten().any(|e| e.file_name().to_string_lossy().starts_with("ld-musl-")))
+        .unwrap_or(false)
+}
+
+pub fn check_binary(path: &Path) -> Result<(), String> {
+    let Some(elf) = read_elf(path) else {
+        return Ok(());
+    };
+    if cfg!(target_os = "macos") {
+        return Err("a linux binary, that won't run on macos".to_string());
+    }
+
+    if let Some(host) = host_machine()
+        && host != elf.machine
+    {
+        return Err(format!("built for {} but this box is {}", arch_name(elf.machine), host_arch()));
+    }
+
+    if let Some(interp) = &elf.interpreter
+        && interp.contains("ld-linux")
+        && !Path::new(ROOT).join(interp.trim_start_matches('/')).exists()
+        && host_is_musl()
+    {
+        return Err("linked against glibc but this box runs musl".to_string());
+    }
+    Ok(())
+}
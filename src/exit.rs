@@ -0,0 +1,49 @@
+use std::fmt;
+use std::io;
+
+pub const FAILURE: i32 = 1;
+pub const NOT_FOUND: i32 = 2;
+pub const NETWORK: i32 = 3;
+pub const CHECKSUM: i32 = 4;
+pub const PERMISSION: i32 = 5;
+pub const CONFLICT: i32 = 6;
+pub const PARTIAL: i32 = 7;
+
+#[derive(Clone, Debug)]
+pub struct Failure {
+    pub code: i32,
+    pub message: String,
+}
+
+impl Failure {
+    pub fn context(self, what: impl fmt::Display) -> Self {
+        Self { code: self.code, message: format!("{}: {}", what, self.message) }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<String> for Failure {
+    fn from(message: String) -> Self {
+        Self { code: FAILURE, message }
+    }
+}
+
+impl From<&str> for Failure {
+    fn from(message: &str) -> Self {
+        Self { code: FAILURE, message: message.to_string() }
+    }
+}
+
+pub fn fail(code: i32, msg: impl Into<String>) -> Failure {
+    Failure { code, message: msg.into() }
+}
+
+pub fn io_fail(e: &io::Error, msg: impl Into<String>) -> Failure {
+    let code = if e.kind() == io::ErrorKind::PermissionDenied { PERMISSION } else { FAILURE };
+    fail(code, msg)
+}
@@ -0,0 +1,170 @@
+use std::fs;
+use std::path::Path;
+
+use crate::types::{Alternative, PackageInfo};
+use crate::utils::{parse_list, walk_files};
+
+fn man_section(file_name: &str) -> Option<char> {
+    let name = file_name.strip_suffix(".gz").unwrap_or(file_name);
+    let (stem, ext) = name.rsplit_once('.')?;
+    let mut chars = ext.chars();
+    let section = chars.next()?;
+    if stem.is_empty() || chars.next().is_some() || !('1'..='9').contains(&section) {
+        return None;
+    }
+    let mut parts = stem.split('.');
+    if parts.clone().any(|part| part == "so") || parts.next_back().is_some_and(|part| part.chars().all(|c| c.is_ascii_digit())) {
+        return None;
+    }
+    Some(section)
+}
+
+pub fn find_man_pages(project_path: &str, build_dirs: &[String]) -> Vec<(String, String)> {
+    let mut dirs = vec![format!("{}/doc", project_path), format!("{}/man", project_path)];
+    dirs.extend(build_dirs.iter().cloned());
+
+    let mut pages: Vec<(String, String)> = Vec::new();
+    for dir in &dirs {
+        for rel in walk_files(dir) {
+            let file_name = Path::new(&rel)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let Some(section) = man_section(&file_name) else {
+                continue;
+            };
+            let dest = format!("man/man{}/{}", section, file_name);
+            if !pages.iter().any(|(_, d)| *d == dest) {
+                pages.push((format!("{}/{}", dir, rel), dest));
+            }
+        }
+    }
+    pages
+}
+
+fn completion_dest(name: &str, rel: &str) -> Option<String> {
+    let path = Path::new(rel);
+    let file_name = path.file_name()?.to_string_lossy().to_string();
+    let in_bash_dir = path.parent().is_some_and(|p| p.ends_with("bash"));
+
+    if file_name == format!("{}.bash", name) || (in_bash_dir && file_name == name) {
+        Some(format!("bash-completion/completions/{}", name))
+    } else if file_name == format!("_{}", name) || file_name == format!("{}.zsh", name) {
+        Some(format!("zsh/site-functions/_{}", name))
+    } else if file_name == format!("{}.fish", name) {
+        Some(format!("fish/vendor_completions.d/{}.fish", name))
+    } else {
+        None
+    }
+}
+
+pub fn find_completions(project_path: &str, name: &str, build_dirs: &[String]) -> Vec<(String, String)> {
+    let mut dirs = vec![
+        format!("{}/completions", project_path),
+        format!("{}/completion", project_path),
+        format!("{}/contrib/completions", project_path),
+    ];
+    dirs.extend(build_dirs.iter().cloned());
+
+    let mut found: Vec<(String, String)> = Vec::new();
+    for dir in &dirs {
+        for rel in walk_files(dir) {
+            let Some(dest) = completion_dest(name, &rel) else {
+                continue;
+            };
+            if !found.iter().any(|(_, d)| *d == dest) {
+                found.push((format!("{}/{}", dir, rel), dest));
+            }
+        }
+    }
+    found
+}
+
+const SPLIT_DEFAULTS: [(&str, &[&str]); 2] = [
+    ("docs", &["share/man/*", "share/doc/*", "share/info/*"]),
+    ("dev", &["include/*", "lib/pkgconfig/*", "share/pkgconfig/*", "share/aclocal/*"]),
+];
+
+pub fn split_rules(project_path: &str) -> Result<Vec<(String, Vec<String>)>, String> {
+    let Ok(content) = fs::read_to_string(format!("{}/pls.toml", project_path)) else {
+        return Ok(Vec::new());
+    };
+
+    let mut kinds = Vec::new();
+    let mut custom: Vec<(String, Vec<String>)> = Vec::new();
+    for line in content.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"').trim_matches('\'');
+        match key.trim() {
+            "split" => kinds = parse_list(value),
+            key => {
+                if let Some(kind) = key.strip_prefix("split.") {
+                    custom.push((kind.to_string(), parse_list(value)));
+                }
+            }
+        }
+    }
+
+    kinds
+        .into_iter()
+        .map(|kind| {
+            let patterns = match custom.iter().find(|(k, _)| *k == kind) {
+                Some((_, patterns)) => patterns.clone(),
+                None => SPLIT_DEFAULTS
+                    .iter()
+                    .find(|(k, _)| *k == kind)
+                    .map(|(_, patterns)| patterns.iter().map(|p| p.to_string()).collect())
+                    .ok_or_else(|| format!("pls.toml wants a '{}' split but has no split.{} patterns", kind, kind))?,
+            };
+            Ok((kind, patterns))
+        })
+        .collect()
+}
+
+pub fn find_alternatives(project_path: &str, package: &str) -> Vec<Alternative> {
+    let Ok(content) = fs::read_to_string(format!("{}/pls.toml", project_path)) else {
+        return Vec::new();
+    };
+    content
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .filter(|(key, _)| key.trim() == "alternatives")
+        .flat_map(|(_, value)| parse_list(value.trim()))
+        .filter_map(|value| Alternative::parse(&value, package))
+        .collect()
+}
+
+pub fn find_contact(project_path: &str) -> (Option<String>, Option<String>) {
+    let Ok(content) = fs::read_to_string(format!("{}/pls.toml", project_path)) else {
+        return (None, None);
+    };
+    let toml = PackageInfo::parse_pls_toml(&content);
+    (Some(toml.maintainer).filter(|m| !m.is_empty()), Some(toml.homepage).filter(|h| !h.is_empty()))
+}
+
+pub fn find_examples(project_path: &str, name: &str) -> Vec<(String, String)> {
+    let dir = format!("{}/examples", project_path);
+    walk_files(&dir)
+        .into_iter()
+        .map(|rel| (format!("{}/{}", dir, rel), format!("doc/{}/examples/{}", name, rel)))
+        .collect()
+}
+
+pub fn find_dev_files(project_path: &str, build_dirs: &[String]) -> Vec<(String, String)> {
+    let include = format!("{}/include", project_path);
+    let mut found: Vec<(String, String)> =
+        walk_files(&include).into_iter().map(|rel| (format!("{}/{}", include, rel), format!("include/{}", rel))).collect();
+
+    for dir in build_dirs {
+        for rel in walk_files(dir).into_iter().filter(|rel| rel.ends_with(".pc")) {
+            let file_name = rel.rsplit('/').next().unwrap_or(&rel);
+            let dest = format!("lib/pkgconfig/{}", file_name);
+            if !found.iter().any(|(_, d)| *d == dest) {
+                found.push((format!("{}/{}", dir, rel), dest));
+            }
+        }
+    }
+    found
+}
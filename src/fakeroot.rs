@@ -0,0 +1,88 @@
+use std::fs;
+use std::path::Path;
+
+use crate::utils::glob_match;
+
+pub const FILE: &str = "perms";
+
+pub struct Rule {
+    pattern: String,
+    mode: Option<u32>,
+    owner: Option<(u64, u64)>,
+}
+
+fn lookup_id(db: &str, name: &str) -> Option<u64> {
+    if let Ok(id) = name.parse() {
+        return Some(id);
+    }
+    fs::read_to_string(db)
+        .ok()?
+        .lines()
+        .map(|line| line.split(':').collect::<Vec<_>>())
+        .find(|fields| fields.first() == Some(&name))
+        .and_then(|fields| fields.get(2)?.parse().ok())
+}
+
+pub fn parse_rule(pattern: &str, spec: &str) -> Result<Rule, String> {
+    let mut rule = Rule { pattern: pattern.trim_start_matches('/').to_string(), mode: None, owner: None };
+    for token in spec.split_whitespace() {
+        if let Some((user, group)) = token.split_once(':') {
+            let uid = lookup_id("/etc/passwd", user).ok_or_else(|| format!("no user '{}' on this box, use a number", user))?;
+            let gid = lookup_id("/etc/group", group).ok_or_else(|| format!("no group '{}' on this box, use a number", group))?;
+            rule.owner = Some((uid, gid));
+        } else {
+            let mode = u32::from_str_radix(token, 8)
+                .ok()
+                .filter(|mode| *mode <= 0o7777)
+                .ok_or_else(|| format!("'{}' for {} isn't a mode like 0755 or an owner like root:games", token, pattern))?;
+            rule.mode = Some(mode);
+        }
+    }
+    if rule.mode.is_none() && rule.owner.is_none() {
+        return Err(format!("perms for {} say nothing, want a mode and/or user:group", pattern));
+    }
+    Ok(rule)
+}
+
+pub fn from_pls_toml(project_path: &str) -> String {
+    let Ok(content) = fs::read_to_string(format!("{}/pls.toml", project_path)) else {
+        return String::new();
+    };
+    content
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .filter_map(|(key, value)| {
+            let pattern = key.trim().strip_prefix("perms.")?.trim_matches('"');
+            Some(format!("{} {}\n", pattern, value.trim().trim_matches('"').trim_matches('\'')))
+        })
+        .collect()
+}
+
+pub fn take(build_dir: &str) -> Result<Vec<Rule>, String> {
+    let path = Path::new(build_dir).join(FILE);
+    let Ok(content) = fs::read_to_string(&path) else {
+        return Ok(Vec::new());
+    };
+    let _ = fs::remove_file(&path);
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (pattern, spec) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            parse_rule(pattern, spec)
+        })
+        .collect()
+}
+
+pub fn apply(rules: &[Rule], rel: &str, header: &mut tar::Header) {
+    for rule in rules.iter().filter(|rule| glob_match(&rule.pattern, rel)) {
+        if let Some(mode) = rule.mode {
+            header.set_mode(mode);
+        }
+        if let Some((uid, gid)) = rule.owner {
+            header.set_uid(uid);
+            header.set_gid(gid);
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde_json::json;
+
+use crate::alternatives;
+use crate::config::Config;
+use crate::paths::{hooks_dir, is_user, running_as_root};
+use crate::term::{dim, yellow};
+use crate::types::Change;
+use crate::{ROOT, say};
+
+static LIBS_CHANGED: AtomicBool = AtomicBool::new(false);
+
+fn hook_scripts(hook: &str) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(format!("{}/{}", hooks_dir(), hook)) else {
+        return Vec::new();
+    };
+    let mut scripts: Vec<String> = entries
+        .flatten()
+        .filter(|e| e.metadata().is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0))
+        .map(|e| e.path().to_string_lossy().to_string())
+        .collect();
+    scripts.sort();
+    scripts
+}
+
+fn run_hooks(hook: &str, action: &str, change: &Change) -> Result<(), String> {
+    for script in hook_scripts(hook) {
+        let status = Command::new(&script)
+            .env("PLS_HOOK", hook)
+            .env("PLS_ACTION", action)
+            .env("PLS_PACKAGE", &change.name)
+            .env("PLS_VERSION", &change.version)
+            .env("PLS_OLD_VERSION", change.old_version.as_deref().unwrap_or(""))
+            .env("PLS_ROOT", ROOT)
+            .status()
+            .map_err(|e| format!("couldn't run {} hook {}: {}", hook, script, e))?;
+        if !status.success() {
+            return Err(format!("{} hook {} said no ({})", hook, script, status));
+        }
+    }
+    Ok(())
+}
+
+pub fn pre(action: &str, change: &Change) -> Result<(), String> {
+    run_hooks(&format!("pre-{}", action), action, change)
+}
+
+pub fn post(action: &str, change: &Change) {
+    if let Err(e) = run_hooks(&format!("post-{}", action), action, change) {
+        println!("{} {}", yellow("heads up,"), e);
+    }
+}
+
+pub fn libs_changed() {
+    LIBS_CHANGED.store(true, Ordering::Relaxed);
+}
+
+fn refresh_linker_cache() {
+    if !LIBS_CHANGED.swap(false, Ordering::Relaxed) {
+        return;
+    }
+    if is_user() || !running_as_root() {
+        say!("{} make sure {} is in LD_LIBRARY_PATH so programs find its libraries", dim("tip:"), Config::load().libdir());
+        return;
+    }
+    match Command::new("ldconfig").status() {
+        Ok(status) if status.success() => {}
+        Ok(status) => say!("{} ldconfig failed ({}), new libraries may not be found yet", yellow("heads up,"), status),
+        Err(e) => say!("{} couldn't run ldconfig ({}), new libraries may not be found yet", yellow("heads up,"), e),
+    }
+}
+
+pub fn post_transaction(action: &str, changes: &[Change]) {
+    if changes.is_empty() {
+        return;
+    }
+    refresh_linker_cache();
+    alternatives::refresh_all();
+    let Some(script) = Config::load().post_transaction else {
+        return;
+    };
+
+    let summary = json!({ "action": action, "packages": changes });
+    if let Err(e) = run_with_stdin(&script, &summary.to_string()) {
+        println!("{} post_transaction hook {} failed: {}", yellow("heads up,"), script, e);
+    }
+}
+
+fn run_with_stdin(script: &str, input: &str) -> Result<(), String> {
+    let mut child = Command::new(script)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(input.as_bytes());
+    }
+    let status = child.wait().map_err(|e| e.to_string())?;
+    if !status.success() {
+        return Err(status.to_string());
+    }
+    Ok(())
+}
@@ -0,0 +1,133 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+use std::sync::Mutex;
+
+use sha2::{Digest, Sha256};
+
+use crate::config::{Config, MAIN_REPO};
+use crate::exit::{CHECKSUM, Failure, fail};
+use crate::network::fetch_bytes;
+use crate::paths::known_keys_path;
+use crate::term::{bold, confirm, red, yellow};
+use crate::say;
+
+static CHECKED: Mutex<Vec<(String, Option<String>)>> = Mutex::new(Vec::new());
+static ASKING: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+fn known_key(url: &str) -> Option<String> {
+    fs::read_to_string(known_keys_path())
+        .ok()?
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .find(|(known_url, _)| *known_url == url)
+        .map(|(_, key)| key.trim().to_string())
+}
+
+fn lock_known_keys() -> Option<File> {
+    let path = known_keys_path();
+    fs::create_dir_all(Path::new(&path).parent()?).ok()?;
+    let file = File::options().create(true).append(true).open(&path).ok()?;
+    file.lock().ok()?;
+    Some(file)
+}
+
+fn remember(url: &str, key: &str) -> Result<(), String> {
+    let path = known_keys_path();
+    if let Some(dir) = Path::new(&path).parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("couldn't create {}: {}", dir.display(), e))?;
+    }
+    File::options()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(format!("{} {}\n", url, key).as_bytes()))
+        .map_err(|e| format!("couldn't save key to {}: {}", path, e))
+}
+
+pub fn fingerprint(key: &str) -> String {
+    Sha256::digest(key.as_bytes())[..16]
+        .chunks(2)
+        .map(hex::encode)
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+async fn published_key(url: &str) -> Option<String> {
+    let bytes = fetch_bytes(&format!("{}/minisign.pub", url)).await.ok()?;
+    String::from_utf8_lossy(&bytes)
+        .lines()
+        .map(str::trim)
+        .rfind(|line| !line.is_empty() && !line.starts_with("untrusted comment:"))
+        .map(str::to_string)
+}
+
+async fn trusted_key(repo: &str, url: &str) -> Result<Option<String>, Failure> {
+    let (known, _lock) = match known_key(url) {
+        Some(known) => (Some(known), None),
+        None => {
+            let lock = tokio::task::spawn_blocking(lock_known_keys).await.ok().flatten();
+            (known_key(url), lock)
+        }
+    };
+    let published = published_key(url).await;
+
+    match (known, published) {
+        (Some(known), Some(published)) if known != published => {
+            eprintln!("{}", red("!!! the signing key of this repo changed !!!"));
+            eprintln!("  repo: {} ({})", repo, url);
+            eprintln!("  trusted:   {}", fingerprint(&known));
+            eprintln!("  published: {}", fingerprint(&published));
+            eprintln!("  could be a key rotation, could be someone in the middle. sticking with the trusted key,");
+            eprintln!("  if the change is legit remove the line for {} from {}", url, known_keys_path());
+            Ok(Some(known))
+        }
+        (Some(known), _) => Ok(Some(known)),
+        (None, Some(published)) => {
+            say!("{} repo '{}' ({}) signs its packages with a key pls hasn't seen before", yellow("heads up,"), repo, url);
+            say!("  key:         {}", published);
+            say!("  fingerprint: {}", bold(fingerprint(&published)));
+            if !confirm("trust it from now on?") {
+                let setting = if repo == MAIN_REPO { "pubkey".to_string() } else { format!("pubkey.{}", repo) };
+                return Err(fail(CHECKSUM, format!("not trusting the key of repo '{}', set {} in the config to skip asking", repo, setting)));
+            }
+            remember(url, &published)?;
+            say!("saved to {}", known_keys_path());
+            Ok(Some(published))
+        }
+        (None, None) => Ok(None),
+    }
+}
+
+pub async fn key_for(repo: &str, url: &str) -> Result<Option<String>, Failure> {
+    if let Some(key) = Config::load().pubkey_for(repo) {
+        return Ok(Some(key.to_string()));
+    }
+    let checked = || CHECKED.lock().unwrap().iter().find(|(checked, _)| checked == url).map(|(_, key)| key.clone());
+    if let Some(key) = checked() {
+        return Ok(key);
+    }
+
+    let _asking = ASKING.lock().await;
+    if let Some(key) = checked() {
+        return Ok(key);
+    }
+    let key = trusted_key(repo, url).await?;
+    CHECKED.lock().unwrap().push((url.to_string(), key.clone()));
+    Ok(key)
+}
+
+pub fn cosign_verify(args: &[&str], identity: &str, issuer: &str) -> Result<(), Failure> {
+    let output = Command::new("cosign")
+        .args(args)
+        .args(["--certificate-identity", identity, "--certificate-oidc-issuer", issuer])
+        .output()
+        .map_err(|_| fail(CHECKSUM, "cosign isn't installed, get it or drop cosign_identity from the config"))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let reason = stderr.lines().rfind(|line| !line.trim().is_empty()).unwrap_or("no reason given").trim();
+    Err(fail(CHECKSUM, format!("cosign says no: {}", reason)))
+}
@@ -0,0 +1,155 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+
+use crate::network::stream_to_file;
+use crate::paths::cache_dir;
+use crate::say;
+
+const SERVICE_TYPE: &str = "_pls._tcp.local.";
+pub const DEFAULT_PORT: u16 = 7433;
+
+pub fn hostname() -> String {
+    fs::read_to_string("/etc/hostname")
+        .ok()
+        .or_else(|| Command::new("hostname").output().ok().map(|o| String::from_utf8_lossy(&o.stdout).to_string()))
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "pls".to_string())
+}
+
+fn is_safe_file_name(name: &str) -> bool {
+    !name.is_empty() && !name.starts_with('.') && !name.contains('/') && name.ends_with(".pls")
+}
+
+fn handle_request(mut stream: TcpStream) -> io::Result<()> {
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let file_name = parts.next().unwrap_or("").trim_start_matches('/');
+
+    if method == "GET"
+        && is_safe_file_name(file_name)
+        && let Ok(mut file) = File::open(format!("{}/{}", cache_dir(), file_name))
+    {
+        let len = file.metadata()?.len();
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            len
+        )?;
+        io::copy(&mut file, &mut stream)?;
+        return Ok(());
+    }
+
+    stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+}
+
+pub fn serve(port: u16) -> Result<(), String> {
+    let listener = TcpListener::bind(("0.0.0.0", port))
+        .map_err(|e| format!("couldn't listen on port {}: {}", port, e))?;
+
+    let mdns = ServiceDaemon::new().map_err(|e| format!("couldn't start mdns: {}", e))?;
+    let host = hostname();
+    let service = ServiceInfo::new(SERVICE_TYPE, &host, &format!("{}.local.", host), (), port, None)
+        .map_err(|e| format!("couldn't build mdns service: {}", e))?
+        .enable_addr_auto();
+    mdns.register(service)
+        .map_err(|e| format!("couldn't announce on the lan: {}", e))?;
+
+    say!("sharing {} on port {} as '{}', ctrl-c to stop", cache_dir(), port, host);
+
+    for stream in listener.incoming().flatten() {
+        thread::spawn(move || {
+            let _ = handle_request(stream);
+        });
+    }
+
+    Ok(())
+}
+
+fn discover_peers(wait: Duration) -> Vec<String> {
+    let Ok(mdns) = ServiceDaemon::new() else {
+        return Vec::new();
+    };
+    let Ok(receiver) = mdns.browse(SERVICE_TYPE) else {
+        return Vec::new();
+    };
+
+    let mut peers = Vec::new();
+    let deadline = Instant::now() + wait;
+    while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+        match receiver.recv_timeout(left) {
+            Ok(ServiceEvent::ServiceResolved(info)) => {
+                for addr in info.get_addresses_v4() {
+                    let peer = format!("http://{}:{}", addr, info.get_port());
+                    if !peers.contains(&peer) {
+                        peers.push(peer);
+                    }
+                }
+            }
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+
+    let _ = mdns.shutdown();
+    peers
+}
+
+pub async fn fetch_from_peers(cache_path: &str, sha256: &str) -> Option<String> {
+    let file_name = Path::new(cache_path).file_name()?.to_string_lossy().to_string();
+
+    let peers = tokio::task::spawn_blocking(|| discover_peers(Duration::from_millis(1500)))
+        .await
+        .ok()?;
+    if peers.is_empty() {
+        return None;
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(60))
+        .build()
+        .ok()?;
+
+    for peer in &peers {
+        let url = format!("{}/{}", peer, file_name);
+        let Ok(res) = client.get(&url).send().await else {
+            continue;
+        };
+        if !res.status().is_success() {
+            continue;
+        }
+
+        fs::create_dir_all(cache_dir()).ok()?;
+        let fetched = format!("{}.lan", cache_path);
+        match stream_to_file(res, &fetched).await {
+            Ok(sum) if sum == sha256 => {}
+            Ok(_) => {
+                say!("peer {} has a different {}, skipping", peer, file_name);
+                let _ = fs::remove_file(&fetched);
+                continue;
+            }
+            Err(_) => {
+                let _ = fs::remove_file(format!("{}.part", fetched));
+                continue;
+            }
+        }
+        if fs::rename(&fetched, cache_path).is_err() {
+            let _ = fs::remove_file(&fetched);
+            return None;
+        }
+        say!("got {} from {}", file_name, peer);
+        return Some(cache_path.to_string());
+    }
+
+    None
+}
@@ -1,15 +1,48 @@
+mod alternatives;
+mod cache;
+mod changelog;
 mod commands;
+mod completions;
+mod config;
+mod conflicts;
+mod deb;
+mod elf;
+mod exit;
+mod extras;
+mod fakeroot;
+mod hooks;
+mod keys;
+mod lan;
+mod mirrors;
 mod network;
+mod oci;
+mod paths;
+mod plan;
+mod provenance;
+mod resolver;
+mod store;
+mod sysext;
+mod term;
+mod throttle;
+mod transaction;
 mod types;
 mod utils;
+mod versions;
 
 use std::env;
 
+use paths::{config_path, default_prefix, hooks_dir, versions_dir};
+
 pub const REPO_URL: &str = "https://tostcra.github.io/aura-repo";
-pub const CACHE_DIR: &str = "/var/cache/pls";
-pub const PACKAGES_DIR: &str = "/var/lib/pls/packages";
-pub const DB_DIR: &str = "/var/lib/pls/db";
 pub const ROOT: &str = "/";
+pub const VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    " (",
+    env!("PLS_GIT_HASH"),
+    ", built ",
+    env!("PLS_BUILD_DATE"),
+    ")"
+);
 
 fn print_help() {
     println!("pls - package manager that doesn't mess with ya");
@@ -17,32 +50,238 @@ fn print_help() {
     println!("usage: pls <command> [args]");
     println!();
     println!("commands:");
-    println!("  install <pkg>     install a package (name, path, or url)");
-    println!("  remove <pkg>      remove a package");
-    println!("  info <pkg>        show package info");
+    println!("  install <pkg>...  install packages (name, path, url, oci://registry/repo:pkg-version or .plsx),");
+    println!("                    - reads a .pls from stdin");
+    println!("    --with-optional also install optional dependencies");
+    println!("    --force         install even if ignored or built for another arch or libc");
+    println!("    --side-by-side  keep every version under {}/<pkg>/<version>, bin/ gets symlinks", versions_dir());
+    println!("                    (pkg@1.2.0 works if that version is cached)");
+    println!("  remove <pkg>      remove a package, pkg@1.2.0 drops one side by side version");
+    println!("  use <pkg@ver>     switch the active side by side version, plain <pkg> lists them");
+    println!("  alternatives [name]  show commands several packages offer (editor, cc...) and who has them");
+    println!("    set <name> <pkg>   point bin/<name> at pkg's version for good");
+    println!("    auto <name>        go back to the highest priority one");
+    println!("  info <pkg>        show package info (installed, from the repo or a .pls file)");
+    println!("    --notes         show the package's post-install notes");
+    println!("    --files         list what's inside a .pls (mode, size, path) without unpacking it");
+    println!("    --provenance    where and how it was built (source, commit, builder, toolchain, flags)");
+    println!("  search <term>     find repo packages by name or keyword, exact matches first");
+    println!("    --desc          look in descriptions too");
+    println!("  deps <pkg>        show what a package depends on");
+    println!("    --tree          show the full dependency tree");
+    println!("    --remote        everything an install would pull in and how much to download");
+    println!("  why <pkg>         show what keeps an installed package around");
+    println!("  provides <file>   which repo package ships a file (bin/rg, rg or /usr/bin/rg),");
+    println!("                    with -q just the package names, handy for command-not-found hooks");
+    println!("  changelog <pkg>   show what the latest version brings");
+    println!("  extract <pkg.pls> [dir]  unpack a package without installing it");
+    println!("  convert <pkg.pls> --to deb [-o <file.deb>]");
+    println!("                    turn a package into a plain .deb for apt-only boxes (files go to /usr/local)");
+    println!("  run <pkg> [args...]  run a package's binary once without installing it");
+    println!("  try <pkg>         drop into a shell with the package on PATH, gone when you exit");
     println!("  list              list installed packages");
-    println!("  update            update all installed packages");
+    println!("    --explicit      only the ones you asked for");
+    println!("    --deps          only the ones pulled in as dependencies");
+    println!("    --tree          nest dependencies under the packages that pulled them in");
+    println!("    --deprecated    only the ones the repo marked deprecated or end of life, with replacements");
+    println!("  autoremove        remove dependencies nothing needs anymore");
+    println!("  mark <pkg>...     --explicit keeps it around, --auto lets autoremove take it");
+    println!("  update [pkg...]   update installed packages (all, or the ones matching, globs work)");
+    println!("    --exclude <pkg>[,<pkg>...]  skip these this time (globs work)");
+    println!("    --allow-major   take major version jumps without asking (skipped when there's no one to ask)");
+    println!("    --force         update end of life packages too and go ahead even if the disk looks too full");
+    println!("  rollback <pkg>    go back to the previous version kept in the cache");
+    println!("  rebuild <pkg>     build an installed package from the source and commit it records, then reinstall");
+    println!("    --check         only tell whether the rebuild matches what's installed");
+    println!("  audit             check installed packages against the repo's advisories");
+    println!("  verify <pkg>...   check installed files are all there and unchanged");
+    println!("    --all           check every installed package");
+    println!("    --repair        reinstall broken packages from the cache or the repo");
     println!("  add <path>        create package from project");
     println!("    --draft         use debug build instead of release");
+    println!("    --install       install the package right after building it");
+    println!("    --set-version <ver>  stamp a different version than the project");
+    println!("    --bump <part>   bump the project version (patch, minor, major)");
+    println!("    --output <dir>  output to custom directory");
+    println!("    --all           pack every binary of a cargo workspace / cmake / meson project");
+    println!("    --no-man        don't bundle man pages found in doc/, man/ or the build output");
+    println!("    --test          run cargo test / ctest / meson test (or test = \"...\" from pls.toml) first, no package if they fail");
+    println!("  add-bin <file>    wrap a prebuilt binary into a .pls");
+    println!("    --name, --version, --output, --install  same as above");
+    println!("  create <dir>      pack a prepared bin/ include/ lib/ share/ tree into a .pls");
+    println!("    --name <name>   package name (or from <dir>/info)");
+    println!("    --version <ver> package version (or from <dir>/info)");
     println!("    --output <dir>  output to custom directory");
-    println!("  repo update       update index.json from packages/");
+    println!("                    <dir>/perms lines like 'bin/foo 4755 root:root' set modes and owners in the");
+    println!("                    archive, no root needed");
+    println!("  repo update       update index.json (plus index.json.zst and index.bin) from packages/");
+    println!("                    bundles.toml (base = [\"hello\"], work = [\"@base\", \"app\"]) is checked and compiled in");
+    println!("                    deprecated.toml ([old] reason = \"...\", replaced_by = \"new\", eol = \"2027-01-31\")");
+    println!("                    marks packages, installs warn and refuse end of life ones without --force");
+    println!("    --changes <file>  also write what was added/updated/removed as json");
+    println!("    --webhook <url>  announce new and updated packages (discord, slack or any json endpoint)");
+    println!("  repo diff [old] [new]  added, removed and bumped packages with size changes between two");
+    println!("                    index.json files or repo urls (default: the configured repo vs ./index.json)");
+    println!("  repo stats        sizes, ages and loose ends of the repo you're in");
+    println!("  repo gc [--keep <n>] [--dry-run]");
+    println!("                    delete all but the newest n (default 3) versions of each package,");
+    println!("                    keeping ones bundles or deps still ask for, then reindex");
+    println!("  repo publish <target>  update, then push packages and index to https:// (PUT/WebDAV,");
+    println!("                    PLS_PUBLISH_TOKEN for auth), user@host:/path (rsync over ssh), s3://");
+    println!("                    or oci://registry/repo (each .pls becomes an artifact tagged <file name>,");
+    println!("                    login from PLS_OCI_USER/PLS_OCI_TOKEN or ~/.docker/config.json)");
     println!("  bundle <name>     install a bundle (gaming, dev-rust, etc)");
+    println!("                    a member like @base pulls in that whole bundle too");
+    println!("    --force         go ahead even if the disk looks too full");
+    println!("  export-bundle <pkg>... -o <file.plsx>");
+    println!("                    pack packages + deps for offline installs");
+    println!("  export --oci <bundle|pkg>... -o <image.tar>");
+    println!("                    lay packages + deps into /usr/local on a bare base and write an oci image,");
+    println!("                    load it with docker load or push it with skopeo, the base has no libc so");
+    println!("                    binaries need to be static");
+    println!("    --tag <name:tag>  image name (default <pkg>:<version>, or <bundle>:latest)");
+    println!("  export --sysext <bundle|pkg>... [-o <name.raw>]");
+    println!("                    build a systemd-sysext image (erofs, or squashfs) with the files under /usr,");
+    println!("                    for immutable distros, needs mkfs.erofs or mksquashfs");
+    println!("  mirror <url> <dir> clone a whole repo for offline use");
+    println!("  mirrors [--test]  show how the mirrors from pls.conf (mirror = <url>, mirror.<repo> = <url>)");
+    println!("                    did, downloads go to the fastest one that's up to date, retimed hourly");
+    println!("  clean             empty the download cache");
+    println!("    --auto          only evict old packages over cache_max_size");
+    println!("    --unused        only drop packages not installed and not in the repo");
+    println!("    --store         drop files in the store no installed package links to anymore");
+    println!("  serve             share cached packages with the lan");
+    println!("    --port <port>   listen on a custom port");
+    println!("  env               show paths and the active config, handy for bug reports");
+    println!("  completions <shell>  print tab-completion for bash, zsh or fish, package names come");
+    println!("                    from the installed ones and the last index pls saw, so it's instant");
+    println!("                    e.g. pls completions bash > /etc/bash_completion.d/pls");
+    println!("  --version         show version, git hash and build date");
+    println!("  --no-color        plain output (also NO_COLOR=1, or when not a terminal)");
+    println!("  -q, --quiet, --brief  only print one line per thing done, for scripts");
+    println!("  --limit-rate <speed>  cap download speed, e.g. 500K or 2M per second (all downloads combined)");
+    println!("  --on-conflict <keep|replace|abort>  what to do when a package wants a file another one");
+    println!("                    already installed, instead of asking (no terminal to ask means abort)");
+    println!("  --user            install into ~/.local and keep cache, config and state under the XDG dirs");
+    println!("                    (automatic when not running as root and there's no system install)");
     println!();
     println!("supported projects:");
     println!("  Rust      Cargo.toml");
     println!("  C/C++     CMakeLists.txt, meson.build");
     println!("  Any       pls.toml (manual config)");
+    println!("  description and keywords from Cargo.toml or pls.toml land in the repo index for pls search");
+    println!("  maintainer = \"you <you@example.com>\" and homepage = \"...\" in pls.toml (or authors and");
+    println!("  homepage/repository from Cargo.toml) show up in pls info, repo stats flags packages without one");
+    println!("  pls-notes.txt next to any of these is shown after install");
+    println!("  no CHANGELOG.md? commits since the last .pls in the output dir become the new version's changelog");
+    println!("  split = [\"docs\", \"dev\"] in a pls.toml next to any of these also emits <name>-docs.pls");
+    println!("  (man pages, docs, examples/) and <name>-dev.pls (include/, .pc files from the build), split.<kind> = [globs]");
+    println!("  overrides what goes where; split packages install and update together with their parent");
+    println!("  alternatives = [\"editor:bin/nvim:50\"] offers a binary as a shared command, the highest priority");
+    println!("  installed one owns bin/editor unless pls alternatives set picks another");
+    println!("  perms.<glob> = \"4755 root:root\" records modes and owners in the .pls without packing as root");
+    println!();
+    println!("config ({}):", config_path());
+    println!("  repo = <url>      package repo (https:// or file://)");
+    println!("  repo.<name> = <url>  extra repo, first one to have a package wins,");
+    println!("                    pls install <name>/<pkg> to pick, updates stick to where it came from");
+    println!("  pin.<pkg> = <repo>  always take matching packages (globs work) from that repo");
+    println!("  pubkey = <minisign key>  only install packages with a valid <file>.pls.minisig next to them,");
+    println!("                    pubkey.<repo> = <key> does the same for extra repos");
+    println!("                    without one, a repo's minisign.pub is trusted on first use and kept in known_keys");
+    println!("  cosign_identity = <email or uri>  verify <file>.pls.sigstore.json bundles with cosign instead,");
+    println!("  cosign_issuer = <oidc issuer url>  e.g. https://token.actions.githubusercontent.com, both needed,");
+    println!("                    .<repo> or .<registry> versions work like pubkey.<repo>, oci:// installs run cosign verify");
+    println!("  priority.<repo> = <n>  higher wins when repos overlap (default 0), below 0 only when pinned");
+    println!("  prefix = <dir>    where bin/ and share/ go (default {})", default_prefix());
+    println!("  libdir = <dir>    where a package's lib/ goes (default <prefix>/lib), ldconfig runs after");
+    println!("                    changes; packages provide the sonames in there and can depend on");
+    println!("                    them with depend = so:libfoo.so.1");
+    println!("  includedir = <dir>  where a package's include/ goes (default <prefix>/include), pkg-config");
+    println!("                    files in lib/pkgconfig get their prefix, libdir and includedir pointed here");
+    println!("  lan = true        try peers running 'pls serve' before the repo");
+    println!("  cache_max_size = 2G  evict least recently used packages above this");
+    println!("  limit_rate = 2M   default for --limit-rate");
+    println!("  keep_versions = 3  keep this many cached versions per installed package for rollback");
+    println!("  ignore = [\"foo\", \"bar-*\"]  never touch these in update/bundle, install needs --force");
+    println!("  repo_webhook = <url>  default for repo update --webhook");
+    println!("  post_transaction = /path/to/script  run after install/remove/update, gets a json summary on stdin");
+    println!();
+    println!("hooks ({}/{{pre,post}}-{{install,remove,update}}/):", hooks_dir());
+    println!("  executables in there run per package with PLS_PACKAGE, PLS_VERSION, PLS_OLD_VERSION,");
+    println!("  PLS_ACTION and PLS_ROOT set, a failing pre- hook cancels the whole thing");
+    println!();
+    println!("exit codes:");
+    println!("  0 ok, 1 anything else, 2 not found, 3 network, 4 checksum mismatch,");
+    println!("  5 permission denied, 6 conflict, 7 partial failure");
     println!();
     println!("examples:");
     println!("  pls install yplay");
     println!("  pls install https://example.com/app.deb");
+    println!("  pls install https://example.com/app-1.0-x86_64.pls");
     println!("  pls add . --output ~/my-repo/packages/");
     println!("  pls repo update");
 }
 
+fn flag_value<'a>(args: &'a [String], names: &[&str]) -> Option<&'a str> {
+    args.iter()
+        .position(|a| names.contains(&a.as_str()))
+        .and_then(|i| args.get(i + 1))
+        .map(|s| s.as_str())
+}
+
+fn positional_args(args: &[String], value_flags: &[&str]) -> Vec<String> {
+    let mut positional = Vec::new();
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        if value_flags.contains(&arg.as_str()) {
+            rest.next();
+        } else if arg == "-" || !arg.starts_with('-') {
+            positional.push(arg.clone());
+        }
+    }
+    positional
+}
+
 #[tokio::main]
 async fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    let passthrough = match args.iter().skip(1).position(|a| !a.starts_with('-')) {
+        Some(i) if args[i + 1] == "run" => args.split_off((i + 3).min(args.len())),
+        _ => Vec::new(),
+    };
+    let no_color = args.iter().any(|a| a == "--no-color");
+    let quiet = args.iter().any(|a| a == "-q" || a == "--quiet" || a == "--brief");
+    let user = args.iter().any(|a| a == "--user");
+    args.retain(|a| a != "--no-color" && a != "-q" && a != "--quiet" && a != "--brief" && a != "--user");
+    term::init(no_color, quiet);
+    paths::init(user);
+    let limit_rate = match args.iter().position(|a| a == "--limit-rate") {
+        Some(i) => {
+            let value = args.get(i + 1).cloned().unwrap_or_default();
+            args.drain(i..(i + 2).min(args.len()));
+            match utils::parse_size(&value).filter(|rate| *rate > 0) {
+                Some(rate) => Some(rate),
+                None => {
+                    eprintln!("{} --limit-rate wants a speed like 500K or 2M", term::red("nah bro:"));
+                    std::process::exit(exit::FAILURE);
+                }
+            }
+        }
+        None => config::Config::load().limit_rate,
+    };
+    throttle::init(limit_rate);
+    if let Some(i) = args.iter().position(|a| a == "--on-conflict") {
+        let value = args.get(i + 1).cloned().unwrap_or_default();
+        args.drain(i..(i + 2).min(args.len()));
+        match conflicts::parse(&value) {
+            Some(choice) => conflicts::init(choice),
+            None => {
+                eprintln!("{} --on-conflict wants keep, replace or abort", term::red("nah bro:"));
+                std::process::exit(exit::FAILURE);
+            }
+        }
+    }
 
     if args.len() < 2 {
         print_help();
@@ -51,70 +290,315 @@ async fn main() {
 
     let command = &args[1];
 
-    let result = match command.as_str() {
+    let result: Result<(), exit::Failure> = match command.as_str() {
         "help" | "--help" | "-h" => {
             print_help();
             Ok(())
         }
+        "--version" | "-V" | "version" => {
+            println!("pls {}", VERSION);
+            Ok(())
+        }
+        "env" => commands::cmd_env(),
+        "completions" => match args.get(2) {
+            Some(shell) => completions::print(shell),
+            None => Err("completions for which shell? bash, zsh or fish".into()),
+        },
+        "_complete" => {
+            completions::complete(args.get(2).map_or("", |s| s.as_str()), args.get(3).map_or("", |s| s.as_str()));
+            Ok(())
+        }
         "install" => {
-            if args.len() < 3 {
-                Err("install what? give me a package name".to_string())
+            let packages = positional_args(&args[2..], &[]);
+            if packages.is_empty() {
+                Err("install what? give me a package name".into())
             } else {
-                commands::cmd_install(&args[2]).await
+                let opts = commands::InstallOptions {
+                    with_optional: args.iter().any(|a| a == "--with-optional"),
+                    force: args.iter().any(|a| a == "--force"),
+                    side_by_side: args.iter().any(|a| a == "--side-by-side"),
+                };
+                commands::cmd_install(&packages, &opts).await
             }
         }
         "remove" | "rm" => {
             if args.len() < 3 {
-                Err("remove what?".to_string())
+                Err("remove what?".into())
             } else {
                 commands::cmd_remove(&args[2])
             }
         }
+        "use" => match positional_args(&args[2..], &[]).first() {
+            Some(reference) => commands::cmd_use(reference),
+            None => Err("use what? pls use foo@1.2.0".into()),
+        },
+        "alternatives" => {
+            let rest = positional_args(&args[2..], &[]);
+            match rest.iter().map(|s| s.as_str()).collect::<Vec<_>>().as_slice() {
+                [] => alternatives::show(None),
+                ["set", name, package] => alternatives::set(name, package),
+                ["set", ..] => Err("set it to what? pls alternatives set <name> <pkg>".into()),
+                ["auto", name] => alternatives::auto(name),
+                ["auto", ..] => Err("auto what? pls alternatives auto <name>".into()),
+                [name] => alternatives::show(Some(name)),
+                _ => Err("too many args, usage: pls alternatives [name], set <name> <pkg> or auto <name>".into()),
+            }
+        }
         "info" => {
+            let target = positional_args(&args[2..], &[]);
+            match target.first() {
+                Some(pkg) => {
+                    commands::cmd_info(
+                        pkg,
+                        args.iter().any(|a| a == "--notes"),
+                        args.iter().any(|a| a == "--provenance"),
+                        args.iter().any(|a| a == "--files"),
+                    )
+                    .await
+                }
+                None => Err("info about what?".into()),
+            }
+        }
+        "deps" => {
+            let target = positional_args(&args[2..], &[]);
+            match target.first() {
+                Some(pkg) if args.iter().any(|a| a == "--remote") => commands::cmd_deps_remote(pkg).await,
+                Some(pkg) => commands::cmd_deps(pkg, args.iter().any(|a| a == "--tree")).await,
+                None => Err("deps of what?".into()),
+            }
+        }
+        "search" => match positional_args(&args[2..], &[]).first() {
+            Some(term) => commands::cmd_search(term, args.iter().any(|a| a == "--desc")).await,
+            None => Err("search for what?".into()),
+        },
+        "provides" => match positional_args(&args[2..], &[]).first() {
+            Some(file) => commands::cmd_provides(file).await,
+            None => Err("provides what? give me a file like bin/rg".into()),
+        },
+        "why" => match positional_args(&args[2..], &[]).first() {
+            Some(pkg) => commands::cmd_why(pkg).await,
+            None => Err("why what?".into()),
+        },
+        "changelog" => {
+            if args.len() < 3 {
+                Err("changelog of what?".into())
+            } else {
+                commands::cmd_changelog(&args[2]).await
+            }
+        }
+        "run" => match args.get(2) {
+            Some(pkg) => commands::cmd_run(pkg, &passthrough).await,
+            None => Err("run what? usage: pls run <pkg> [args...]".into()),
+        },
+        "try" => match args.get(2) {
+            Some(pkg) => commands::cmd_try(pkg).await,
+            None => Err("try what? usage: pls try <pkg>".into()),
+        },
+        "extract" => {
+            let targets = positional_args(&args[2..], &[]);
+            match targets.first() {
+                Some(pkg) => commands::cmd_extract(pkg, targets.get(1).map(|s| s.as_str())),
+                None => Err("extract what? give me a .pls file".into()),
+            }
+        }
+        "convert" => {
+            let targets = positional_args(&args[2..], &["--to", "-o", "--output"]);
+            match (targets.first(), flag_value(&args, &["--to"])) {
+                (Some(pkg), Some(to)) => commands::cmd_convert(pkg, to, flag_value(&args, &["--output", "-o"])),
+                (Some(_), None) => Err("convert to what? try --to deb".into()),
+                (None, _) => Err("convert what? give me a .pls file".into()),
+            }
+        }
+        "list" | "ls" if args.iter().any(|a| a == "--deprecated") => commands::cmd_list_deprecated().await,
+        "list" | "ls" => {
+            let only = if args.iter().any(|a| a == "--explicit") {
+                Some(types::InstallReason::Explicit)
+            } else if args.iter().any(|a| a == "--deps") {
+                Some(types::InstallReason::Dependency)
+            } else {
+                None
+            };
+            commands::cmd_list(only, args.iter().any(|a| a == "--tree"))
+        }
+        "autoremove" => commands::cmd_autoremove(),
+        "rebuild" => match positional_args(&args[2..], &[]).first() {
+            Some(pkg) => commands::cmd_rebuild(pkg, args.iter().any(|a| a == "--check")),
+            None => Err("rebuild what?".into()),
+        },
+        "rollback" | "downgrade" => {
             if args.len() < 3 {
-                Err("info about what?".to_string())
+                Err("roll back what?".into())
             } else {
-                commands::cmd_info(&args[2])
+                commands::cmd_rollback(&args[2])
             }
         }
-        "list" | "ls" => commands::cmd_list(),
-        "update" => commands::cmd_update().await,
+        "mark" => {
+            let packages = positional_args(&args[2..], &[]);
+            let reason = if args.iter().any(|a| a == "--explicit") {
+                Some(types::InstallReason::Explicit)
+            } else if args.iter().any(|a| a == "--auto") {
+                Some(types::InstallReason::Dependency)
+            } else {
+                None
+            };
+            match reason {
+                Some(_) if packages.is_empty() => Err("mark what?".into()),
+                Some(reason) => commands::cmd_mark(&packages, reason),
+                None => Err("mark it how? --explicit or --auto".into()),
+            }
+        }
+        "update" => {
+            let exclude: Vec<String> = flag_value(&args, &["--exclude"])
+                .map(|list| list.split(',').map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect())
+                .unwrap_or_default();
+            let patterns = positional_args(&args[2..], &["--exclude"]);
+            let allow_major = args.iter().any(|a| a == "--allow-major");
+            commands::cmd_update(&patterns, &exclude, allow_major, args.iter().any(|a| a == "--force")).await
+        }
         "add" => {
             let path = if args.len() >= 3 && !args[2].starts_with('-') {
                 &args[2]
             } else {
                 "."
             };
-            let is_draft = args.iter().any(|a| a == "--draft");
-            let output_dir = args
-                .iter()
-                .position(|a| a == "--output" || a == "-o")
-                .and_then(|i| args.get(i + 1))
-                .map(|s| s.as_str());
+            let opts = commands::AddOptions {
+                is_draft: args.iter().any(|a| a == "--draft"),
+                output_dir: flag_value(&args, &["--output", "-o"]),
+                install: args.iter().any(|a| a == "--install"),
+                set_version: flag_value(&args, &["--set-version"]),
+                bump: flag_value(&args, &["--bump"]),
+                no_man: args.iter().any(|a| a == "--no-man"),
+                all: args.iter().any(|a| a == "--all"),
+                test: args.iter().any(|a| a == "--test"),
+            };
 
-            commands::cmd_add(path, is_draft, output_dir)
+            commands::cmd_add(path, &opts)
+        }
+        "add-bin" => {
+            let files = positional_args(&args[2..], &["--name", "--version", "--output", "-o"]);
+            match files.first() {
+                Some(file) => commands::cmd_add_bin(
+                    file,
+                    flag_value(&args, &["--name"]),
+                    flag_value(&args, &["--version"]),
+                    flag_value(&args, &["--output", "-o"]),
+                    args.iter().any(|a| a == "--install"),
+                ),
+                None => Err("add-bin what? give me a binary".into()),
+            }
+        }
+        "create" => {
+            let dirs = positional_args(&args[2..], &["--name", "--version", "--output", "-o"]);
+            match dirs.first() {
+                Some(dir) => commands::cmd_create(
+                    dir,
+                    flag_value(&args, &["--name"]),
+                    flag_value(&args, &["--version"]),
+                    flag_value(&args, &["--output", "-o"]),
+                ),
+                None => Err("create from what? give me a directory".into()),
+            }
         }
         "repo" => {
             if args.len() < 3 {
-                Err("repo what? try 'pls repo update'".to_string())
+                Err("repo what? try 'pls repo update'".into())
             } else if args[2] == "update" {
-                commands::cmd_repo_update()
+                commands::cmd_repo_update(flag_value(&args, &["--changes"]), flag_value(&args, &["--webhook"])).await
+            } else if args[2] == "diff" {
+                commands::cmd_repo_diff(&positional_args(&args[3..], &[])).await
+            } else if args[2] == "stats" {
+                commands::cmd_repo_stats()
+            } else if args[2] == "gc" {
+                match flag_value(&args, &["--keep"]).map(str::parse::<usize>) {
+                    Some(Err(_)) => Err("--keep wants a number, like --keep 3".into()),
+                    keep => {
+                        let keep = keep.and_then(Result::ok).unwrap_or(3);
+                        commands::cmd_repo_gc(keep, args.iter().any(|a| a == "--dry-run")).await
+                    }
+                }
+            } else if args[2] == "publish" {
+                match args.get(3) {
+                    Some(target) => commands::cmd_repo_publish(target).await,
+                    None => Err("publish where? usage: pls repo publish <https://...|user@host:/path|s3://bucket/path>".into()),
+                }
+            } else {
+                Err(format!("unknown repo command: {}", args[2]).into())
+            }
+        }
+        "audit" => commands::cmd_audit().await,
+        "verify" => {
+            let names = positional_args(&args[2..], &[]);
+            let all = args.iter().any(|a| a == "--all");
+            if names.is_empty() && !all {
+                Err("verify what? give me packages or --all".into())
             } else {
-                Err(format!("unknown repo command: {}", args[2]))
+                commands::cmd_verify(&names, all, args.iter().any(|a| a == "--repair")).await
             }
         }
         "bundle" => {
             if args.len() < 3 {
-                Err("bundle what? try 'pls bundle gaming'".to_string())
+                Err("bundle what? try 'pls bundle gaming'".into())
+            } else {
+                commands::cmd_bundle(&args[2], args.iter().any(|a| a == "--force")).await
+            }
+        }
+        "export-bundle" => {
+            let output = flag_value(&args, &["--output", "-o"]).unwrap_or("bundle.plsx");
+            let names = positional_args(&args[2..], &["-o", "--output"]);
+            if names.is_empty() {
+                Err("export what? give me some package names".into())
             } else {
-                commands::cmd_bundle(&args[2]).await
+                commands::cmd_export_bundle(&names, output).await
             }
         }
-        _ => Err(format!("nah '{}' is not a thing, try 'pls help'", command)),
+        "export" => {
+            let output = flag_value(&args, &["--output", "-o"]);
+            let names = positional_args(&args[2..], &["-o", "--output", "--tag"]);
+            if names.is_empty() {
+                Err("export what? give me a bundle or some package names".into())
+            } else if args.iter().any(|a| a == "--oci") {
+                commands::cmd_export_oci(&names, output.unwrap_or("image.tar"), flag_value(&args, &["--tag"])).await
+            } else if args.iter().any(|a| a == "--sysext") {
+                commands::cmd_export_sysext(&names, output).await
+            } else {
+                Err("export as what? --oci or --sysext, or export-bundle for a .plsx".into())
+            }
+        }
+        "mirrors" => commands::cmd_mirrors(args.iter().any(|a| a == "--test")).await,
+        "mirror" => {
+            if args.len() < 4 {
+                Err("usage: pls mirror <url> <dir>".into())
+            } else {
+                commands::cmd_mirror(&args[2], &args[3]).await
+            }
+        }
+        "clean" => {
+            let auto = args.iter().any(|a| a == "--auto");
+            let unused = args.iter().any(|a| a == "--unused");
+            if args.iter().any(|a| a == "--store") {
+                commands::cmd_clean_store()
+            } else {
+                commands::cmd_clean(auto, unused).await
+            }
+        }
+        "serve" => {
+            match flag_value(&args, &["--port", "-p"]) {
+                Some(p) => p
+                    .parse()
+                    .map_err(|_| format!("'{}' is not a port", p))
+                    .and_then(lan::serve),
+                None => lan::serve(lan::DEFAULT_PORT),
+            }
+            .map_err(exit::Failure::from)
+        }
+        _ => Err(format!("nah '{}' is not a thing, try 'pls help'", command).into()),
     };
 
     if let Err(e) = result {
-        eprintln!("nah bro: {}", e);
-        std::process::exit(1);
+        eprintln!("{} {}", term::red("nah bro:"), e);
+        if e.code == exit::PERMISSION && !paths::is_user() && !paths::running_as_root() {
+            eprintln!("{} the system install is read-only for you, use sudo or --user for your own", term::dim("tip:"));
+        }
+        std::process::exit(e.code);
     }
 }
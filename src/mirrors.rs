@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::OnceCell;
+
+use crate::cache::write_atomic;
+use crate::config::Config;
+use crate::exit::Failure;
+use crate::network::{bad_status, fetch_index_from, unreachable};
+use crate::paths::cache_dir;
+use crate::types::RepoIndex;
+
+const PROBE_EVERY: u64 = 60 * 60;
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+static PICKED: Mutex<Vec<(String, Arc<OnceCell<String>>)>> = Mutex::new(Vec::new());
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Probe {
+    pub url: String,
+    pub latency_ms: Option<u64>,
+    pub behind: usize,
+    pub error: Option<String>,
+}
+
+impl Probe {
+    pub fn healthy(&self) -> bool {
+        self.error.is_none() && self.behind == 0
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Record {
+    pub probed: u64,
+    pub probes: Vec<Probe>,
+}
+
+fn results_path() -> String {
+    format!("{}/mirrors.json", cache_dir())
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn load() -> HashMap<String, Record> {
+    fs::read(results_path()).ok().and_then(|bytes| serde_json::from_slice(&bytes).ok()).unwrap_or_default()
+}
+
+fn save(records: &HashMap<String, Record>) {
+    let _ = fs::create_dir_all(cache_dir());
+    if let Ok(json) = serde_json::to_vec_pretty(records) {
+        let _ = write_atomic(&results_path(), &json);
+    }
+}
+
+pub fn last(repo: &str) -> Option<Record> {
+    load().remove(repo)
+}
+
+pub fn forget(repo: &str, origin: &str) {
+    let mut picked = PICKED.lock().unwrap();
+    picked.retain(|(picked, _)| picked != repo);
+    picked.push((repo.to_string(), Arc::new(OnceCell::new_with(Some(origin.to_string())))));
+    drop(picked);
+
+    let mut records = load();
+    if records.remove(repo).is_some() {
+        save(&records);
+    }
+}
+
+pub fn age(record: &Record) -> u64 {
+    now().saturating_sub(record.probed)
+}
+
+async fn head(url: &str) -> Result<(), Failure> {
+    if let Some(path) = url.strip_prefix("file://") {
+        return fs::metadata(path).map(|_| ()).map_err(|e| format!("{}: {}", path, e).into());
+    }
+    let res = reqwest::Client::new().head(url).timeout(PROBE_TIMEOUT).send().await.map_err(|e| unreachable(url, e))?;
+    if !res.status().is_success() {
+        return Err(bad_status(url, res.status()));
+    }
+    Ok(())
+}
+
+async fn probe(url: String, reference: Arc<Option<RepoIndex>>) -> Probe {
+    let started = Instant::now();
+    if let Err(e) = head(&format!("{}/index.json", url)).await {
+        return Probe { url, latency_ms: None, behind: 0, error: Some(e.message) };
+    }
+    let latency_ms = Some(started.elapsed().as_millis() as u64);
+
+    let index = match tokio::time::timeout(PROBE_TIMEOUT * 4, fetch_index_from(&url)).await {
+        Ok(Ok(index)) => index,
+        Ok(Err(e)) => return Probe { url, latency_ms, behind: 0, error: Some(e.message) },
+        Err(_) => return Probe { url, latency_ms, behind: 0, error: Some("index took too long".to_string()) },
+    };
+    let behind = match reference.as_ref() {
+        Some(reference) => reference
+            .packages
+            .iter()
+            .filter(|(name, meta)| index.packages.get(*name).is_none_or(|m| m.version != meta.version))
+            .count(),
+        None => 0,
+    };
+    Probe { url, latency_ms, behind, error: None }
+}
+
+pub async fn probe_all(repo: &str, origin: &str, mirrors: &[String]) -> Vec<Probe> {
+    let reference = Arc::new(fetch_index_from(origin).await.ok());
+    let handles: Vec<_> = std::iter::once(origin.to_string())
+        .chain(mirrors.iter().cloned())
+        .map(|url| tokio::spawn(probe(url, reference.clone())))
+        .collect();
+    let mut probes = Vec::new();
+    for handle in handles {
+        if let Ok(probe) = handle.await {
+            probes.push(probe);
+        }
+    }
+
+    let mut records = load();
+    records.insert(repo.to_string(), Record { probed: now(), probes: probes.clone() });
+    save(&records);
+    probes
+}
+
+pub fn fastest(probes: &[Probe]) -> Option<&Probe> {
+    probes.iter().filter(|probe| probe.healthy()).min_by_key(|probe| probe.latency_ms.unwrap_or(u64::MAX))
+}
+
+pub async fn pick(repo: &str, origin: &str) -> String {
+    let cell = {
+        let mut picked = PICKED.lock().unwrap();
+        match picked.iter().find(|(picked, _)| picked == repo) {
+            Some((_, cell)) => cell.clone(),
+            None => {
+                let cell = Arc::new(OnceCell::new());
+                picked.push((repo.to_string(), cell.clone()));
+                cell
+            }
+        }
+    };
+    cell.get_or_init(|| choose(repo, origin)).await.clone()
+}
+
+async fn choose(repo: &str, origin: &str) -> String {
+    let mirrors = Config::load().mirrors_for(repo);
+    if mirrors.is_empty() {
+        return origin.to_string();
+    }
+    let fresh = last(repo).filter(|record| {
+        age(record) < PROBE_EVERY && record.probes.iter().map(|p| &p.url).eq(std::iter::once(&origin.to_string()).chain(&mirrors))
+    });
+    let probes = match fresh {
+        Some(record) => record.probes,
+        None => probe_all(repo, origin, &mirrors).await,
+    };
+    fastest(&probes).map(|probe| probe.url.clone()).unwrap_or_else(|| origin.to_string())
+}
@@ -1,47 +1,381 @@
-use std::fs;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
 use std::path::Path;
 use std::process::{self, Command};
 
-use crate::types::RepoIndex;
-use crate::utils::{calculate_sha256, create_package, resolve_package_path};
-use crate::{CACHE_DIR, REPO_URL};
+use chrono::{DateTime, Utc};
+use minisign_verify::{PublicKey, Signature};
+use sha2::{Digest, Sha256};
 
-pub async fn fetch_index() -> Result<RepoIndex, String> {
-    let res = reqwest::get(format!("{}/index.json", REPO_URL))
-        .await
-        .map_err(|e| e.to_string())?;
-    let text = res.text().await.map_err(|e| e.to_string())?;
-    let index = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+use crate::cache::{cache_path, lock, remember_names, touch, write_atomic};
+use crate::config::{Config, MAIN_REPO};
+use crate::exit::{CHECKSUM, FAILURE, Failure, NETWORK, NOT_FOUND, fail, io_fail};
+use crate::keys;
+use crate::lan::fetch_from_peers;
+use crate::mirrors;
+use crate::oci;
+use crate::paths::{cache_dir, tmp_dir};
+use crate::term::yellow;
+use crate::throttle;
+use crate::types::{Advisories, PackageMeta, RepoIndex};
+use crate::utils::{
+    calculate_sha256, create_package, decode_binary_index, host_arch, read_package_info, resolve_package_path, valid_package_name,
+    valid_version,
+};
+use crate::say;
+
+pub async fn fetch_index() -> Result<RepoIndex, Failure> {
+    let config = Config::load();
+    let repos = config.repos();
+    let mut rest = repos.iter();
+    let (name, url) = rest.next().cloned().unwrap_or_default();
+    let mut index = fetch_index_from(&url).await?.for_arch(host_arch()).tag(&name, &url);
+    for (name, url) in rest {
+        match fetch_index_from(url).await {
+            Ok(other) => index.merge(other.for_arch(host_arch()).tag(name, url)),
+            Err(e) => say!("{} skipping repo {}: {}", yellow("heads up,"), name, e),
+        }
+    }
+
+    let names: HashSet<String> = index
+        .by_repo
+        .keys()
+        .filter_map(|key| key.split_once('/'))
+        .map(|(_, name)| name.to_string())
+        .collect();
+    index.packages = names
+        .into_iter()
+        .filter_map(|name| {
+            let available: Vec<&str> = repos
+                .iter()
+                .map(|(repo, _)| repo.as_str())
+                .filter(|repo| index.by_repo.contains_key(&format!("{}/{}", repo, name)))
+                .collect();
+            let repo = config.pick_repo(&name, &available)?;
+            let meta = index.by_repo[&format!("{}/{}", repo, name)].clone();
+            Some((name, meta))
+        })
+        .collect();
+    remember_names(index.packages.keys());
     Ok(index)
 }
 
-pub async fn download_package(name: &str) -> Result<String, String> {
-    let url = format!("{}/packages/{}.pls", REPO_URL, name.trim());
-    let res = reqwest::get(url).await.map_err(|e| e.to_string())?;
-    let bytes = res.bytes().await.map_err(|e| e.to_string())?;
-    fs::create_dir_all(CACHE_DIR).map_err(|e| e.to_string())?;
-    let file_path = format!("{}/{}.pls", CACHE_DIR, name);
-    fs::write(&file_path, bytes).map_err(|e| e.to_string())?;
+fn host_of(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_string))
+        .unwrap_or_else(|| url.to_string())
+}
+
+pub fn unreachable(url: &str, e: reqwest::Error) -> Failure {
+    let host = host_of(url);
+    let mut cause: &dyn std::error::Error = &e;
+    while let Some(source) = cause.source() {
+        cause = source;
+    }
+    let chain = format!("{:?}", e);
+    let message = if e.is_timeout() {
+        format!("{} took too long to answer", host)
+    } else if chain.contains("dns error") || chain.contains("failed to lookup address") {
+        format!("couldn't look up {}, check your dns or the repo url", host)
+    } else if e.is_connect() {
+        format!("couldn't connect to {}: {}", host, cause)
+    } else if e.is_body() || e.is_decode() {
+        format!("lost the connection to {} mid-download: {}", host, cause)
+    } else {
+        format!("request to {} failed: {}", host, cause)
+    };
+    fail(NETWORK, message)
+}
+
+pub fn bad_status(url: &str, status: reqwest::StatusCode) -> Failure {
+    let host = host_of(url);
+    let (code, message) = match status.as_u16() {
+        404 | 410 => (NOT_FOUND, format!("{} isn't there ({})", url, status)),
+        401 | 403 => (NETWORK, format!("{} won't let us in ({})", host, status)),
+        500..=599 => (NETWORK, format!("{} is having a bad day ({})", host, status)),
+        _ => (NETWORK, format!("{} answered {} for {}", host, status, url)),
+    };
+    fail(code, message)
+}
+
+fn unreadable(path: &str, e: io::Error) -> Failure {
+    if e.kind() == io::ErrorKind::NotFound {
+        return fail(NOT_FOUND, format!("{} isn't there", path));
+    }
+    fail(FAILURE, format!("couldn't read {}: {}", path, e))
+}
+
+pub async fn fetch_bytes(url: &str) -> Result<Vec<u8>, Failure> {
+    if let Some(path) = url.strip_prefix("file://") {
+        return fs::read(path).map_err(|e| unreadable(path, e));
+    }
+    let mut res = reqwest::get(url).await.map_err(|e| unreachable(url, e))?;
+    if !res.status().is_success() {
+        return Err(bad_status(url, res.status()));
+    }
+    let mut bytes = Vec::new();
+    while let Some(chunk) = res.chunk().await.map_err(|e| unreachable(url, e))? {
+        throttle::pace(chunk.len()).await;
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+pub const FILES_DB: &str = "files.json.zst";
+
+pub async fn fetch_files_db(repo: &str) -> Result<BTreeMap<String, Vec<String>>, Failure> {
+    let bytes = fetch_bytes(&format!("{}/{}", repo, FILES_DB)).await?;
+    let json = zstd::decode_all(bytes.as_slice()).map_err(|e| fail(FAILURE, format!("{} is broken: {}", FILES_DB, e)))?;
+    serde_json::from_slice(&json).map_err(|e| fail(FAILURE, format!("{} is broken: {}", FILES_DB, e)))
+}
+
+async fn modified(url: &str) -> Option<DateTime<Utc>> {
+    if let Some(path) = url.strip_prefix("file://") {
+        return fs::metadata(path).and_then(|meta| meta.modified()).ok().map(DateTime::from);
+    }
+    let res = reqwest::Client::new().head(url).send().await.ok()?;
+    let stamp = res.headers().get(reqwest::header::LAST_MODIFIED)?.to_str().ok()?;
+    DateTime::parse_from_rfc2822(stamp).ok().map(|stamp| stamp.with_timezone(&Utc))
+}
+
+async fn older_than(url: &str, json: Option<DateTime<Utc>>) -> bool {
+    let Some(json) = json else {
+        return false;
+    };
+    modified(url).await.is_some_and(|derived| derived < json)
+}
+
+pub async fn fetch_index_from(repo: &str) -> Result<RepoIndex, Failure> {
+    let json = modified(&format!("{}/index.json", repo)).await;
+    let binary = format!("{}/index.bin", repo);
+    if !older_than(&binary, json).await
+        && let Ok(bytes) = fetch_bytes(&binary).await
+        && let Some(index) = decode_binary_index(&bytes)
+    {
+        return Ok(index);
+    }
+
+    let zst = format!("{}/index.json.zst", repo);
+    let compressed = match older_than(&zst, json).await {
+        true => None,
+        false => fetch_bytes(&zst).await.ok().and_then(|bytes| zstd::decode_all(bytes.as_slice()).ok()),
+    };
+    let bytes = match compressed {
+        Some(bytes) => bytes,
+        None => match fetch_bytes(&format!("{}/index.json", repo)).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.code == NOT_FOUND => return Err(fail(NOT_FOUND, format!("no index at {}, is that a pls repo?", repo))),
+            Err(e) => return Err(e),
+        },
+    };
+    let index = serde_json::from_slice(&bytes).map_err(|e| fail(FAILURE, format!("index from {} is broken: {}", repo, e)))?;
+    Ok(index)
+}
+
+pub async fn fetch_advisories() -> Result<Option<Advisories>, Failure> {
+    let url = format!("{}/advisories.json", Config::load().repo);
+    let text = if let Some(path) = url.strip_prefix("file://") {
+        match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("couldn't read {}: {}", path, e).into()),
+        }
+    } else {
+        let res = reqwest::get(&url).await.map_err(|e| unreachable(&url, e))?;
+        if res.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !res.status().is_success() {
+            return Err(bad_status(&url, res.status()));
+        }
+        res.text().await.map_err(|e| unreachable(&url, e))?
+    };
+    let advisories = serde_json::from_str(&text).map_err(|e| fail(FAILURE, format!("advisories.json is broken: {}", e)))?;
+    Ok(Some(advisories))
+}
+
+pub async fn download_package(name: &str, pkg_meta: &PackageMeta) -> Result<String, Failure> {
+    let origin = if pkg_meta.repo_url.is_empty() { Config::load().repo } else { pkg_meta.repo_url.clone() };
+    let repo = if pkg_meta.repo.is_empty() { MAIN_REPO } else { pkg_meta.repo.as_str() };
+    let base = mirrors::pick(repo, &origin).await;
+    match download_from(&base, name, pkg_meta).await {
+        Err(e) if base != origin => {
+            say!("{} mirror {} let us down ({}), going to {}", yellow("heads up,"), base, e, origin);
+            mirrors::forget(repo, &origin);
+            download_from(&origin, name, pkg_meta).await
+        }
+        result => result,
+    }
+}
+
+async fn download_from(repo: &str, name: &str, pkg_meta: &PackageMeta) -> Result<String, Failure> {
+    let url = format!("{}/packages/{}", repo, pkg_meta.file_name(name.trim()));
+    fs::create_dir_all(cache_dir()).map_err(|e| e.to_string())?;
+    let file_path = cache_path(name, &pkg_meta.version);
+
+    let sha256 = if let Some(path) = url.strip_prefix("file://") {
+        copy_into_place(path, &file_path)?;
+        calculate_sha256(&file_path).map_err(|e| format!("couldn't hash {}: {}", file_path, e))?
+    } else {
+        let res = reqwest::get(&url).await.map_err(|e| unreachable(&url, e))?;
+        if !res.status().is_success() {
+            return Err(bad_status(&url, res.status()));
+        }
+        stream_to_file(res, &file_path).await?
+    };
+    let verified = if pkg_meta.b3sum.is_empty() { sha256 == pkg_meta.sha256 } else { pkg_meta.matches(&file_path) };
+    if !verified {
+        let _ = fs::remove_file(&file_path);
+        return Err(fail(CHECKSUM, format!("checksum mismatch for {}, the repo or the network is lying", name)));
+    }
     Ok(file_path)
 }
 
-pub async fn download_deb(url: &str, name: &str) -> Result<String, String> {
-    println!("downloading from debian...");
-    let res = reqwest::get(url).await.map_err(|e| e.to_string())?;
+pub async fn stream_to_file(mut res: reqwest::Response, dest: &str) -> Result<String, Failure> {
+    let url = res.url().to_string();
+    let partial = format!("{}.part", dest);
+    let mut file = File::create(&partial).map_err(|e| format!("couldn't create {}: {}", partial, e))?;
+    let mut hasher = Sha256::new();
+
+    while let Some(chunk) = res.chunk().await.map_err(|e| unreachable(&url, e))? {
+        throttle::pace(chunk.len()).await;
+        hasher.update(&chunk);
+        file.write_all(&chunk).map_err(|e| format!("couldn't write {}: {}", partial, e))?;
+    }
+    file.sync_all().map_err(|e| format!("couldn't write {}: {}", partial, e))?;
+
+    fs::rename(&partial, dest).map_err(|e| format!("couldn't move {} into place: {}", dest, e))?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+pub async fn post_json(url: &str, body: &serde_json::Value) -> Result<(), String> {
+    let res = reqwest::Client::new()
+        .post(url)
+        .header("Content-Type", "application/json")
+        .body(body.to_string())
+        .send()
+        .await
+        .map_err(|e| unreachable(url, e).message)?;
+    if !res.status().is_success() {
+        return Err(bad_status(url, res.status()).message);
+    }
+    Ok(())
+}
 
+pub async fn upload_file(url: &str, path: &str) -> Result<(), Failure> {
+    let body = fs::read(path).map_err(|e| format!("couldn't read {}: {}", path, e))?;
+    let mut req = reqwest::Client::new().put(url).body(body);
+    if let Ok(token) = std::env::var("PLS_PUBLISH_TOKEN") {
+        req = req.bearer_auth(token);
+    }
+    let res = req.send().await.map_err(|e| unreachable(url, e))?;
     if !res.status().is_success() {
-        return Err(format!("failed to download: {}", res.status()));
+        return Err(fail(NETWORK, format!("upload of {} refused: {}", path, bad_status(url, res.status()).message)));
     }
+    Ok(())
+}
 
-    let bytes = res.bytes().await.map_err(|e| e.to_string())?;
+fn copy_into_place(source: &str, dest: &str) -> Result<(), Failure> {
+    let partial = format!("{}.part", dest);
+    fs::copy(source, &partial).map_err(|e| format!("couldn't copy {}: {}", source, e))?;
+    fs::rename(&partial, dest).map_err(|e| io_fail(&e, format!("couldn't move {} into place: {}", dest, e)))
+}
+
+pub async fn download_resumable(url: &str, dest: &str) -> Result<(), Failure> {
+    if let Some(path) = url.strip_prefix("file://") {
+        return copy_into_place(path, dest);
+    }
+
+    let partial = format!("{}.part", dest);
+    let have = fs::metadata(&partial).map(|m| m.len()).unwrap_or(0);
+
+    let client = reqwest::Client::new();
+    let mut req = client.get(url);
+    if have > 0 {
+        req = req.header("Range", format!("bytes={}-", have));
+    }
+    let mut res = req.send().await.map_err(|e| unreachable(url, e))?;
+
+    // nothing left past what we have, the checksum will tell whether it's the whole thing
+    if have > 0 && res.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+        return fs::rename(&partial, dest).map_err(|e| io_fail(&e, format!("couldn't move {} into place: {}", dest, e)));
+    }
+    let resuming = res.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+    if !res.status().is_success() {
+        return Err(bad_status(url, res.status()));
+    }
+    if resuming {
+        say!("  resuming from {} bytes", have);
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resuming)
+        .truncate(!resuming)
+        .open(&partial)
+        .map_err(|e| format!("couldn't open {}: {}", partial, e))?;
+
+    while let Some(chunk) = res.chunk().await.map_err(|e| unreachable(url, e))? {
+        throttle::pace(chunk.len()).await;
+        file.write_all(&chunk).map_err(|e| format!("couldn't write {}: {}", partial, e))?;
+    }
+    file.sync_all().map_err(|e| format!("couldn't write {}: {}", partial, e))?;
+
+    fs::rename(&partial, dest).map_err(|e| format!("couldn't move {} into place: {}", dest, e))?;
+    Ok(())
+}
+
+pub async fn download_pls(url: &str) -> Result<String, Failure> {
+    let file_name = url.split(['?', '#']).next().unwrap_or(url).rsplit('/').next().unwrap_or(url);
+    fs::create_dir_all(cache_dir()).map_err(|e| e.to_string())?;
+    let dest = format!("{}/{}", cache_dir(), file_name);
+
+    let _lock = lock(&dest).await?;
+    say!("downloading {}...", file_name);
+    download_resumable(url, &dest).await?;
+    let sha256 = calculate_sha256(&dest).map_err(|e| format!("couldn't hash {}: {}", dest, e))?;
+    into_cache(&dest, url, &sha256)
+}
+
+pub fn into_cache(dest: &str, source: &str, sha256: &str) -> Result<String, Failure> {
+    let Ok(info) = read_package_info(dest) else {
+        let _ = fs::remove_file(dest);
+        return Err(format!("{} isn't a .pls", source).into());
+    };
+    if !valid_package_name(&info.name) || !valid_version(&info.version) {
+        let _ = fs::remove_file(dest);
+        return Err(format!("{} says it is {} v{}, pls won't cache that", source, info.name, info.version).into());
+    }
+    say!("  {} v{}, sha256 {}", info.name, info.version, sha256);
+
+    let cached = cache_path(&info.name, &info.version);
+    if cached != dest {
+        fs::rename(dest, &cached).map_err(|e| format!("couldn't move {} into the cache: {}", dest, e))?;
+    }
+    Ok(cached)
+}
+
+pub async fn download_deb(url: &str, name: &str) -> Result<String, Failure> {
+    if cfg!(target_os = "macos") {
+        return Err(fail(NOT_FOUND, format!("{} is a .deb, those only work on linux", name)));
+    }
+    say!("downloading from debian...");
+    let res = reqwest::get(url).await.map_err(|e| unreachable(url, e))?;
+
+    if !res.status().is_success() {
+        return Err(bad_status(url, res.status()));
+    }
 
     let pid = process::id();
-    let deb_dir = format!("/tmp/pls-deb-{}", pid);
+    let deb_dir = format!("{}/pls-deb-{}-{}", tmp_dir(), pid, name);
     let _ = fs::remove_dir_all(&deb_dir);
     fs::create_dir_all(&deb_dir).map_err(|e| e.to_string())?;
 
     let deb_path = format!("{}/package.deb", &deb_dir);
-    fs::write(&deb_path, &bytes).map_err(|e| e.to_string())?;
+    stream_to_file(res, &deb_path).await?;
 
     let status = Command::new("ar")
         .args(["x", &deb_path])
@@ -50,7 +384,7 @@ pub async fn download_deb(url: &str, name: &str) -> Result<String, String> {
         .map_err(|_| "ar not found, install binutils")?;
 
     if !status.success() {
-        return Err("failed to extract .deb".to_string());
+        return Err("failed to extract .deb".into());
     }
 
     let data_tar = if Path::new(&format!("{}/data.tar.xz", &deb_dir)).exists() {
@@ -60,7 +394,7 @@ pub async fn download_deb(url: &str, name: &str) -> Result<String, String> {
     } else if Path::new(&format!("{}/data.tar.gz", &deb_dir)).exists() {
         format!("{}/data.tar.gz", &deb_dir)
     } else {
-        return Err("couldn't find data.tar in .deb".to_string());
+        return Err("couldn't find data.tar in .deb".into());
     };
 
     let extract_dir = format!("{}/extract", &deb_dir);
@@ -72,10 +406,10 @@ pub async fn download_deb(url: &str, name: &str) -> Result<String, String> {
         .map_err(|e| e.to_string())?;
 
     if !status.success() {
-        return Err("failed to extract data.tar".to_string());
+        return Err("failed to extract data.tar".into());
     }
 
-    let build_dir = format!("/tmp/pls-deb-build-{}", pid);
+    let build_dir = format!("{}/pls-deb-build-{}-{}", tmp_dir(), pid, name);
     let _ = fs::remove_dir_all(&build_dir);
     fs::create_dir_all(format!("{}/bin", &build_dir)).map_err(|e| e.to_string())?;
 
@@ -87,48 +421,150 @@ pub async fn download_deb(url: &str, name: &str) -> Result<String, String> {
 
     let mut found_binary = false;
     for bin_dir in &bin_dirs {
-        if Path::new(bin_dir).exists() {
-            if let Ok(entries) = fs::read_dir(bin_dir) {
-                for entry in entries.flatten() {
-                    let src = entry.path();
-                    if src.is_file() {
-                        let dest = format!("{}/bin/{}", &build_dir, entry.file_name().to_string_lossy());
-                        let _ = fs::copy(&src, &dest);
-                        found_binary = true;
-                    }
+        if Path::new(bin_dir).exists()
+            && let Ok(entries) = fs::read_dir(bin_dir)
+        {
+            for entry in entries.flatten() {
+                let src = entry.path();
+                if src.is_file() {
+                    let dest = format!("{}/bin/{}", &build_dir, entry.file_name().to_string_lossy());
+                    let _ = fs::copy(&src, &dest);
+                    found_binary = true;
                 }
             }
         }
     }
 
     if !found_binary {
-        return Err("no binaries found in .deb".to_string());
+        return Err("no binaries found in .deb".into());
     }
 
     let info_content = format!("name = {}\nversion = 1.0.0\n", name);
     fs::write(format!("{}/info", &build_dir), info_content).map_err(|e| e.to_string())?;
 
-    fs::create_dir_all(CACHE_DIR).map_err(|e| e.to_string())?;
-    let pls_path = format!("{}/{}.pls", CACHE_DIR, name);
-    create_package(&build_dir, &pls_path).map_err(|e| e.to_string())?;
+    fs::create_dir_all(cache_dir()).map_err(|e| e.to_string())?;
+    let pls_path = cache_path(name, "1.0.0");
+    let _lock = lock(&pls_path).await?;
+    create_package(&build_dir, &pls_path, &[]).map_err(|e| e.to_string())?;
 
     let _ = fs::remove_dir_all(&deb_dir);
     let _ = fs::remove_dir_all(&build_dir);
 
-    println!("converted deb to pls!");
+    say!("converted deb to pls!");
     Ok(pls_path)
 }
 
-pub async fn resolve_or_download(name: &str) -> Result<String, String> {
+fn signature_holds(key: &PublicKey, sig_path: &str, path: &str) -> bool {
+    let Some(sig) = fs::read_to_string(sig_path).ok().and_then(|content| Signature::decode(&content).ok()) else {
+        return false;
+    };
+    let (Ok(mut verifier), Ok(mut file)) = (key.verify_stream(&sig), File::open(path)) else {
+        return false;
+    };
+    let mut buffer = vec![0u8; 1024 * 1024];
+    loop {
+        match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => verifier.update(&buffer[..n]),
+            Err(_) => return false,
+        }
+    }
+    verifier.finalize().is_ok()
+}
+
+pub const SIGNATURES: [&str; 2] = ["minisig", "sigstore.json"];
+
+pub async fn fetch_signature(url: &str, dest: &str, kind: &str) -> Result<(), Failure> {
+    let bytes = fetch_bytes(&format!("{}.{}", url, kind)).await?;
+    write_atomic(&format!("{}.{}", dest, kind), &bytes).map_err(|e| io_fail(&e, format!("couldn't save signature for {}: {}", dest, e)))
+}
+
+async fn verify_signature(name: &str, pkg_meta: &PackageMeta, path: &str) -> Result<(), Failure> {
+    let repo = if pkg_meta.repo.is_empty() { MAIN_REPO } else { pkg_meta.repo.as_str() };
+    let config = Config::load();
+    let base = if pkg_meta.repo_url.is_empty() { config.repo.clone() } else { pkg_meta.repo_url.clone() };
+    let url = format!("{}/packages/{}", base, pkg_meta.file_name(name));
+
+    if let Some((identity, issuer)) = config.cosign_for(repo) {
+        let bundle = format!("{}.sigstore.json", path);
+        if !Path::new(&bundle).exists() {
+            fetch_signature(&url, path, "sigstore.json").await.map_err(|e| {
+                fail(CHECKSUM, format!("repo '{}' wants cosign signatures but {} has no sigstore bundle ({})", repo, name, e))
+            })?;
+        }
+        if let Err(e) = keys::cosign_verify(&["verify-blob", "--bundle", &bundle, path], identity, issuer) {
+            let _ = fs::remove_file(&bundle);
+            let _ = fs::remove_file(path);
+            return Err(fail(CHECKSUM, format!("{} isn't signed by {}: {}", name, identity, e)));
+        }
+        return Ok(());
+    }
+
+    let Some(key) = keys::key_for(repo, &base).await? else {
+        return Ok(());
+    };
+    let key = PublicKey::from_base64(&key).map_err(|e| format!("pubkey for repo '{}' is broken: {}", repo, e))?;
+
+    let sig_path = format!("{}.minisig", path);
+    if !Path::new(&sig_path).exists() {
+        fetch_signature(&url, path, "minisig").await.map_err(|e| {
+            fail(CHECKSUM, format!("repo '{}' has a pubkey but {} isn't signed ({})", repo, name, e))
+        })?;
+    }
+
+    if !signature_holds(&key, &sig_path, path) {
+        let _ = fs::remove_file(&sig_path);
+        let _ = fs::remove_file(path);
+        return Err(fail(CHECKSUM, format!("bad signature on {}, someone messed with it", name)));
+    }
+    Ok(())
+}
+
+pub async fn fetch_package(name: &str, pkg_meta: &PackageMeta) -> Result<String, Failure> {
+    let _lock = lock(&cache_path(name, &pkg_meta.version)).await?;
+    let path = fetch_checked(name, pkg_meta).await?;
+    verify_signature(name, pkg_meta, &path).await?;
+    Ok(path)
+}
+
+async fn fetch_checked(name: &str, pkg_meta: &PackageMeta) -> Result<String, Failure> {
+    let cached = cache_path(name, &pkg_meta.version);
+
+    if Path::new(&cached).exists() && pkg_meta.matches(&cached) {
+        say!("using cached {}...", name);
+        touch(&cached);
+        return Ok(cached);
+    }
+
+    if Config::load().lan {
+        say!("asking the lan for {}...", name);
+        if let Some(path) = fetch_from_peers(&cached, &pkg_meta.sha256).await {
+            return Ok(path);
+        }
+    }
+
+    say!("downloading {}...", name);
+    download_package(name, pkg_meta).await
+}
+
+pub async fn resolve_or_download(name: &str) -> Result<String, Failure> {
     if let Some(path) = resolve_package_path(name) {
         return Ok(path);
     }
 
+    if name.starts_with("oci://") {
+        return oci::fetch(name).await;
+    }
+
+    if name.starts_with("http") && name.split(['?', '#']).next().unwrap_or(name).ends_with(".pls") {
+        return download_pls(name).await;
+    }
+
     if name.ends_with(".deb") || name.starts_with("http") {
         let url = name;
         let pkg_name = name
             .split('/')
-            .last()
+            .next_back()
             .unwrap_or(name)
             .trim_end_matches(".deb")
             .split('_')
@@ -137,24 +573,58 @@ pub async fn resolve_or_download(name: &str) -> Result<String, String> {
         return download_deb(url, pkg_name).await;
     }
 
-    println!("lemme check the repo...");
+    say!("lemme check the repo...");
     let index = fetch_index().await?;
 
     if let Some(pkg_meta) = index.packages.get(name) {
-        let cache_path = format!("{}/{}.pls", CACHE_DIR, name);
+        return fetch_package(name, pkg_meta).await;
+    }
 
-        if Path::new(&cache_path).exists() {
-            if let Ok(local_sha) = calculate_sha256(&cache_path) {
-                if local_sha == pkg_meta.sha256 {
-                    println!("using cached {}...", name);
-                    return Ok(cache_path);
-                }
+    Err(fail(NOT_FOUND, format!("'{}' not found in repo. try: pls install <url-to-deb>", name)))
+}
+
+pub fn is_repo_name(input: &str) -> bool {
+    resolve_package_path(input).is_none()
+        && !input.ends_with(".deb")
+        && !input.starts_with("http")
+        && !input.starts_with("oci://")
+}
+
+pub async fn resolve_all(inputs: &[String], index: Option<&RepoIndex>) -> Result<Vec<String>, Failure> {
+    let mut tasks = Vec::new();
+    for input in inputs {
+        let input = input.clone();
+        let meta = index.and_then(|idx| idx.packages.get(&input).cloned());
+        tasks.push(tokio::spawn(async move {
+            match meta {
+                Some(meta) => fetch_package(&input, &meta).await,
+                None if is_repo_name(&input) => Err(fail(NOT_FOUND, "not found in repo. try: pls install <url-to-deb>")),
+                None => resolve_or_download(&input).await,
             }
-        }
+        }));
+    }
 
-        println!("downloading {}...", name);
-        return download_package(name).await;
+    let mut paths = Vec::new();
+    let mut errors = Vec::new();
+    let mut codes = Vec::new();
+    for (input, task) in inputs.iter().zip(tasks) {
+        match task.await {
+            Ok(Ok(path)) => paths.push(path),
+            Ok(Err(e)) => {
+                codes.push(e.code);
+                errors.push(format!("{}: {}", input, e));
+            }
+            Err(e) => {
+                codes.push(FAILURE);
+                errors.push(format!("{}: {}", input, e));
+            }
+        }
     }
 
-    Err(format!("'{}' not found in repo. try: pls install <url-to-deb>", name))
+    if !errors.is_empty() {
+        // only keep a specific code when every failure agrees on it
+        let code = if codes.iter().all(|&c| c == codes[0]) { codes[0] } else { FAILURE };
+        return Err(fail(code, format!("couldn't get everything, nothing was changed\n  {}", errors.join("\n  "))));
+    }
+    Ok(paths)
 }
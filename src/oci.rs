@@ -0,0 +1,434 @@
+use std::env;
+use std::fs::{self, File};
+use std::io;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use reqwest::header::{ACCEPT, CONTENT_TYPE, LOCATION, WWW_AUTHENTICATE};
+use reqwest::{Client, RequestBuilder, Response, StatusCode, Url};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use tar::EntryType;
+
+use crate::cache::lock;
+use crate::config::{Config, MAIN_REPO};
+use crate::elf::parse_elf;
+use crate::exit::{CHECKSUM, Failure, NOT_FOUND, fail};
+use crate::keys::cosign_verify;
+use crate::network::{bad_status, into_cache, stream_to_file, unreachable};
+use crate::paths::{cache_dir, home};
+use crate::utils::{host_arch, relocate_payload};
+use crate::say;
+use crate::term::yellow;
+
+const MANIFEST_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+const ARTIFACT_TYPE: &str = "application/vnd.pls.package.v1";
+const LAYER_TYPE: &str = "application/vnd.pls.package.v1.tar+zstd";
+const EMPTY_TYPE: &str = "application/vnd.oci.empty.v1+json";
+const EMPTY_CONFIG: &[u8] = b"{}";
+const INDEX_TYPE: &str = "application/vnd.oci.image.index.v1+json";
+const IMAGE_CONFIG_TYPE: &str = "application/vnd.oci.image.config.v1+json";
+const IMAGE_LAYER_TYPE: &str = "application/vnd.oci.image.layer.v1.tar";
+const IMAGE_PREFIX: &str = "usr/local";
+
+pub struct Reference {
+    pub registry: String,
+    pub repository: String,
+    pub tag: Option<String>,
+}
+
+impl Reference {
+    pub fn parse(input: &str) -> Option<Self> {
+        let (registry, path) = input.strip_prefix("oci://")?.split_once('/')?;
+        let (repository, tag) = match path.rsplit_once(':') {
+            Some((repository, tag)) => (repository, Some(tag.to_string())),
+            None => (path, None),
+        };
+        if registry.is_empty() || repository.is_empty() {
+            return None;
+        }
+        Some(Self { registry: registry.to_string(), repository: repository.trim_end_matches('/').to_string(), tag })
+    }
+
+    fn url(&self, path: &str) -> String {
+        let local = self.registry.starts_with("localhost") || self.registry.starts_with("127.0.0.1");
+        let scheme = if local { "http" } else { "https" };
+        format!("{}://{}/v2/{}/{}", scheme, self.registry, self.repository, path)
+    }
+}
+
+enum Auth {
+    Anonymous,
+    Basic(String, String),
+    Bearer(String),
+}
+
+fn credentials(registry: &str) -> Option<(String, String)> {
+    if let (Ok(user), Ok(token)) = (env::var("PLS_OCI_USER"), env::var("PLS_OCI_TOKEN")) {
+        return Some((user, token));
+    }
+    let dir = env::var("DOCKER_CONFIG").unwrap_or_else(|_| format!("{}/.docker", home()));
+    let config: Value = serde_json::from_str(&fs::read_to_string(format!("{}/config.json", dir)).ok()?).ok()?;
+    let auth = config["auths"][registry]["auth"].as_str()?;
+    let decoded = String::from_utf8(STANDARD.decode(auth).ok()?).ok()?;
+    let (user, pass) = decoded.split_once(':')?;
+    Some((user.to_string(), pass.to_string()))
+}
+
+fn challenge_param(challenge: &str, key: &str) -> Option<String> {
+    let start = challenge.find(&format!("{}=\"", key))? + key.len() + 2;
+    let end = challenge[start..].find('"')? + start;
+    Some(challenge[start..end].to_string())
+}
+
+struct Registry {
+    client: Client,
+    reference: Reference,
+    scope: &'static str,
+    auth: Auth,
+}
+
+impl Registry {
+    fn new(reference: Reference, scope: &'static str) -> Self {
+        Self { client: Client::new(), reference, scope, auth: Auth::Anonymous }
+    }
+
+    fn authed(&self, req: RequestBuilder) -> RequestBuilder {
+        match &self.auth {
+            Auth::Anonymous => req,
+            Auth::Basic(user, pass) => req.basic_auth(user, Some(pass)),
+            Auth::Bearer(token) => req.bearer_auth(token),
+        }
+    }
+
+    async fn send(&mut self, url: &str, build: impl Fn(&Client) -> RequestBuilder) -> Result<Response, Failure> {
+        let res = self.authed(build(&self.client)).send().await.map_err(|e| unreachable(url, e))?;
+        if res.status() != StatusCode::UNAUTHORIZED || !matches!(self.auth, Auth::Anonymous) {
+            return Ok(res);
+        }
+        let challenge = res.headers().get(WWW_AUTHENTICATE).and_then(|h| h.to_str().ok()).unwrap_or("").to_string();
+        self.login(&challenge).await?;
+        self.authed(build(&self.client)).send().await.map_err(|e| unreachable(url, e))
+    }
+
+    async fn login(&mut self, challenge: &str) -> Result<(), Failure> {
+        let registry = self.reference.registry.clone();
+        let creds = credentials(&registry);
+        let Some(realm) = challenge_param(challenge, "realm").filter(|_| challenge.starts_with("Bearer")) else {
+            let (user, pass) = creds.ok_or_else(|| format!("{} wants a login, set PLS_OCI_USER and PLS_OCI_TOKEN", registry))?;
+            self.auth = Auth::Basic(user, pass);
+            return Ok(());
+        };
+
+        let mut url = Url::parse(&realm).map_err(|e| format!("{} sent a weird login url {}: {}", registry, realm, e))?;
+        url.query_pairs_mut().append_pair("scope", &format!("repository:{}:{}", self.reference.repository, self.scope));
+        if let Some(service) = challenge_param(challenge, "service") {
+            url.query_pairs_mut().append_pair("service", &service);
+        }
+        let mut req = self.client.get(url);
+        if let Some((user, pass)) = &creds {
+            req = req.basic_auth(user, Some(pass));
+        }
+        let res = req.send().await.map_err(|e| unreachable(&realm, e))?;
+        if !res.status().is_success() {
+            return Err(bad_status(&realm, res.status()));
+        }
+        let bytes = res.bytes().await.map_err(|e| unreachable(&realm, e))?;
+        let body: Value = serde_json::from_slice(&bytes).map_err(|e| format!("{} sent a weird token: {}", registry, e))?;
+        let token = body["token"].as_str().or(body["access_token"].as_str()).ok_or_else(|| format!("{} sent no token", registry))?;
+        self.auth = Auth::Bearer(token.to_string());
+        Ok(())
+    }
+
+    async fn manifest(&mut self, tag: &str) -> Result<Option<(Value, String)>, Failure> {
+        let url = self.reference.url(&format!("manifests/{}", tag));
+        let res = self.send(&url, |c| c.get(&url).header(ACCEPT, MANIFEST_TYPE)).await?;
+        if res.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !res.status().is_success() {
+            return Err(bad_status(&url, res.status()));
+        }
+        let bytes = res.bytes().await.map_err(|e| unreachable(&url, e))?;
+        let digest = format!("sha256:{}", hex::encode(Sha256::digest(&bytes)));
+        let manifest = serde_json::from_slice(&bytes).map_err(|e| format!("manifest for {} is broken: {}", tag, e))?;
+        Ok(Some((manifest, digest)))
+    }
+
+    async fn push_blob(&mut self, bytes: &[u8]) -> Result<String, Failure> {
+        let digest = format!("sha256:{}", hex::encode(Sha256::digest(bytes)));
+        let url = self.reference.url(&format!("blobs/{}", digest));
+        if self.send(&url, |c| c.head(&url)).await?.status().is_success() {
+            return Ok(digest);
+        }
+
+        let start = self.reference.url("blobs/uploads/");
+        let res = self.send(&start, |c| c.post(&start)).await?;
+        if !res.status().is_success() {
+            return Err(bad_status(&start, res.status()));
+        }
+        let location = res.headers().get(LOCATION).and_then(|h| h.to_str().ok()).ok_or("registry gave no upload location")?;
+        let location = match location.starts_with('/') {
+            true => format!("{}{}", start.split("/v2/").next().unwrap_or(""), location),
+            false => location.to_string(),
+        };
+        let upload = format!("{}{}digest={}", location, if location.contains('?') { '&' } else { '?' }, digest);
+        let res = self
+            .send(&upload, |c| c.put(&upload).header(CONTENT_TYPE, "application/octet-stream").body(bytes.to_vec()))
+            .await?;
+        if !res.status().is_success() {
+            return Err(bad_status(&upload, res.status()));
+        }
+        Ok(digest)
+    }
+}
+
+pub async fn fetch(input: &str) -> Result<String, Failure> {
+    let reference = Reference::parse(input).ok_or_else(|| format!("'{}' isn't an oci://registry/repo:tag reference", input))?;
+    let tag = reference.tag.clone().ok_or_else(|| format!("{} needs a tag, like :hello-1.0", input))?;
+    if tag.is_empty() || tag.len() > 128 || !tag.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-')) || tag.starts_with(['.', '-']) {
+        return Err(format!("'{}' isn't a tag, tags are letters, digits, '_', '.' and '-'", tag).into());
+    }
+    let mut registry = Registry::new(reference, "pull");
+
+    say!("asking {} for {}...", registry.reference.registry, tag);
+    let mut manifest = None;
+    for candidate in [format!("{}-{}", tag, host_arch()), tag.clone()] {
+        if let Some(found) = registry.manifest(&candidate).await? {
+            manifest = Some(found);
+            break;
+        }
+    }
+    let (manifest, manifest_digest) =
+        manifest.ok_or_else(|| fail(NOT_FOUND, format!("{} has no {}", registry.reference.repository, tag)))?;
+
+    let config = Config::load();
+    if let Some((identity, issuer)) = config.cosign_for(&registry.reference.registry).or(config.cosign_for(MAIN_REPO)) {
+        say!("checking cosign signature...");
+        let image = format!("{}/{}@{}", registry.reference.registry, registry.reference.repository, manifest_digest);
+        cosign_verify(&["verify", &image], identity, issuer)
+            .map_err(|e| fail(e.code, format!("{} isn't signed by {}: {}", input, identity, e)))?;
+    }
+    let layers = manifest["layers"].as_array().cloned().unwrap_or_default();
+    let layer = layers
+        .iter()
+        .find(|layer| layer["mediaType"] == LAYER_TYPE)
+        .or(layers.first())
+        .ok_or_else(|| format!("{} is an empty artifact", input))?;
+    let digest = layer["digest"].as_str().ok_or_else(|| format!("{} has a layer without a digest", input))?;
+
+    let url = registry.reference.url(&format!("blobs/{}", digest));
+    let res = registry.send(&url, |c| c.get(&url)).await?;
+    if !res.status().is_success() {
+        return Err(bad_status(&url, res.status()));
+    }
+    fs::create_dir_all(cache_dir()).map_err(|e| e.to_string())?;
+    let dest = format!("{}/oci-{}.pls", cache_dir(), tag);
+    let _lock = lock(&dest).await?;
+    let sha256 = stream_to_file(res, &dest).await?;
+    if digest.strip_prefix("sha256:") != Some(sha256.as_str()) {
+        let _ = fs::remove_file(&dest);
+        return Err(fail(CHECKSUM, format!("{} doesn't match its digest, the registry or the network is lying", input)));
+    }
+    into_cache(&dest, input, &sha256)
+}
+
+pub async fn publish(target: &str, files: &[(String, String)]) -> Result<usize, Failure> {
+    let reference = Reference::parse(target).ok_or_else(|| format!("'{}' isn't an oci://registry/repo target", target))?;
+    let mut registry = Registry::new(reference, "pull,push");
+    let config = registry.push_blob(EMPTY_CONFIG).await?;
+
+    let mut pushed = 0;
+    for (file, path) in files {
+        let tag = file.trim_end_matches(".pls");
+        let bytes = fs::read(path).map_err(|e| format!("couldn't read {}: {}", path, e))?;
+        let digest = format!("sha256:{}", hex::encode(Sha256::digest(&bytes)));
+        if let Some((existing, _)) = registry.manifest(tag).await?
+            && existing["layers"][0]["digest"] == digest.as_str()
+        {
+            continue;
+        }
+
+        say!("  {}", file);
+        registry.push_blob(&bytes).await?;
+        let manifest = json!({
+            "schemaVersion": 2,
+            "mediaType": MANIFEST_TYPE,
+            "artifactType": ARTIFACT_TYPE,
+            "config": { "mediaType": EMPTY_TYPE, "digest": config, "size": EMPTY_CONFIG.len() },
+            "layers": [{
+                "mediaType": LAYER_TYPE,
+                "digest": digest,
+                "size": bytes.len(),
+                "annotations": { "org.opencontainers.image.title": file },
+            }],
+        })
+        .to_string();
+        let url = registry.reference.url(&format!("manifests/{}", tag));
+        let res = registry
+            .send(&url, |c| c.put(&url).header(CONTENT_TYPE, MANIFEST_TYPE).body(manifest.clone()))
+            .await?;
+        if !res.status().is_success() {
+            return Err(bad_status(&url, res.status()));
+        }
+        pushed += 1;
+    }
+    Ok(pushed)
+}
+
+fn platform_arch(arch: &str) -> &str {
+    match arch {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        "x86" | "i686" => "386",
+        "powerpc64" => "ppc64le",
+        other => other,
+    }
+}
+
+fn base_layer() -> io::Result<Vec<u8>> {
+    let mut tar = tar::Builder::new(Vec::new());
+    let dirs = ["etc", "tmp", "root", "usr", "usr/local", "usr/local/bin", "usr/local/include", "usr/local/lib", "usr/local/share"];
+    for dir in dirs {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(EntryType::Directory);
+        header.set_mode(match dir {
+            "tmp" => 0o1777,
+            "root" => 0o700,
+            _ => 0o755,
+        });
+        header.set_size(0);
+        tar.append_data(&mut header, dir, io::empty())?;
+    }
+    for (file, content) in [
+        ("etc/passwd", "root:x:0:0:root:/root:/bin/sh\nnobody:x:65534:65534:nobody:/nonexistent:/bin/false\n"),
+        ("etc/group", "root:x:0:\nnobody:x:65534:\n"),
+    ] {
+        let mut header = tar::Header::new_gnu();
+        header.set_mode(0o644);
+        header.set_size(content.len() as u64);
+        tar.append_data(&mut header, file, content.as_bytes())?;
+    }
+    tar.into_inner()
+}
+
+fn descriptor(media_type: &str, bytes: &[u8]) -> Value {
+    json!({ "mediaType": media_type, "digest": format!("sha256:{}", hex::encode(Sha256::digest(bytes))), "size": bytes.len() })
+}
+
+fn append_blob(tar: &mut tar::Builder<File>, path: &str, bytes: &[u8]) -> io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_mode(0o644);
+    header.set_size(bytes.len() as u64);
+    tar.append_data(&mut header, path, bytes)
+}
+
+fn dynamic_binaries(layer: &[u8]) -> Vec<(String, String)> {
+    let mut found = Vec::new();
+    let mut archive = tar::Archive::new(layer);
+    let Ok(entries) = archive.entries() else {
+        return found;
+    };
+    for mut entry in entries.flatten() {
+        let path = entry.path().map(|p| p.to_string_lossy().to_string()).unwrap_or_default();
+        if !entry.header().entry_type().is_file() || !path.starts_with(&format!("{}/bin/", IMAGE_PREFIX)) {
+            continue;
+        }
+        let mut data = Vec::new();
+        if io::Read::read_to_end(&mut entry, &mut data).is_ok()
+            && let Some(interp) = parse_elf(&data).and_then(|elf| elf.interpreter)
+        {
+            found.push((path, interp));
+        }
+    }
+    found
+}
+
+pub fn write_image(packages: &[(String, String)], image: &str, entrypoint: Option<&str>, output: &str) -> Result<(), String> {
+    let mut layers = vec![("base".to_string(), base_layer().map_err(|e| format!("couldn't build the base layer: {}", e))?)];
+    for (label, path) in packages {
+        let mut layer = tar::Builder::new(Vec::new());
+        relocate_payload(path, IMAGE_PREFIX, &mut layer)
+            .and_then(|_| layer.into_inner())
+            .map(|bytes| layers.push((label.clone(), bytes)))
+            .map_err(|e| format!("couldn't turn {} into a layer: {}", label, e))?;
+    }
+
+    let dynamic: Vec<(String, String)> = layers.iter().flat_map(|(_, bytes)| dynamic_binaries(bytes)).collect();
+    if let Some(entrypoint) = entrypoint
+        && let Some((_, interp)) = dynamic.iter().find(|(bin, _)| *bin == format!("{}/bin/{}", IMAGE_PREFIX, entrypoint))
+    {
+        return Err(format!(
+            "{} is dynamically linked (needs {}) and the image has no libc, it'd never start. link it statically first",
+            entrypoint, interp
+        ));
+    }
+    for (bin, interp) in &dynamic {
+        say!("{} /{} is dynamically linked (needs {}), it won't run in this image", yellow("heads up,"), bin, interp);
+    }
+
+    let descriptors: Vec<Value> = layers
+        .iter()
+        .map(|(label, bytes)| {
+            let mut layer = descriptor(IMAGE_LAYER_TYPE, bytes);
+            layer["annotations"] = json!({ "org.opencontainers.image.title": label });
+            layer
+        })
+        .collect();
+    let mut container = json!({
+        "Env": [
+            format!("PATH=/{}/bin:/usr/bin:/bin", IMAGE_PREFIX),
+            format!("LD_LIBRARY_PATH=/{}/lib", IMAGE_PREFIX),
+        ],
+        "WorkingDir": "/",
+    });
+    if let Some(entrypoint) = entrypoint {
+        container["Entrypoint"] = json!([format!("/{}/bin/{}", IMAGE_PREFIX, entrypoint)]);
+    }
+    let config = json!({
+        "architecture": platform_arch(host_arch()),
+        "os": "linux",
+        "config": container,
+        "rootfs": { "type": "layers", "diff_ids": descriptors.iter().map(|d| d["digest"].clone()).collect::<Vec<_>>() },
+        "history": layers.iter().map(|(label, _)| json!({ "created_by": format!("pls export {}", label) })).collect::<Vec<_>>(),
+    })
+    .to_string();
+    let manifest = json!({
+        "schemaVersion": 2,
+        "mediaType": MANIFEST_TYPE,
+        "config": descriptor(IMAGE_CONFIG_TYPE, config.as_bytes()),
+        "layers": descriptors,
+    })
+    .to_string();
+    let tag = image.rsplit_once(':').map(|(_, tag)| tag).unwrap_or("latest");
+    let mut manifest_descriptor = descriptor(MANIFEST_TYPE, manifest.as_bytes());
+    manifest_descriptor["annotations"] = json!({ "io.containerd.image.name": image, "org.opencontainers.image.ref.name": tag });
+    let index = json!({ "schemaVersion": 2, "mediaType": INDEX_TYPE, "manifests": [manifest_descriptor] }).to_string();
+
+    let blob_path = |bytes: &[u8]| format!("blobs/sha256/{}", hex::encode(Sha256::digest(bytes)));
+    let docker = json!([{
+        "Config": blob_path(config.as_bytes()),
+        "RepoTags": [image],
+        "Layers": layers.iter().map(|(_, bytes)| blob_path(bytes)).collect::<Vec<_>>(),
+    }])
+    .to_string();
+
+    let partial = format!("{}.part", output);
+    let result = (|| {
+        let mut tar = tar::Builder::new(File::create(&partial)?);
+        append_blob(&mut tar, "oci-layout", br#"{"imageLayoutVersion":"1.0.0"}"#)?;
+        append_blob(&mut tar, "index.json", index.as_bytes())?;
+        append_blob(&mut tar, "manifest.json", docker.as_bytes())?;
+        append_blob(&mut tar, &blob_path(config.as_bytes()), config.as_bytes())?;
+        append_blob(&mut tar, &blob_path(manifest.as_bytes()), manifest.as_bytes())?;
+        for (_, bytes) in &layers {
+            append_blob(&mut tar, &blob_path(bytes), bytes)?;
+        }
+        tar.into_inner()?.sync_all()?;
+        fs::rename(&partial, output)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result.map_err(|e| format!("couldn't write {}: {}", output, e))
+}
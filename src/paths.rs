@@ -0,0 +1,149 @@
+use std::env;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::process::Command;
+use std::sync::OnceLock;
+
+#[cfg(not(target_os = "macos"))]
+const SYSTEM: [&str; 8] = [
+    "/var/cache/pls",
+    "/var/lib/pls/db",
+    "/var/lib/pls/packages",
+    "/var/lib/pls/store",
+    "/etc/pls/pls.conf",
+    "/etc/pls/hooks.d",
+    "/usr",
+    "/opt/pls",
+];
+#[cfg(target_os = "macos")]
+const SYSTEM: [&str; 8] = [
+    "/Library/Caches/pls",
+    "/Library/Application Support/pls/db",
+    "/Library/Application Support/pls/packages",
+    "/Library/Application Support/pls/store",
+    "/usr/local/etc/pls/pls.conf",
+    "/usr/local/etc/pls/hooks.d",
+    "/usr/local",
+    "/usr/local/opt/pls",
+];
+
+struct Paths {
+    user: bool,
+    cache: String,
+    db: String,
+    packages: String,
+    store: String,
+    config: String,
+    hooks: String,
+    prefix: String,
+    versions: String,
+    tmp: String,
+}
+
+static PATHS: OnceLock<Paths> = OnceLock::new();
+
+pub fn running_as_root() -> bool {
+    fs::metadata("/proc/self")
+        .map(|m| m.uid() == 0)
+        .ok()
+        .or_else(|| Command::new("id").arg("-u").output().ok().map(|o| o.stdout.trim_ascii() == b"0"))
+        .unwrap_or(true)
+}
+
+fn system_paths() -> Paths {
+    let [cache, db, packages, store, config, hooks, prefix, versions] = SYSTEM.map(String::from);
+    Paths { user: false, cache, db, packages, store, config, hooks, prefix, versions, tmp: "/tmp".to_string() }
+}
+
+fn xdg(var: &str, fallback: &str) -> String {
+    env::var(var)
+        .ok()
+        .filter(|dir| dir.starts_with('/'))
+        .unwrap_or_else(|| format!("{}/{}", home(), fallback))
+}
+
+pub fn home() -> String {
+    env::var("HOME").unwrap_or_default().trim_end_matches('/').to_string()
+}
+
+fn user_paths() -> Paths {
+    let cache = format!("{}/pls", xdg("XDG_CACHE_HOME", ".cache"));
+    let config = format!("{}/pls", xdg("XDG_CONFIG_HOME", ".config"));
+    let state = format!("{}/pls", xdg("XDG_STATE_HOME", ".local/state"));
+    let tmp = env::var("XDG_RUNTIME_DIR")
+        .ok()
+        .filter(|dir| dir.starts_with('/'))
+        .map(|dir| format!("{}/pls", dir))
+        .unwrap_or_else(|| format!("{}/tmp", cache));
+    Paths {
+        user: true,
+        db: format!("{}/db", state),
+        packages: format!("{}/packages", state),
+        store: format!("{}/store", state),
+        hooks: format!("{}/hooks.d", config),
+        config: format!("{}/pls.conf", config),
+        prefix: format!("{}/.local", home()),
+        versions: format!("{}/.local/opt/pls", home()),
+        cache,
+        tmp,
+    }
+}
+
+pub fn init(user: bool) {
+    let system = system_paths();
+    let per_user = user || (!running_as_root() && !Path::new(&system.db).exists());
+    let _ = PATHS.set(if per_user { user_paths() } else { system });
+}
+
+fn get() -> &'static Paths {
+    PATHS.get_or_init(system_paths)
+}
+
+pub fn is_user() -> bool {
+    get().user
+}
+
+pub fn cache_dir() -> &'static str {
+    &get().cache
+}
+
+pub fn db_dir() -> &'static str {
+    &get().db
+}
+
+pub fn packages_dir() -> &'static str {
+    &get().packages
+}
+
+pub fn store_dir() -> &'static str {
+    &get().store
+}
+
+pub fn config_path() -> &'static str {
+    &get().config
+}
+
+pub fn hooks_dir() -> &'static str {
+    &get().hooks
+}
+
+pub fn default_prefix() -> &'static str {
+    &get().prefix
+}
+
+pub fn versions_dir() -> &'static str {
+    &get().versions
+}
+
+pub fn tmp_dir() -> &'static str {
+    &get().tmp
+}
+
+pub fn alternatives_dir() -> String {
+    Path::new(db_dir()).with_file_name("alternatives").to_string_lossy().to_string()
+}
+
+pub fn known_keys_path() -> String {
+    Path::new(config_path()).with_file_name("known_keys").to_string_lossy().to_string()
+}
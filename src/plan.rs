@@ -0,0 +1,199 @@
+use std::cmp::Ordering;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use crate::cache::cache_path;
+use crate::config::Config;
+use crate::exit::{FAILURE, Failure, fail};
+use crate::paths::{cache_dir, db_dir, store_dir, tmp_dir};
+use crate::term::{bold, dim, green, red, yellow};
+use crate::types::{PackageInfo, RepoIndex};
+use crate::utils::{compare_versions, format_size, installed_files};
+use crate::say;
+
+pub struct Step {
+    pub name: String,
+    pub version: String,
+    pub old_version: Option<String>,
+    pub dependency: bool,
+    pub size: u64,
+    pub installed_size: u64,
+    pub cached: bool,
+    pub old_size: u64,
+    pub remove: bool,
+}
+
+impl Step {
+    fn action(&self) -> &'static str {
+        if self.remove {
+            return "remove";
+        }
+        match &self.old_version {
+            None => "install",
+            Some(old) => match compare_versions(old, &self.version) {
+                Ordering::Less => "upgrade",
+                Ordering::Greater => "downgrade",
+                Ordering::Equal => "reinstall",
+            },
+        }
+    }
+}
+
+pub struct Plan {
+    pub steps: Vec<Step>,
+}
+
+fn free_space(path: &str) -> Option<(String, u64)> {
+    let existing = Path::new(path).ancestors().find(|dir| dir.exists())?;
+    let output = Command::new("df").arg("-Pk").arg(existing).output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let fields: Vec<&str> = stdout.lines().nth(1)?.split_whitespace().collect();
+    let available: u64 = fields.get(3)?.parse().ok()?;
+    Some((fields.last()?.to_string(), available * 1024))
+}
+
+fn size_on_disk(name: &str) -> u64 {
+    installed_files(name)
+        .iter()
+        .filter(|entry| !entry.ends_with('/'))
+        .filter_map(|file| fs::symlink_metadata(file).ok())
+        .map(|meta| meta.len())
+        .sum()
+}
+
+impl Plan {
+    pub fn build(index: &RepoIndex, names: &[String], dependencies: &[String]) -> Self {
+        let steps = names
+            .iter()
+            .filter_map(|name| {
+                let meta = index.packages.get(name)?;
+                let old_version = PackageInfo::from_file(&format!("{}/{}/info", db_dir(), name)).ok().map(|pkg| pkg.version);
+                Some(Step {
+                    name: name.clone(),
+                    version: meta.version.clone(),
+                    old_size: if old_version.is_some() { size_on_disk(name) } else { 0 },
+                    old_version,
+                    dependency: dependencies.contains(name),
+                    size: meta.size,
+                    installed_size: meta.installed_size,
+                    cached: Path::new(&cache_path(name, &meta.version)).exists(),
+                    remove: false,
+                })
+            })
+            .collect();
+        Self { steps }
+    }
+
+    pub fn removing(packages: &[&PackageInfo]) -> Self {
+        let steps = packages
+            .iter()
+            .map(|pkg| Step {
+                name: pkg.name.clone(),
+                version: pkg.version.clone(),
+                old_version: Some(pkg.version.clone()),
+                dependency: false,
+                size: 0,
+                installed_size: 0,
+                cached: false,
+                old_size: size_on_disk(&pkg.name),
+                remove: true,
+            })
+            .collect();
+        Self { steps }
+    }
+
+    pub fn download_size(&self) -> u64 {
+        self.steps.iter().filter(|step| !step.cached).map(|step| step.size).sum()
+    }
+
+    pub fn disk_growth(&self) -> Option<i64> {
+        if self.steps.iter().any(|step| !step.remove && step.installed_size == 0) {
+            return None;
+        }
+        Some(self.steps.iter().map(|step| step.installed_size as i64 - step.old_size as i64).sum())
+    }
+
+    pub fn check_space(&self) -> Result<(), Failure> {
+        if self.disk_growth().is_none() {
+            return Ok(());
+        }
+        let unpacked: u64 = self.steps.iter().map(|step| step.installed_size).sum();
+        let prefix = Config::load().prefix;
+        let landing = match (free_space(store_dir()), free_space(&prefix)) {
+            (Some((store, _)), Some((target, _))) if store == target => store_dir().to_string(),
+            _ => prefix,
+        };
+        let mut needs: Vec<(String, u64, u64)> = Vec::new();
+        for (path, bytes) in [(cache_dir().to_string(), self.download_size()), (tmp_dir().to_string(), unpacked), (landing, unpacked)] {
+            let Some((mount, free)) = free_space(&path) else {
+                continue;
+            };
+            match needs.iter_mut().find(|(m, _, _)| *m == mount) {
+                Some((_, need, _)) => *need += bytes,
+                None => needs.push((mount, bytes, free)),
+            }
+        }
+        match needs.iter().find(|(_, need, free)| need > free) {
+            Some((mount, need, free)) => Err(fail(
+                FAILURE,
+                format!("not enough space on {}, this needs {} and only {} is free (--force to try anyway)", mount, format_size(*need), format_size(*free)),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    pub fn print(&self) {
+        if self.steps.is_empty() {
+            return;
+        }
+        say!("{}", bold("plan:"));
+        for step in &self.steps {
+            let action = step.action();
+            let version = match &step.old_version {
+                Some(old) if *old != step.version && !step.remove => format!("{} -> {}", old, step.version),
+                _ => step.version.clone(),
+            };
+            let mut notes = Vec::new();
+            if step.dependency {
+                notes.push("dependency");
+            }
+            if step.cached {
+                notes.push("cached");
+            }
+            let notes = if notes.is_empty() { String::new() } else { format!(" {}", dim(format!("({})", notes.join(", ")))) };
+            let action = match action {
+                "install" => green(format!("{:<9}", action)),
+                "downgrade" => yellow(format!("{:<9}", action)),
+                "remove" => red(format!("{:<9}", action)),
+                _ => format!("{:<9}", action),
+            };
+            say!("  {} {} {}{}", action, step.name, version, notes);
+        }
+
+        let mut counts: Vec<String> = Vec::new();
+        for action in ["install", "upgrade", "downgrade", "reinstall", "remove"] {
+            let count = self.steps.iter().filter(|step| step.action() == action).count();
+            if count > 0 {
+                counts.push(format!("{} to {}", count, action));
+            }
+        }
+        let cached = self.steps.iter().filter(|step| step.cached).count();
+        let mut totals = counts.join(", ");
+        if self.steps.iter().any(|step| !step.remove) {
+            totals.push_str(&format!(", download {}", format_size(self.download_size())));
+        }
+        if cached > 0 {
+            totals.push_str(&format!(" ({} cached)", cached));
+        }
+        let freed: u64 = self.steps.iter().map(|step| step.old_size).sum();
+        match self.disk_growth() {
+            Some(growth) if growth >= 0 => totals.push_str(&format!(", takes {} more disk", format_size(growth as u64))),
+            Some(growth) => totals.push_str(&format!(", frees {} of disk", format_size(growth.unsigned_abs()))),
+            None if freed > 0 => totals.push_str(&format!(", replaces {} of installed files", format_size(freed))),
+            None => {}
+        }
+        say!("  {}", totals);
+        say!();
+    }
+}
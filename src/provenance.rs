@@ -0,0 +1,93 @@
+use std::env;
+use std::process::Command;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::VERSION;
+use crate::changelog::git;
+use crate::commands::ProjectType;
+use crate::lan::hostname;
+use crate::types::Provenance;
+use crate::utils::host_arch;
+
+pub const FILE: &str = "provenance";
+
+fn first_line(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout.lines().next().map(|line| line.trim().to_string()).filter(|line| !line.is_empty())
+}
+
+pub struct Revision {
+    pub commit: String,
+    pub tag: String,
+    pub dirty: bool,
+}
+
+pub fn revision(project_path: &str) -> Option<Revision> {
+    let commit = git(project_path, &["rev-parse", "HEAD"])?.trim().to_string();
+    let tag = git(project_path, &["describe", "--tags"]).unwrap_or_default().trim().to_string();
+    let dirty = git(project_path, &["status", "--porcelain"]).is_some_and(|s| !s.trim().is_empty());
+    Some(Revision { commit, tag, dirty })
+}
+
+pub fn collect(project_path: &str, project_type: &ProjectType, is_draft: bool) -> Provenance {
+    let mut provenance = Provenance::new();
+
+    if let Some(url) = git(project_path, &["remote", "get-url", "origin"]) {
+        provenance.insert("source".to_string(), url.trim().to_string());
+    }
+    if let Some(rev) = revision(project_path) {
+        provenance.insert("commit".to_string(), format!("{}{}", rev.commit, if rev.dirty { "-dirty" } else { "" }));
+    }
+    provenance.insert("builder".to_string(), format!("{} ({} {})", hostname(), env::consts::OS, host_arch()));
+
+    let toolchain: Vec<String> = match project_type {
+        ProjectType::Rust => vec![first_line("rustc", &["--version"]), first_line("cargo", &["--version"])],
+        ProjectType::CMake => vec![first_line("cmake", &["--version"]), first_line("cc", &["--version"])],
+        ProjectType::Meson => vec![first_line("meson", &["--version"]).map(|v| format!("meson {}", v)), first_line("cc", &["--version"])],
+        ProjectType::PlsToml => Vec::new(),
+    }
+    .into_iter()
+    .flatten()
+    .collect();
+    if !toolchain.is_empty() {
+        provenance.insert("toolchain".to_string(), toolchain.join("; "));
+    }
+
+    let mut flags = vec![match project_type {
+        ProjectType::PlsToml => "prebuilt".to_string(),
+        _ if is_draft => "debug".to_string(),
+        _ => "release".to_string(),
+    }];
+    for var in ["RUSTFLAGS", "CFLAGS", "CXXFLAGS", "LDFLAGS"] {
+        if let Ok(value) = env::var(var)
+            && !value.trim().is_empty()
+        {
+            flags.push(format!("{}={}", var, value.trim()));
+        }
+    }
+    provenance.insert("flags".to_string(), flags.join(" "));
+
+    let built_at = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.trim().parse().ok())
+        .and_then(|secs| DateTime::<Utc>::from_timestamp(secs, 0))
+        .unwrap_or_else(Utc::now);
+    provenance.insert("built_at".to_string(), built_at.to_rfc3339_opts(SecondsFormat::Secs, true));
+    provenance.insert("pls".to_string(), VERSION.to_string());
+    provenance
+}
+
+pub fn render(provenance: &Provenance) -> String {
+    provenance.iter().map(|(key, value)| format!("{} = {}\n", key, value)).collect()
+}
+
+pub fn parse(content: &str) -> Provenance {
+    content
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .filter(|(key, _)| !key.is_empty())
+        .collect()
+}
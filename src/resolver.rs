@@ -0,0 +1,201 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::config::Config;
+use crate::elf::library_present;
+use crate::exit::{CONFLICT, Failure, NOT_FOUND, fail};
+use crate::types::{PackageInfo, RepoIndex};
+use crate::utils::split_dep;
+
+fn cycle_error(path: &[String], name: &str) -> Failure {
+    let start = path.iter().position(|p| p == name).unwrap_or(0);
+    let mut cycle: Vec<&str> = path[start..].iter().map(|s| s.as_str()).collect();
+    cycle.push(name);
+    fail(CONFLICT, format!("dependency cycle: {}", cycle.join(" -> ")))
+}
+
+fn dep_target<'a>(index: &'a RepoIndex, dep: &'a str) -> Result<Option<&'a str>, Failure> {
+    let name = split_dep(dep).0;
+    let Some(soname) = name.strip_prefix("so:") else {
+        return Ok(Some(name));
+    };
+    if library_present(soname, &Config::load().libdir()) {
+        return Ok(None);
+    }
+    index
+        .packages
+        .iter()
+        .filter(|(_, meta)| meta.provides.iter().any(|p| p == soname))
+        .map(|(name, _)| name.as_str())
+        .min()
+        .map(Some)
+        .ok_or_else(|| fail(NOT_FOUND, format!("nothing provides {}, not the repo and not this box", soname)))
+}
+
+pub fn dep_provider<'a>(index: &'a RepoIndex, dep: &'a str) -> Option<&'a str> {
+    let name = split_dep(dep).0;
+    let Some(soname) = name.strip_prefix("so:") else {
+        return Some(name);
+    };
+    index.packages.iter().filter(|(_, meta)| meta.provides.iter().any(|p| p == soname)).map(|(name, _)| name.as_str()).min()
+}
+
+pub fn installed_provider<'a>(installed: &'a [PackageInfo], dep: &'a str) -> Option<&'a str> {
+    let name = split_dep(dep).0;
+    let Some(soname) = name.strip_prefix("so:") else {
+        return Some(name);
+    };
+    installed.iter().find(|pkg| pkg.provides.iter().any(|p| p == soname)).map(|pkg| pkg.name.as_str())
+}
+
+pub fn closure(index: &RepoIndex, names: &[String]) -> Result<Vec<String>, Failure> {
+    let mut order = Vec::new();
+    let mut done = HashSet::new();
+    let mut path = Vec::new();
+    for name in names {
+        visit(index, name, &mut done, &mut path, &mut order, None)?;
+    }
+    Ok(order)
+}
+
+fn visit(
+    index: &RepoIndex,
+    name: &str,
+    done: &mut HashSet<String>,
+    path: &mut Vec<String>,
+    order: &mut Vec<String>,
+    mut missing: Option<&mut Vec<String>>,
+) -> Result<(), Failure> {
+    if done.contains(name) {
+        return Ok(());
+    }
+    if path.iter().any(|p| p == name) {
+        return Err(cycle_error(path, name));
+    }
+
+    let Some(meta) = index.packages.get(name) else {
+        return match missing {
+            Some(missing) => {
+                missing.push(name.to_string());
+                done.insert(name.to_string());
+                Ok(())
+            }
+            None => Err(fail(NOT_FOUND, format!("'{}' not found in repo", name))),
+        };
+    };
+
+    path.push(name.to_string());
+    for dep in &meta.deps {
+        let target = match (dep_target(index, dep), missing.as_deref_mut()) {
+            (Ok(target), _) => target,
+            (Err(_), Some(missing)) => {
+                missing.push(split_dep(dep).0.to_string());
+                None
+            }
+            (Err(e), None) => return Err(e),
+        };
+        if let Some(target) = target {
+            visit(index, target, done, path, order, missing.as_deref_mut())?;
+        }
+    }
+    path.pop();
+
+    done.insert(name.to_string());
+    order.push(name.to_string());
+    Ok(())
+}
+
+fn split_parent<'a>(index: &'a RepoIndex, name: &'a str) -> &'a str {
+    match index.packages.get(name) {
+        Some(meta) if !meta.split_from.is_empty() => &meta.split_from,
+        _ => name,
+    }
+}
+
+pub fn split_siblings(index: &RepoIndex, names: &[String], installed: &[PackageInfo]) -> Vec<String> {
+    let mut siblings = Vec::new();
+    for pkg in installed {
+        let Some(meta) = index.packages.get(&pkg.name) else {
+            continue;
+        };
+        if meta.version == pkg.version || names.contains(&pkg.name) || siblings.contains(&pkg.name) {
+            continue;
+        }
+        let parent = split_parent(index, &pkg.name);
+        if names.iter().any(|name| split_parent(index, name) == parent) {
+            siblings.push(pkg.name.clone());
+        }
+    }
+    siblings
+}
+
+pub fn levels(index: &RepoIndex, names: &[String]) -> Result<(Vec<Vec<String>>, Vec<String>), Failure> {
+    let mut order = Vec::new();
+    let mut done = HashSet::new();
+    let mut missing = Vec::new();
+    for name in names {
+        visit(index, name, &mut done, &mut Vec::new(), &mut order, Some(&mut missing))?;
+    }
+    missing.sort();
+    missing.dedup();
+
+    let mut depth: HashMap<&str, usize> = HashMap::new();
+    let mut levels: Vec<Vec<String>> = Vec::new();
+    for name in &order {
+        let level = index.packages[name]
+            .deps
+            .iter()
+            .filter_map(|dep| depth.get(dep_target(index, dep).ok().flatten()?))
+            .map(|d| d + 1)
+            .max()
+            .unwrap_or(0);
+        depth.insert(name, level);
+
+        if levels.len() <= level {
+            levels.resize(level + 1, Vec::new());
+        }
+        levels[level].push(name.clone());
+    }
+    Ok((levels, missing))
+}
+
+pub fn topo_order<F>(names: &[String], deps_of: F) -> Result<Vec<String>, Failure>
+where
+    F: Fn(&str) -> Vec<String>,
+{
+    fn visit<F: Fn(&str) -> Vec<String>>(
+        name: &str,
+        names: &[String],
+        deps_of: &F,
+        done: &mut HashSet<String>,
+        path: &mut Vec<String>,
+        order: &mut Vec<String>,
+    ) -> Result<(), Failure> {
+        if done.contains(name) {
+            return Ok(());
+        }
+        if path.iter().any(|p| p == name) {
+            return Err(cycle_error(path, name));
+        }
+
+        path.push(name.to_string());
+        for dep in deps_of(name) {
+            let dep = split_dep(&dep).0;
+            if names.iter().any(|n| n == dep) {
+                visit(dep, names, deps_of, done, path, order)?;
+            }
+        }
+        path.pop();
+
+        done.insert(name.to_string());
+        order.push(name.to_string());
+        Ok(())
+    }
+
+    let mut order = Vec::new();
+    let mut done = HashSet::new();
+    let mut path = Vec::new();
+    for name in names {
+        visit(name, names, &deps_of, &mut done, &mut path, &mut order)?;
+    }
+    Ok(order)
+}
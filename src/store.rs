@@ -0,0 +1,87 @@
+use std::fs;
+use std::io;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::paths::store_dir;
+use crate::utils::{calculate_sha256, copy_attributes, walk_files};
+
+pub fn add(src: &Path, sha: &str, dest_dir: &Path) -> io::Result<Option<PathBuf>> {
+    let meta = fs::metadata(src)?;
+    if xattr::list(src).map(|mut names| names.next().is_some()).unwrap_or(false) {
+        return Ok(None);
+    }
+
+    let dir = Path::new(store_dir()).join(&sha[..2]);
+    fs::create_dir_all(&dir)?;
+    if fs::metadata(&dir)?.dev() != fs::metadata(dest_dir)?.dev() {
+        return Ok(None);
+    }
+    let stored = dir.join(format!("{}-{:o}-{}-{}", sha, meta.mode() & 0o7777, meta.uid(), meta.gid()));
+    let read_only = fs::Permissions::from_mode(meta.mode() & 0o7555);
+    if stored.exists() {
+        if calculate_sha256(&stored.to_string_lossy())? == sha {
+            fs::set_permissions(&stored, read_only)?;
+            return Ok(Some(stored));
+        }
+        fs::remove_file(&stored)?;
+    }
+
+    let partial = dir.join(format!(".{}-{}", sha, process::id()));
+    fs::copy(src, &partial)?;
+    copy_attributes(src, &partial)?;
+    fs::set_permissions(&partial, read_only)?;
+    fs::rename(&partial, &stored)?;
+    Ok(Some(stored))
+}
+
+pub fn evict(sha: &str) -> usize {
+    let Some(Ok(entries)) = sha.get(..2).map(|prefix| fs::read_dir(Path::new(store_dir()).join(prefix))) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(&format!("{}-", sha)))
+        .filter(|entry| fs::remove_file(entry.path()).is_ok())
+        .count()
+}
+
+pub struct StoreUsage {
+    pub files: usize,
+    pub size: u64,
+    pub unused_size: u64,
+}
+
+pub fn usage() -> StoreUsage {
+    let mut usage = StoreUsage { files: 0, size: 0, unused_size: 0 };
+    for rel in walk_files(store_dir()) {
+        let Ok(meta) = fs::metadata(Path::new(store_dir()).join(&rel)) else {
+            continue;
+        };
+        usage.files += 1;
+        usage.size += meta.len();
+        if meta.nlink() == 1 {
+            usage.unused_size += meta.len();
+        }
+    }
+    usage
+}
+
+pub fn prune() -> (usize, u64) {
+    let (mut count, mut freed) = (0, 0);
+    for rel in walk_files(store_dir()) {
+        let path = Path::new(store_dir()).join(&rel);
+        if let Ok(meta) = fs::metadata(&path)
+            && meta.nlink() == 1
+            && fs::remove_file(&path).is_ok()
+        {
+            count += 1;
+            freed += meta.len();
+            if let Some(dir) = path.parent() {
+                let _ = fs::remove_dir(dir);
+            }
+        }
+    }
+    (count, freed)
+}
@@ -0,0 +1,75 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::{self, Command};
+
+use tar::Archive;
+
+use crate::paths::tmp_dir;
+use crate::utils::{host_arch, relocate_payload};
+
+fn systemd_arch(arch: &str) -> &str {
+    match arch {
+        "x86_64" => "x86-64",
+        "aarch64" => "arm64",
+        "i686" => "x86",
+        "powerpc64" => "ppc64-le",
+        other => other,
+    }
+}
+
+pub fn extension_name(output: &str) -> String {
+    let file = Path::new(output).file_name().map(|f| f.to_string_lossy().to_string()).unwrap_or_default();
+    file.strip_suffix(".raw").unwrap_or(&file).to_string()
+}
+
+fn mkfs(stage: &str, output: &str) -> Result<&'static str, String> {
+    let tools: [(&str, Vec<&str>); 2] = [
+        ("mkfs.erofs", vec!["--all-root", output, stage]),
+        ("mksquashfs", vec![stage, output, "-all-root", "-noappend", "-quiet"]),
+    ];
+    for (tool, args) in tools {
+        match Command::new(tool).args(&args).status() {
+            Ok(status) if status.success() => return Ok(tool),
+            Ok(_) => return Err(format!("{} failed on {}", tool, output)),
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(format!("couldn't run {}: {}", tool, e)),
+        }
+    }
+    Err("need mkfs.erofs or mksquashfs to build a sysext, install erofs-utils or squashfs-tools".to_string())
+}
+
+pub fn build(packages: &[(String, String)], arch_specific: bool, output: &str) -> Result<&'static str, String> {
+    let name = extension_name(output);
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return Err(format!("'{}' makes a bad extension name, try something like -o tools.raw", output));
+    }
+
+    let mut tar = tar::Builder::new(Vec::new());
+    for (label, path) in packages {
+        relocate_payload(path, "usr", &mut tar).map_err(|e| format!("couldn't repack {}: {}", label, e))?;
+    }
+    let bytes = tar.into_inner().map_err(|e| e.to_string())?;
+
+    let stage = format!("{}/pls-sysext-{}", tmp_dir(), process::id());
+    let _ = fs::remove_dir_all(&stage);
+    let result = (|| {
+        let mut archive = Archive::new(bytes.as_slice());
+        archive.set_preserve_permissions(true);
+        archive.unpack(&stage).map_err(|e| format!("couldn't stage files: {}", e))?;
+
+        let release_dir = format!("{}/usr/lib/extension-release.d", stage);
+        fs::create_dir_all(&release_dir).map_err(|e| format!("couldn't create {}: {}", release_dir, e))?;
+        let mut release = "ID=_any\n".to_string();
+        if arch_specific {
+            release.push_str(&format!("ARCHITECTURE={}\n", systemd_arch(host_arch())));
+        }
+        fs::write(format!("{}/extension-release.{}", release_dir, name), release)
+            .map_err(|e| format!("couldn't write extension-release: {}", e))?;
+
+        let _ = fs::remove_file(output);
+        mkfs(&stage, output)
+    })();
+    let _ = fs::remove_dir_all(&stage);
+    result
+}
@@ -0,0 +1,96 @@
+use std::env;
+use std::fmt::Display;
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+#[macro_export]
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if !$crate::term::is_quiet() {
+            println!($($arg)*);
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! brief {
+    ($($arg:tt)*) => {
+        if $crate::term::is_quiet() {
+            println!($($arg)*);
+        }
+    };
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+pub fn init(no_color: bool, quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+    let enabled = !no_color
+        && env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+        && env::var("TERM").map(|t| t != "dumb").unwrap_or(true)
+        && io::stdout().is_terminal();
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+fn paint(code: &str, text: impl Display) -> String {
+    if ENABLED.load(Ordering::Relaxed) {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    } else {
+        text.to_string()
+    }
+}
+
+pub fn green(text: impl Display) -> String {
+    paint("32", text)
+}
+
+pub fn yellow(text: impl Display) -> String {
+    paint("33", text)
+}
+
+pub fn red(text: impl Display) -> String {
+    paint("31", text)
+}
+
+pub fn bold(text: impl Display) -> String {
+    paint("1", text)
+}
+
+pub fn dim(text: impl Display) -> String {
+    paint("2", text)
+}
+
+pub fn confirm(question: &str) -> bool {
+    if !io::stdin().is_terminal() {
+        return false;
+    }
+    print!("{} [y/N] ", question);
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).is_ok() && matches!(answer.trim(), "y" | "Y" | "yes")
+}
+
+pub fn pick(question: &str, choices: &[char]) -> Option<char> {
+    if !io::stdin().is_terminal() {
+        return None;
+    }
+    let hint: Vec<String> = choices.iter().map(|c| c.to_string()).collect();
+    loop {
+        print!("{} [{}] ", question, hint.join("/"));
+        let _ = io::stdout().flush();
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer).ok()? == 0 {
+            return None;
+        }
+        if let Some(choice) = answer.trim().chars().next().map(|c| c.to_ascii_lowercase())
+            && choices.contains(&choice)
+        {
+            return Some(choice);
+        }
+    }
+}
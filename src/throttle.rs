@@ -0,0 +1,34 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+static RATE: AtomicU64 = AtomicU64::new(0);
+static BUCKET: Mutex<Option<(Instant, u64)>> = Mutex::new(None);
+
+pub fn init(bytes_per_sec: Option<u64>) {
+    RATE.store(bytes_per_sec.unwrap_or(0), Ordering::Relaxed);
+}
+
+pub fn rate() -> Option<u64> {
+    Some(RATE.load(Ordering::Relaxed)).filter(|rate| *rate > 0)
+}
+
+pub async fn pace(bytes: usize) {
+    let Some(rate) = rate() else {
+        return;
+    };
+    let wait = {
+        let mut bucket = BUCKET.lock().unwrap_or_else(|e| e.into_inner());
+        let (start, sent) = bucket.get_or_insert_with(|| (Instant::now(), 0));
+        let allowed = Duration::from_secs_f64(*sent as f64 / rate as f64);
+        if start.elapsed() > allowed + Duration::from_secs(1) {
+            *start = Instant::now();
+            *sent = 0;
+        }
+        *sent += bytes as u64;
+        Duration::from_secs_f64(*sent as f64 / rate as f64).saturating_sub(start.elapsed())
+    };
+    if !wait.is_zero() {
+        tokio::time::sleep(wait).await;
+    }
+}
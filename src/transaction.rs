@@ -0,0 +1,128 @@
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::Path;
+use std::process;
+
+use crate::exit::{Failure, io_fail};
+use crate::paths::tmp_dir;
+use crate::utils::copy_attributes;
+
+pub struct Transaction {
+    backup_dir: String,
+    created_files: Vec<String>,
+    created_dirs: Vec<String>,
+    replaced: Vec<(String, String)>,
+}
+
+impl Transaction {
+    pub fn begin() -> Result<Self, Failure> {
+        let backup_dir = format!("{}/pls-txn-{}", tmp_dir(), process::id());
+        let _ = fs::remove_dir_all(&backup_dir);
+        fs::create_dir_all(&backup_dir)
+            .map_err(|e| io_fail(&e, format!("couldn't start transaction: {}", e)))?;
+
+        Ok(Self {
+            backup_dir,
+            created_files: Vec::new(),
+            created_dirs: Vec::new(),
+            replaced: Vec::new(),
+        })
+    }
+
+    pub fn create_dir(&mut self, path: &str) -> Result<Vec<String>, Failure> {
+        let mut missing = Vec::new();
+        let mut current = Path::new(path);
+        while !current.exists() {
+            missing.push(current.to_string_lossy().to_string());
+            match current.parent() {
+                Some(parent) => current = parent,
+                None => break,
+            }
+        }
+
+        fs::create_dir_all(path).map_err(|e| io_fail(&e, format!("couldn't create {}: {}", path, e)))?;
+        missing.reverse();
+        self.created_dirs.extend(missing.iter().cloned());
+        Ok(missing)
+    }
+
+    fn backup(&mut self, dest: &str) -> Result<(), Failure> {
+        if self.created_files.iter().any(|f| f == dest) || self.replaced.iter().any(|(d, _)| d == dest) {
+            return Ok(());
+        }
+
+        if let Ok(target) = fs::read_link(dest) {
+            let backup = format!("{}/{}", self.backup_dir, self.replaced.len());
+            symlink(target, &backup).map_err(|e| io_fail(&e, format!("couldn't back up {}: {}", dest, e)))?;
+            self.replaced.push((dest.to_string(), backup));
+        } else if Path::new(dest).exists() {
+            let backup = format!("{}/{}", self.backup_dir, self.replaced.len());
+            fs::copy(dest, &backup).map_err(|e| io_fail(&e, format!("couldn't back up {}: {}", dest, e)))?;
+            self.replaced.push((dest.to_string(), backup));
+        } else {
+            self.created_files.push(dest.to_string());
+        }
+        Ok(())
+    }
+
+    pub fn copy_file(&mut self, src: &Path, dest: &str) -> Result<(), Failure> {
+        self.backup(dest)?;
+        let _ = fs::remove_file(dest);
+        fs::copy(src, dest).map_err(|e| io_fail(&e, format!("couldn't copy {}: {}", dest, e)))?;
+        copy_attributes(src, Path::new(dest)).map_err(|e| io_fail(&e, format!("couldn't keep modes/owner/xattrs on {}: {}", dest, e)))
+    }
+
+    pub fn link_file(&mut self, src: &Path, dest: &str) -> Result<(), Failure> {
+        self.backup(dest)?;
+        let _ = fs::remove_file(dest);
+        if fs::hard_link(src, dest).is_ok() {
+            return Ok(());
+        }
+        self.copy_file(src, dest)
+    }
+
+    pub fn symlink(&mut self, target: &Path, dest: &str) -> Result<(), Failure> {
+        self.backup(dest)?;
+        let _ = fs::remove_file(dest);
+        symlink(target, dest).map_err(|e| io_fail(&e, format!("couldn't link {}: {}", dest, e)))
+    }
+
+    pub fn write_file(&mut self, dest: &str, content: &str) -> Result<(), Failure> {
+        self.backup(dest)?;
+        let _ = fs::remove_file(dest);
+        fs::write(dest, content).map_err(|e| io_fail(&e, format!("couldn't write {}: {}", dest, e)))
+    }
+
+    pub fn remove_file(&mut self, path: &str) -> Result<(), Failure> {
+        if fs::symlink_metadata(path).is_err() {
+            return Ok(());
+        }
+        self.backup(path)?;
+        fs::remove_file(path).map_err(|e| io_fail(&e, format!("couldn't remove {}: {}", path, e)))
+    }
+
+    pub fn rollback(self) {
+        for file in self.created_files.iter().rev() {
+            let _ = fs::remove_file(file);
+        }
+        for (dest, backup) in &self.replaced {
+            let _ = fs::remove_file(dest);
+            match fs::read_link(backup) {
+                Ok(target) => {
+                    let _ = symlink(target, dest);
+                }
+                Err(_) => {
+                    let _ = fs::copy(backup, dest);
+                }
+            }
+        }
+        for dir in self.created_dirs.iter().rev() {
+            let _ = fs::remove_dir(dir);
+        }
+        let _ = fs::remove_dir_all(&self.backup_dir);
+    }
+
+    pub fn commit(self) {
+        let _ = fs::remove_dir_all(&self.backup_dir);
+    }
+}
@@ -1,8 +1,13 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::fs;
 use std::io;
 
+use crate::utils::{checksum_matches, parse_list, version_in_range};
+
+pub type Provenance = BTreeMap<String, String>;
+
 #[derive(Serialize, Deserialize)]
 pub struct RepoIndex {
     pub version: u32,
@@ -10,29 +15,402 @@ pub struct RepoIndex {
     pub packages: HashMap<String, PackageMeta>,
     #[serde(default)]
     pub bundles: HashMap<String, Vec<String>>,
+    #[serde(skip)]
+    pub by_repo: HashMap<String, PackageMeta>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct PackageMeta {
     pub version: String,
     pub size: u64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub installed_size: u64,
     pub sha256: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub b3sum: String,
     #[serde(default)]
     pub deps: Vec<String>,
+    #[serde(default)]
+    pub optional: Vec<OptionalDep>,
     pub desc: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub changelog: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub arch: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub file: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub builds: Vec<ArchBuild>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub provenance: Provenance,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub split_from: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub provides: Vec<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub maintainer: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub homepage: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<Deprecation>,
+    #[serde(skip)]
+    pub repo: String,
+    #[serde(skip)]
+    pub repo_url: String,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Deprecation {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub reason: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub replaced_by: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub eol: String,
+}
+
+impl Deprecation {
+    pub fn is_eol(&self) -> bool {
+        !self.eol.is_empty() && self.eol <= chrono::Local::now().format("%Y-%m-%d").to_string()
+    }
+
+    pub fn describe(&self, name: &str) -> String {
+        let mut note = match (self.eol.is_empty(), self.is_eol()) {
+            (false, true) => format!("{} reached end of life on {}", name, self.eol),
+            (false, false) => format!("{} is deprecated, end of life on {}", name, self.eol),
+            _ => format!("{} is deprecated", name),
+        };
+        if !self.reason.is_empty() {
+            note.push_str(&format!(" ({})", self.reason));
+        }
+        if !self.replaced_by.is_empty() {
+            note.push_str(&format!(", use {} instead", self.replaced_by));
+        }
+        note
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ArchBuild {
+    pub arch: String,
+    pub file: String,
+    pub size: u64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub installed_size: u64,
+    pub sha256: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub b3sum: String,
+}
+
+fn is_zero(n: &u64) -> bool {
+    *n == 0
+}
+
+impl PackageMeta {
+    pub fn matches(&self, path: &str) -> bool {
+        checksum_matches(path, &self.sha256, &self.b3sum)
+    }
+
+    pub fn file_name(&self, name: &str) -> String {
+        if self.file.is_empty() { format!("{}.pls", name) } else { self.file.clone() }
+    }
+
+    pub fn artifacts(&self, name: &str) -> Vec<ArchBuild> {
+        if self.builds.is_empty() {
+            return vec![ArchBuild {
+                arch: self.arch.clone(),
+                file: self.file_name(name),
+                size: self.size,
+                installed_size: self.installed_size,
+                sha256: self.sha256.clone(),
+                b3sum: self.b3sum.clone(),
+            }];
+        }
+        self.builds.clone()
+    }
+
+    pub fn for_arch(&self, name: &str, arch: &str) -> Option<PackageMeta> {
+        let fits = |a: &str| a.is_empty() || a == "any" || a == arch;
+        let build = self
+            .artifacts(name)
+            .into_iter()
+            .filter(|b| fits(&b.arch))
+            .max_by_key(|b| b.arch == arch)?;
+        Some(PackageMeta {
+            size: build.size,
+            installed_size: build.installed_size,
+            sha256: build.sha256,
+            b3sum: build.b3sum,
+            arch: build.arch,
+            file: build.file,
+            builds: Vec::new(),
+            ..self.clone()
+        })
+    }
+}
+
+impl ArchBuild {
+    pub fn matches(&self, path: &str) -> bool {
+        checksum_matches(path, &self.sha256, &self.b3sum)
+    }
+}
+
+impl RepoIndex {
+    pub fn for_arch(mut self, arch: &str) -> Self {
+        self.packages = self
+            .packages
+            .iter()
+            .filter_map(|(name, meta)| Some((name.clone(), meta.for_arch(name, arch)?)))
+            .collect();
+        self
+    }
+
+    pub fn tag(mut self, repo: &str, url: &str) -> Self {
+        for (name, meta) in self.packages.iter_mut() {
+            meta.repo = repo.to_string();
+            meta.repo_url = url.to_string();
+            self.by_repo.insert(format!("{}/{}", repo, name), meta.clone());
+        }
+        self
+    }
+
+    pub fn merge(&mut self, other: RepoIndex) {
+        for (name, meta) in other.packages {
+            self.packages.entry(name).or_insert(meta);
+        }
+        for (name, members) in other.bundles {
+            self.bundles.entry(name).or_insert(members);
+        }
+        self.by_repo.extend(other.by_repo);
+    }
+
+    pub fn expand_bundle(&self, name: &str) -> Result<Vec<String>, String> {
+        fn expand(bundles: &HashMap<String, Vec<String>>, name: &str, path: &mut Vec<String>, out: &mut Vec<String>) -> Result<(), String> {
+            if path.iter().any(|seen| seen == name) {
+                return Err(format!("bundles go in a circle: @{} -> @{}", path.join(" -> @"), name));
+            }
+            let members = bundles.get(name).ok_or_else(|| match path.last() {
+                Some(parent) => format!("bundle '{}' includes @{} but there's no such bundle", parent, name),
+                None => format!("bundle '{}' not found in repo", name),
+            })?;
+            path.push(name.to_string());
+            for member in members {
+                match member.strip_prefix('@') {
+                    Some(inner) => expand(bundles, inner, path, out)?,
+                    None if !out.contains(member) => out.push(member.clone()),
+                    None => {}
+                }
+            }
+            path.pop();
+            Ok(())
+        }
+
+        let mut packages = Vec::new();
+        expand(&self.bundles, name, &mut Vec::new(), &mut packages)?;
+        Ok(packages)
+    }
+
+    pub fn pin(&mut self, reference: &str) -> String {
+        if let Some((_, name)) = reference.split_once('/')
+            && let Some(meta) = self.by_repo.get(reference)
+        {
+            self.packages.insert(name.to_string(), meta.clone());
+            return name.to_string();
+        }
+        reference.to_string()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum InstallReason {
+    Explicit,
+    Dependency,
+}
+
+impl InstallReason {
+    pub fn parse(value: &str) -> Self {
+        match value.trim() {
+            "dependency" => Self::Dependency,
+            _ => Self::Explicit,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Explicit => "explicit",
+            Self::Dependency => "dependency",
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct Change {
+    pub name: String,
+    pub version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_version: Option<String>,
+}
+
+impl Change {
+    pub fn action(&self) -> &'static str {
+        match &self.old_version {
+            Some(old) if *old != self.version => "update",
+            _ => "install",
+        }
+    }
+}
+
+pub type Advisories = HashMap<String, Vec<Advisory>>;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Advisory {
+    pub id: String,
+    #[serde(default)]
+    pub severity: String,
+    pub affected: String,
+    #[serde(default)]
+    pub fixed: String,
+    #[serde(default)]
+    pub summary: String,
+}
+
+impl Advisory {
+    pub fn affects(&self, version: &str) -> bool {
+        version_in_range(version, &self.affected)
+    }
+
+    pub fn is_serious(&self) -> bool {
+        matches!(self.severity.to_lowercase().as_str(), "high" | "critical")
+    }
+}
+
+impl fmt::Display for Advisory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = if self.severity.is_empty() { "UNKNOWN".to_string() } else { self.severity.to_uppercase() };
+        write!(f, "[{}] {}", severity, self.id)?;
+        if !self.summary.is_empty() {
+            write!(f, ": {}", self.summary)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OptionalDep {
+    pub name: String,
+    #[serde(default)]
+    pub reason: String,
+}
+
+impl OptionalDep {
+    pub fn parse(value: &str) -> Self {
+        match value.split_once(':') {
+            Some((name, reason)) => Self {
+                name: name.trim().to_string(),
+                reason: reason.trim().to_string(),
+            },
+            None => Self {
+                name: value.trim().to_string(),
+                reason: String::new(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for OptionalDep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.reason.is_empty() {
+            write!(f, "{}", self.name)
+        } else {
+            write!(f, "{}: {}", self.name, self.reason)
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Alternative {
+    pub name: String,
+    pub path: String,
+    pub priority: i32,
 }
 
+impl Alternative {
+    pub fn parse(value: &str, package: &str) -> Option<Self> {
+        let mut parts = value.split(':').map(str::trim);
+        let name = parts.next().filter(|name| !name.is_empty() && !name.contains('/'))?.to_string();
+        let mut path = format!("bin/{}", package);
+        let mut priority = 0;
+        for part in parts {
+            match part.parse() {
+                Ok(n) => priority = n,
+                Err(_) => path = part.trim_start_matches('/').to_string(),
+            }
+        }
+        Some(Self { name, path, priority })
+    }
+}
+
+impl fmt::Display for Alternative {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.name, self.path, self.priority)
+    }
+}
+
+#[derive(Clone, Default)]
 pub struct PackageInfo {
     pub name: String,
     pub version: String,
     pub depend: Vec<String>,
+    pub optional: Vec<OptionalDep>,
+    pub arch: String,
+    pub split_from: String,
+    pub provides: Vec<String>,
+    pub alternatives: Vec<Alternative>,
+    pub desc: String,
+    pub keywords: Vec<String>,
+    pub maintainer: String,
+    pub homepage: String,
+    pub commit: String,
+    pub tag: String,
+    pub dirty: bool,
 }
 
 impl PackageInfo {
+    pub fn revision(&self) -> Option<String> {
+        if self.commit.is_empty() {
+            return None;
+        }
+        let mut notes = Vec::new();
+        if !self.tag.is_empty() {
+            notes.push(self.tag.as_str());
+        }
+        if self.dirty {
+            notes.push("dirty");
+        }
+        match notes.is_empty() {
+            true => Some(self.commit.clone()),
+            false => Some(format!("{} ({})", self.commit, notes.join(", "))),
+        }
+    }
+
     pub fn parse_info(content: &str) -> Self {
         let mut name = String::new();
         let mut version = String::new();
         let mut depend = Vec::new();
+        let mut optional = Vec::new();
+        let mut arch = String::new();
+        let mut split_from = String::new();
+        let mut provides = Vec::new();
+        let mut alternatives = Vec::new();
+        let mut desc = String::new();
+        let mut keywords = Vec::new();
+        let mut maintainer = String::new();
+        let mut homepage = String::new();
+        let mut commit = String::new();
+        let mut tag = String::new();
+        let mut dirty = false;
 
         for line in content.lines() {
             let line = line.trim();
@@ -41,11 +419,67 @@ impl PackageInfo {
                     "name" => name = value.to_string(),
                     "version" => version = value.to_string(),
                     "depend" => depend.push(value.to_string()),
+                    "optional" => optional.push(OptionalDep::parse(value)),
+                    "arch" => arch = value.to_string(),
+                    "split_from" => split_from = value.to_string(),
+                    "provides" => provides.push(value.to_string()),
+                    "alternative" => alternatives.extend(Alternative::parse(value, &name)),
+                    "desc" => desc = value.to_string(),
+                    "keyword" => keywords.push(value.to_string()),
+                    "maintainer" => maintainer = value.to_string(),
+                    "homepage" => homepage = value.to_string(),
+                    "commit" => commit = value.to_string(),
+                    "tag" => tag = value.to_string(),
+                    "dirty" => dirty = value == "true",
                     _ => {}
                 }
             }
         }
-        Self { name, version, depend }
+        Self { name, version, depend, optional, arch, split_from, provides, alternatives, desc, keywords, maintainer, homepage, commit, tag, dirty }
+    }
+
+    pub fn to_info(&self) -> String {
+        let mut out = format!("name = {}\nversion = {}\n", self.name, self.version);
+        if !self.desc.is_empty() {
+            out.push_str(&format!("desc = {}\n", self.desc));
+        }
+        for keyword in &self.keywords {
+            out.push_str(&format!("keyword = {}\n", keyword));
+        }
+        if !self.maintainer.is_empty() {
+            out.push_str(&format!("maintainer = {}\n", self.maintainer));
+        }
+        if !self.homepage.is_empty() {
+            out.push_str(&format!("homepage = {}\n", self.homepage));
+        }
+        if !self.arch.is_empty() {
+            out.push_str(&format!("arch = {}\n", self.arch));
+        }
+        if !self.commit.is_empty() {
+            out.push_str(&format!("commit = {}\n", self.commit));
+        }
+        if !self.tag.is_empty() {
+            out.push_str(&format!("tag = {}\n", self.tag));
+        }
+        if self.dirty {
+            out.push_str("dirty = true\n");
+        }
+        if !self.split_from.is_empty() {
+            out.push_str(&format!("split_from = {}\n", self.split_from));
+        }
+        for dep in &self.depend {
+            out.push_str(&format!("depend = {}\n", dep));
+        }
+        for soname in &self.provides {
+            out.push_str(&format!("provides = {}\n", soname));
+        }
+        for opt in &self.optional {
+            out.push_str(&format!("optional = {}\n", opt));
+        }
+        for alternative in &self.alternatives {
+            out.push_str(&format!("alternative = {}\n", alternative));
+        }
+        out
     }
 
     pub fn from_file(path: &str) -> io::Result<Self> {
@@ -57,6 +491,10 @@ impl PackageInfo {
         let mut name = String::new();
         let mut version = String::new();
         let mut depend = Vec::new();
+        let mut desc = String::new();
+        let mut keywords = Vec::new();
+        let mut maintainer = String::new();
+        let mut homepage = String::new();
         let mut section = String::new();
 
         for line in content.lines() {
@@ -68,16 +506,21 @@ impl PackageInfo {
                     match key {
                         "name" => name = value.trim_matches('"').to_string(),
                         "version" => version = value.trim_matches('"').to_string(),
+                        "description" => desc = value.trim_matches('"').to_string(),
+                        "keywords" => keywords = parse_list(value),
+                        "authors" => maintainer = parse_list(value).into_iter().next().unwrap_or_default(),
+                        "homepage" => homepage = value.trim_matches('"').to_string(),
+                        "repository" if homepage.is_empty() => homepage = value.trim_matches('"').to_string(),
                         _ => {}
                     }
                 }
-            } else if section == "dependencies" {
-                if let Some((dep_name, _)) = line.split_once(" = ") {
-                    depend.push(dep_name.trim().to_string());
-                }
+            } else if section == "dependencies"
+                && let Some((dep_name, _)) = line.split_once(" = ")
+            {
+                depend.push(dep_name.trim().to_string());
             }
         }
-        Self { name, version, depend }
+        Self { name, version, depend, desc, keywords, maintainer, homepage, ..Default::default() }
     }
 
     pub fn parse_cmake(content: &str) -> Self {
@@ -111,7 +554,7 @@ impl PackageInfo {
             version = "0.1.0".to_string();
         }
 
-        Self { name, version, depend: Vec::new() }
+        Self { name, version, ..Default::default() }
     }
 
     pub fn parse_meson(content: &str) -> Self {
@@ -121,18 +564,18 @@ impl PackageInfo {
         for line in content.lines() {
             let line = line.trim();
             if line.starts_with("project(") {
-                if let Some(start) = line.find('\'') {
-                    if let Some(end) = line[start + 1..].find('\'') {
-                        name = line[start + 1..start + 1 + end].to_string();
-                    }
+                if let Some(start) = line.find('\'')
+                    && let Some(end) = line[start + 1..].find('\'')
+                {
+                    name = line[start + 1..start + 1 + end].to_string();
                 }
 
                 if let Some(ver_pos) = line.find("version:") {
                     let after_ver = &line[ver_pos + 8..];
-                    if let Some(start) = after_ver.find('\'') {
-                        if let Some(end) = after_ver[start + 1..].find('\'') {
-                            version = after_ver[start + 1..start + 1 + end].to_string();
-                        }
+                    if let Some(start) = after_ver.find('\'')
+                        && let Some(end) = after_ver[start + 1..].find('\'')
+                    {
+                        version = after_ver[start + 1..start + 1 + end].to_string();
                     }
                 }
             }
@@ -142,13 +585,18 @@ impl PackageInfo {
             version = "0.1.0".to_string();
         }
 
-        Self { name, version, depend: Vec::new() }
+        Self { name, version, ..Default::default() }
     }
 
     pub fn parse_pls_toml(content: &str) -> Self {
         let mut name = String::new();
         let mut version = String::new();
         let mut depend = Vec::new();
+        let mut optional = Vec::new();
+        let mut desc = String::new();
+        let mut keywords = Vec::new();
+        let mut maintainer = String::new();
+        let mut homepage = String::new();
 
         for line in content.lines() {
             let line = line.trim();
@@ -158,19 +606,12 @@ impl PackageInfo {
                 match key {
                     "name" => name = value.to_string(),
                     "version" => version = value.to_string(),
-                    "depend" | "deps" => {
-                        if value.starts_with('[') {
-                            let inner = value.trim_matches(|c| c == '[' || c == ']');
-                            for dep in inner.split(',') {
-                                let dep = dep.trim().trim_matches('"').trim_matches('\'');
-                                if !dep.is_empty() {
-                                    depend.push(dep.to_string());
-                                }
-                            }
-                        } else {
-                            depend.push(value.to_string());
-                        }
-                    }
+                    "depend" | "deps" => depend.extend(parse_list(value)),
+                    "optional" => optional.extend(parse_list(value).iter().map(|v| OptionalDep::parse(v))),
+                    "description" | "desc" => desc = value.to_string(),
+                    "keywords" => keywords = parse_list(value),
+                    "maintainer" => maintainer = value.to_string(),
+                    "homepage" => homepage = value.to_string(),
                     _ => {}
                 }
             }
@@ -180,6 +621,6 @@ impl PackageInfo {
             version = "0.1.0".to_string();
         }
 
-        Self { name, version, depend }
+        Self { name, version, depend, optional, desc, keywords, maintainer, homepage, ..Default::default() }
     }
 }
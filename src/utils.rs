@@ -1,58 +1,758 @@
+use serde::Serialize;
 use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::fs;
 use std::fs::File;
-use std::io::{self, Read};
+use std::io::{self, Read, Write};
+use std::os::unix::fs::{MetadataExt, PermissionsExt, lchown};
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::{Mutex, mpsc};
+use std::thread;
+use std::time::{Duration, Instant};
 use tar::Archive;
 
-use crate::DB_DIR;
+use crate::fakeroot::{self, Rule};
+use crate::paths::{db_dir, running_as_root};
+use crate::types::{InstallReason, PackageInfo, RepoIndex};
+
+const PROGRESS_MIN_SIZE: u64 = 16 * 1024 * 1024;
 
 pub fn extract_package(archive_path: &str, dest: &str) -> io::Result<()> {
-    let _ = fs::remove_dir_all(dest);
+    let _ = remove_tree(dest);
     fs::create_dir_all(dest)?;
 
     let file = File::open(archive_path)?;
+    let show_progress = file.metadata()?.len() >= PROGRESS_MIN_SIZE && !crate::term::is_quiet();
     let decoder = zstd::stream::Decoder::new(file)?;
     let mut archive = Archive::new(decoder);
-    archive.unpack(dest)?;
+    archive.set_preserve_permissions(true);
+    archive.set_preserve_ownerships(running_as_root());
+    archive.set_unpack_xattrs(true);
+
+    let started = Instant::now();
+    let mut last_report = started;
+    let (mut files, mut bytes) = (0u64, 0u64);
+    // a read-only dir's mode would lock us out of its children, so dirs go last and deepest first
+    let mut dirs = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.header().entry_type().is_dir() {
+            dirs.push(entry);
+            continue;
+        }
+        if entry.header().entry_type().is_file() {
+            bytes += entry.size();
+            files += 1;
+        }
+        entry.unpack_in(dest)?;
+
+        if show_progress && last_report.elapsed() >= Duration::from_millis(200) {
+            last_report = Instant::now();
+            print_extract_progress(files, bytes, started);
+        }
+    }
+    dirs.sort_by(|a, b| b.path_bytes().cmp(&a.path_bytes()));
+    for mut dir in dirs {
+        dir.unpack_in(dest)?;
+    }
+
+    if show_progress {
+        print_extract_progress(files, bytes, started);
+        println!();
+    }
     Ok(())
 }
 
-pub fn create_package(source_dir: &str, output_path: &str) -> io::Result<()> {
-    let file = File::create(output_path)?;
-    let encoder = zstd::stream::Encoder::new(file, 3)?;
-    let mut tar = tar::Builder::new(encoder);
-    tar.append_dir_all(".", source_dir)?;
-    let encoder = tar.into_inner()?;
-    encoder.finish()?;
+/// remove_dir_all for unpacked packages, which can carry dirs we aren't allowed to empty
+pub fn remove_tree(path: &str) -> io::Result<()> {
+    make_dirs_writable(Path::new(path));
+    fs::remove_dir_all(path)
+}
+
+fn make_dirs_writable(dir: &Path) {
+    let Ok(meta) = fs::symlink_metadata(dir) else {
+        return;
+    };
+    if !meta.is_dir() {
+        return;
+    }
+    let mut perms = meta.permissions();
+    if perms.mode() & 0o700 != 0o700 {
+        perms.set_mode(perms.mode() | 0o700);
+        let _ = fs::set_permissions(dir, perms);
+    }
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+            make_dirs_writable(&entry.path());
+        }
+    }
+}
+
+fn print_extract_progress(files: u64, bytes: u64, started: Instant) {
+    let secs = started.elapsed().as_secs_f64().max(0.001);
+    print!(
+        "\r  unpacking: {} files, {} ({}/s)   ",
+        files,
+        format_size(bytes),
+        format_size((bytes as f64 / secs) as u64)
+    );
+    let _ = io::stdout().flush();
+}
+
+pub fn read_package_file(archive_path: &str, file_name: &str) -> io::Result<String> {
+    let file = File::open(archive_path)?;
+    let decoder = zstd::stream::Decoder::new(file)?;
+    let mut archive = Archive::new(decoder);
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.path()?.to_string_lossy().trim_start_matches("./") == file_name {
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+            return Ok(content);
+        }
+    }
+
+    Err(io::Error::new(io::ErrorKind::NotFound, format!("no {} file in package", file_name)))
+}
+
+pub struct PackageEntry {
+    pub path: String,
+    pub size: u64,
+    pub mode: String,
+    pub link: Option<String>,
+}
+
+fn mode_string(kind: tar::EntryType, mode: u32) -> String {
+    let mut out = String::from(match kind {
+        tar::EntryType::Directory => 'd',
+        tar::EntryType::Symlink => 'l',
+        tar::EntryType::Link => 'h',
+        _ => '-',
+    });
+    for (shift, special, mark) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+        let bits = (mode >> shift) & 7;
+        out.push(if bits & 4 != 0 { 'r' } else { '-' });
+        out.push(if bits & 2 != 0 { 'w' } else { '-' });
+        out.push(match (bits & 1 != 0, mode & special != 0) {
+            (true, true) => mark,
+            (false, true) => mark.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    out
+}
+
+pub fn list_package(archive_path: &str, mut visit: impl FnMut(PackageEntry)) -> io::Result<()> {
+    let file = File::open(archive_path)?;
+    let decoder = zstd::stream::Decoder::new(file)?;
+    let mut archive = Archive::new(decoder);
+
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let header = entry.header();
+        visit(PackageEntry {
+            path: entry.path()?.to_string_lossy().trim_start_matches("./").to_string(),
+            size: entry.size(),
+            mode: mode_string(header.entry_type(), header.mode()?),
+            link: entry.link_name()?.map(|target| target.to_string_lossy().to_string()),
+        });
+    }
     Ok(())
 }
 
-pub fn resolve_package_path(input: &str) -> Option<String> {
-    if input.contains('/') || input.ends_with(".pls") {
-        if Path::new(input).exists() {
-            return Some(input.to_string());
+pub fn relocate_payload<W: Write>(archive_path: &str, prefix: &str, tar: &mut tar::Builder<W>) -> io::Result<u64> {
+    let mut archive = Archive::new(zstd::stream::Decoder::new(File::open(archive_path)?)?);
+    let mut size = 0;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let rel = entry.path()?.to_string_lossy().trim_start_matches("./").trim_end_matches('/').to_string();
+        if !["bin", "include", "lib", "share"].iter().any(|dir| rel == *dir || rel.starts_with(&format!("{}/", dir))) {
+            continue;
+        }
+        let name = format!("{}/{}", prefix, rel);
+        let mut header = entry.header().clone();
+        size += entry.size();
+        match entry.link_name()?.map(|target| target.to_string_lossy().to_string()) {
+            Some(target) if header.entry_type().is_hard_link() => {
+                tar.append_link(&mut header, &name, format!("{}/{}", prefix, target.trim_start_matches("./")))?
+            }
+            Some(target) => tar.append_link(&mut header, &name, target)?,
+            None => tar.append_data(&mut header, &name, &mut entry)?,
+        }
+    }
+    Ok(size)
+}
+
+pub fn read_package_info(archive_path: &str) -> io::Result<PackageInfo> {
+    read_package_file(archive_path, "info").map(|content| PackageInfo::parse_info(&content))
+}
+
+fn read_xattrs(path: &Path) -> Vec<(String, Vec<u8>)> {
+    let Ok(names) = xattr::list(path) else {
+        return Vec::new();
+    };
+    names
+        .filter_map(|name| {
+            let value = xattr::get(path, &name).ok()??;
+            Some((name.to_string_lossy().to_string(), value))
+        })
+        .collect()
+}
+
+fn append_entry<W: Write>(tar: &mut tar::Builder<W>, path: &Path, name: &Path, keep_owner: bool, perms: &[Rule]) -> io::Result<()> {
+    let rel = name.strip_prefix(".").unwrap_or(name).to_string_lossy().to_string();
+    if let Ok(target) = fs::read_link(path) {
+        let mut header = tar::Header::new_gnu();
+        header.set_metadata(&fs::symlink_metadata(path)?);
+        header.set_size(0);
+        if !keep_owner {
+            header.set_uid(0);
+            header.set_gid(0);
+        }
+        fakeroot::apply(perms, &rel, &mut header);
+        return tar.append_link(&mut header, name, target);
+    }
+
+    let meta = fs::metadata(path)?;
+    let xattrs = read_xattrs(path);
+    if !xattrs.is_empty() {
+        let keys: Vec<String> = xattrs.iter().map(|(key, _)| format!("SCHILY.xattr.{}", key)).collect();
+        tar.append_pax_extensions(keys.iter().map(String::as_str).zip(xattrs.iter().map(|(_, v)| v.as_slice())))?;
+    }
+
+    let mut header = tar::Header::new_gnu();
+    header.set_metadata(&meta);
+    if !keep_owner {
+        header.set_uid(0);
+        header.set_gid(0);
+    }
+    fakeroot::apply(perms, &rel, &mut header);
+    if !meta.is_dir() {
+        return tar.append_data(&mut header, name, File::open(path)?);
+    }
+
+    tar.append_data(&mut header, name, io::empty())?;
+    let mut children: Vec<_> = fs::read_dir(path)?.flatten().map(|e| e.file_name()).collect();
+    children.sort();
+    for child in children {
+        append_entry(tar, &path.join(&child), &name.join(&child), keep_owner, perms)?;
+    }
+    Ok(())
+}
+
+pub fn create_package(source_dir: &str, output_path: &str, perms: &[Rule]) -> io::Result<()> {
+    let partial = format!("{}.part", output_path);
+    let result = (|| {
+        let file = File::create(&partial)?;
+        let encoder = zstd::stream::Encoder::new(file, 3)?;
+        let mut tar = tar::Builder::new(encoder);
+        append_entry(&mut tar, Path::new(source_dir), Path::new("."), running_as_root(), perms)?;
+        let encoder = tar.into_inner()?;
+        encoder.finish()?.sync_all()?;
+        fs::rename(&partial, output_path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result
+}
+
+const KNOWN_ARCHES: [&str; 9] = ["x86_64", "aarch64", "arm", "x86", "i686", "riscv64", "powerpc64", "s390x", "any"];
+
+pub fn host_arch() -> &'static str {
+    std::env::consts::ARCH
+}
+
+pub fn package_file_name(name: &str, version: &str, arch: &str) -> String {
+    let arch = if arch.is_empty() { "any" } else { arch };
+    format!("{}-{}-{}.pls", name, version, arch)
+}
+
+pub fn parse_package_file_name(file_name: &str) -> Option<(String, String, String)> {
+    let stem = file_name.strip_suffix(".pls")?;
+    let (rest, arch) = stem.rsplit_once('-')?;
+    let split = rest.rmatch_indices('-').map(|(i, _)| i).find(|i| valid_version(&rest[i + 1..]))?;
+    let (name, version) = (&rest[..split], &rest[split + 1..]);
+    if !valid_package_name(name) || !KNOWN_ARCHES.contains(&arch) {
+        return None;
+    }
+    Some((name.to_string(), version.to_string(), arch.to_string()))
+}
+
+pub fn package_file_version(file_name: &str, name: &str) -> Option<(String, String)> {
+    let stem = file_name.strip_suffix(".pls")?.strip_prefix(name)?.strip_prefix('-')?;
+    let (version, arch) = stem.rsplit_once('-')?;
+    let major = version.split(['.', '-', '+', '~']).next().unwrap_or_default();
+    if !valid_version(version) || !major.chars().all(|c| c.is_ascii_digit()) || !KNOWN_ARCHES.contains(&arch) {
+        return None;
+    }
+    Some((version.to_string(), arch.to_string()))
+}
+
+fn split_prerelease(version: &str) -> (&str, Option<&str>) {
+    let bytes = version.as_bytes();
+    let split = (0..bytes.len()).find(|&i| bytes[i] == b'~' || (bytes[i] == b'-' && bytes.get(i + 1).is_some_and(|c| !c.is_ascii_digit())));
+    match split {
+        Some(i) => (&version[..i], Some(&version[i + 1..])),
+        None => (version, None),
+    }
+}
+
+fn version_parts(version: &str) -> Vec<u64> {
+    split_prerelease(version)
+        .0
+        .split(['.', '-', '+'])
+        .map(|p| p.trim_start_matches(|c: char| !c.is_ascii_digit()))
+        .map(|p| p[..p.find(|c: char| !c.is_ascii_digit()).unwrap_or(p.len())].parse().unwrap_or(0))
+        .collect()
+}
+
+fn compare_prerelease(a: &str, b: &str) -> Ordering {
+    let mut a = a.split(['.', '-', '~', '+']);
+    let mut b = b.split(['.', '-', '~', '+']);
+    loop {
+        let order = match (a.next(), b.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => match (x.parse::<u64>(), y.parse::<u64>()) {
+                (Ok(x), Ok(y)) => x.cmp(&y),
+                (Ok(_), Err(_)) => Ordering::Less,
+                (Err(_), Ok(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => {
+                    let (x_word, x_num) = x.split_at(x.find(|c: char| c.is_ascii_digit()).unwrap_or(x.len()));
+                    let (y_word, y_num) = y.split_at(y.find(|c: char| c.is_ascii_digit()).unwrap_or(y.len()));
+                    x_word.cmp(y_word).then_with(|| x_num.parse::<u64>().unwrap_or(0).cmp(&y_num.parse().unwrap_or(0)))
+                }
+            },
+        };
+        if order != Ordering::Equal {
+            return order;
+        }
+    }
+}
+
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let (pa, pb) = (version_parts(a), version_parts(b));
+    let release = (0..pa.len().max(pb.len()))
+        .map(|i| pa.get(i).unwrap_or(&0).cmp(pb.get(i).unwrap_or(&0)))
+        .find(|order| *order != Ordering::Equal)
+        .unwrap_or(Ordering::Equal);
+    release.then_with(|| match (split_prerelease(a).1, split_prerelease(b).1) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(x), Some(y)) => compare_prerelease(x, y),
+    })
+}
+
+pub fn is_major_jump(from: &str, to: &str) -> bool {
+    let compatible = |v: &str| -> Vec<u64> {
+        let parts = version_parts(v);
+        let significant = parts.iter().position(|p| *p != 0).map_or(parts.len(), |i| i + 1);
+        parts[..significant].to_vec()
+    };
+    compare_versions(from, to) == Ordering::Less && compatible(from) != compatible(to)
+}
+
+pub fn split_dep(dep: &str) -> (&str, &str) {
+    let dep = dep.trim();
+    let split = dep.find(|c: char| c.is_whitespace() || "<>=!".contains(c)).unwrap_or(dep.len());
+    (&dep[..split], dep[split..].trim())
+}
+
+pub fn version_in_range(version: &str, range: &str) -> bool {
+    range.split(',').map(str::trim).filter(|c| !c.is_empty()).all(|constraint| {
+        if constraint == "*" {
+            return true;
+        }
+        let split = constraint.find(|c: char| !"<>=!".contains(c)).unwrap_or(constraint.len());
+        let (op, wanted) = constraint.split_at(split);
+        let order = compare_versions(version, wanted.trim());
+        match op {
+            "<" => order == Ordering::Less,
+            "<=" => order != Ordering::Greater,
+            ">" => order == Ordering::Greater,
+            ">=" => order != Ordering::Less,
+            "!=" => order != Ordering::Equal,
+            _ => order == Ordering::Equal,
         }
+    })
+}
+
+pub fn resolve_package_path(input: &str) -> Option<String> {
+    if !input.contains('/') && !input.ends_with(".pls") {
+        return None;
     }
-    None
+    if Path::new(input).exists() {
+        return Some(input.to_string());
+    }
+
+    let path = Path::new(input);
+    let dir = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let wanted = path.file_name()?.to_string_lossy().trim_end_matches(".pls").to_string();
+    let host = host_arch();
+
+    fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let (version, arch) = package_file_version(&entry.file_name().to_string_lossy(), &wanted)?;
+            (arch == host || arch == "any").then(|| (version, arch, entry.path()))
+        })
+        .max_by(|a, b| compare_versions(&a.0, &b.0).then((a.1 == host).cmp(&(b.1 == host))))
+        .map(|(_, _, path)| path.to_string_lossy().to_string())
 }
 
 pub fn is_installed(name: &str) -> bool {
-    Path::new(&format!("{}/{}", DB_DIR, name)).exists()
+    Path::new(&format!("{}/{}", db_dir(), name)).exists()
+}
+
+pub fn install_reason(name: &str) -> InstallReason {
+    fs::read_to_string(format!("{}/{}/reason", db_dir(), name))
+        .map(|r| InstallReason::parse(&r))
+        .unwrap_or(InstallReason::Explicit)
+}
+
+const INDEX_MAGIC: &[u8] = b"PLSIDX";
+const INDEX_FORMAT: u8 = 1;
+
+pub fn encode_binary_index(index: &RepoIndex) -> Result<Vec<u8>, String> {
+    let body = rmp_serde::to_vec_named(index).map_err(|e| format!("couldn't encode index: {}", e))?;
+    let mut out = INDEX_MAGIC.to_vec();
+    out.push(INDEX_FORMAT);
+    out.extend(body);
+    Ok(out)
+}
+
+pub fn decode_binary_index(bytes: &[u8]) -> Option<RepoIndex> {
+    let body = bytes.strip_prefix(INDEX_MAGIC)?.strip_prefix(&[INDEX_FORMAT])?;
+    rmp_serde::from_slice(body).ok()
+}
+
+pub fn write_index(index_path: &str, index: &RepoIndex) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(index).map_err(|e| format!("couldn't serialize index: {}", e))?;
+    fs::write(index_path, &json).map_err(|e| format!("couldn't write {}: {}", index_path, e))?;
+
+    let compressed = zstd::encode_all(json.as_bytes(), 19).map_err(|e| format!("couldn't compress index: {}", e))?;
+    let zst_path = format!("{}.zst", index_path);
+    fs::write(&zst_path, compressed).map_err(|e| format!("couldn't write {}: {}", zst_path, e))?;
+
+    let bin_path = format!("{}.bin", index_path.trim_end_matches(".json"));
+    fs::write(&bin_path, encode_binary_index(index)?).map_err(|e| format!("couldn't write {}: {}", bin_path, e))
+}
+
+pub fn write_files_db<K: Serialize + Ord>(path: &str, files_db: &BTreeMap<K, Vec<String>>) -> Result<(), String> {
+    let json = serde_json::to_vec(files_db).map_err(|e| format!("couldn't serialize file lists: {}", e))?;
+    let compressed = zstd::encode_all(json.as_slice(), 19).map_err(|e| format!("couldn't compress file lists: {}", e))?;
+    fs::write(path, compressed).map_err(|e| format!("couldn't write {}: {}", path, e))
+}
+
+pub fn valid_package_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '.' | '_' | '+' | '-'))
+}
+
+pub fn valid_version(version: &str) -> bool {
+    version.starts_with(|c: char| c.is_ascii_digit())
+        && version
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '+' | '~' | '-'))
+}
+
+pub fn installed_origin(name: &str) -> Option<String> {
+    fs::read_to_string(format!("{}/{}/repo", db_dir(), name))
+        .ok()
+        .map(|r| r.trim().to_string())
+        .filter(|r| !r.is_empty())
+}
+
+pub fn walk_files(root: &str) -> Vec<String> {
+    fn walk(dir: &Path, prefix: &str, out: &mut Vec<String>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let rel = if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) };
+            let path = entry.path();
+            if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+                walk(&path, &rel, out);
+            } else {
+                out.push(rel);
+            }
+        }
+    }
+
+    let mut files = Vec::new();
+    walk(Path::new(root), "", &mut files);
+    files.sort();
+    files
+}
+
+pub fn copy_attributes(src: &Path, dest: &Path) -> io::Result<()> {
+    let meta = fs::metadata(src)?;
+    if running_as_root() {
+        lchown(dest, Some(meta.uid()), Some(meta.gid()))?;
+    }
+    fs::set_permissions(dest, meta.permissions())?;
+    for (name, value) in read_xattrs(src) {
+        match xattr::set(dest, &name, &value) {
+            Err(e) if e.kind() == io::ErrorKind::Unsupported => {}
+            result => result?,
+        }
+    }
+    Ok(())
+}
+
+pub fn copy_tree(src: &str, dest: &str) -> io::Result<()> {
+    for rel in walk_files(src) {
+        let target = Path::new(dest).join(&rel);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if let Ok(link) = fs::read_link(Path::new(src).join(&rel)) {
+            std::os::unix::fs::symlink(link, &target)?;
+            continue;
+        }
+        fs::copy(Path::new(src).join(&rel), &target)?;
+        copy_attributes(&Path::new(src).join(&rel), &target)?;
+    }
+    Ok(())
+}
+
+pub fn installed_files(name: &str) -> Vec<String> {
+    fs::read_to_string(format!("{}/{}/files", db_dir(), name))
+        .map(|content| content.lines().map(|l| l.to_string()).collect())
+        .unwrap_or_default()
+}
+
+pub fn installed_packages() -> Vec<PackageInfo> {
+    let Ok(entries) = fs::read_dir(db_dir()) else {
+        return Vec::new();
+    };
+
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let info_path = format!("{}/{}/info", db_dir(), entry.file_name().to_string_lossy());
+            PackageInfo::from_file(&info_path).ok()
+        })
+        .collect()
+}
+
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let [first, middle @ .., last] = parts.as_slice() else {
+        return pattern == text;
+    };
+    if text.len() < first.len() + last.len() || !text.starts_with(first) || !text.ends_with(last) {
+        return false;
+    }
+
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in middle {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+pub fn parse_list(value: &str) -> Vec<String> {
+    let value = value.trim();
+    if !value.starts_with('[') {
+        return if value.is_empty() { Vec::new() } else { vec![value.to_string()] };
+    }
+
+    value
+        .trim_matches(|c| c == '[' || c == ']')
+        .split(',')
+        .map(|item| item.trim().trim_matches('"').trim_matches('\'').to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
 }
 
+pub fn bump_version(version: &str, part: &str) -> Result<String, String> {
+    let core = version.split(['-', '+']).next().unwrap_or("");
+    let mut numbers = core.split('.').map(|n| n.parse::<u64>());
+    let mut next = || numbers.next().unwrap_or(Ok(0));
+    let (Ok(major), Ok(minor), Ok(patch)) = (next(), next(), next()) else {
+        return Err(format!("can't bump '{}', it's not a x.y.z version", version));
+    };
+
+    match part {
+        "major" => Ok(format!("{}.0.0", major + 1)),
+        "minor" => Ok(format!("{}.{}.0", major, minor + 1)),
+        "patch" => Ok(format!("{}.{}.{}", major, minor, patch + 1)),
+        _ => Err(format!("--bump takes patch, minor or major, not '{}'", part)),
+    }
+}
+
+pub fn parse_size(input: &str) -> Option<u64> {
+    let input = input.trim();
+    let split = input.find(|c: char| !c.is_ascii_digit()).unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let number: u64 = number.parse().ok()?;
+    let multiplier = match unit.trim().to_uppercase().trim_end_matches("IB").trim_end_matches('B') {
+        "" => 1,
+        "K" => 1024,
+        "M" => 1024 * 1024,
+        "G" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    number.checked_mul(multiplier)
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "K", "M", "G"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{}B", bytes)
+    } else {
+        format!("{:.1}{}", size, UNITS[unit])
+    }
+}
+
+const HASH_CHUNK: usize = 1024 * 1024;
+const PIPELINE_MIN_SIZE: u64 = 8 * HASH_CHUNK as u64;
+
 pub fn calculate_sha256(path: &str) -> io::Result<String> {
     let mut file = File::open(path)?;
     let mut hasher = Sha256::new();
-    let mut buffer = [0u8; 8192];
 
-    loop {
-        let bytes_read = file.read(&mut buffer)?;
-        if bytes_read == 0 {
-            break;
+    if file.metadata()?.len() < PIPELINE_MIN_SIZE {
+        let mut buffer = vec![0u8; HASH_CHUNK];
+        loop {
+            let bytes_read = file.read(&mut buffer)?;
+            if bytes_read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..bytes_read]);
         }
-        hasher.update(&buffer[..bytes_read]);
+        return Ok(hex::encode(hasher.finalize()));
     }
 
+    let (tx, rx) = mpsc::sync_channel::<io::Result<Vec<u8>>>(4);
+    let reader = thread::spawn(move || {
+        loop {
+            let mut chunk = vec![0u8; HASH_CHUNK];
+            let sent = match file.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(n) => {
+                    chunk.truncate(n);
+                    tx.send(Ok(chunk))
+                }
+                Err(e) => tx.send(Err(e)),
+            };
+            if sent.is_err() {
+                break;
+            }
+        }
+    });
+    for chunk in rx {
+        hasher.update(&chunk?);
+    }
+    let _ = reader.join();
+
     Ok(hex::encode(hasher.finalize()))
 }
+
+pub fn calculate_b3sum(path: &str) -> io::Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_mmap_rayon(path)?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+pub fn checksum_matches(path: &str, sha256: &str, b3sum: &str) -> bool {
+    if !b3sum.is_empty() {
+        return calculate_b3sum(path).is_ok_and(|sum| sum == b3sum);
+    }
+    calculate_sha256(path).is_ok_and(|sum| sum == sha256)
+}
+
+pub fn hash_files(paths: &[String]) -> Vec<io::Result<String>> {
+    let workers = thread::available_parallelism().map(|n| n.get()).unwrap_or(1).min(paths.len().max(1));
+    let next = AtomicUsize::new(0);
+    let results: Vec<Mutex<Option<io::Result<String>>>> = paths.iter().map(|_| Mutex::new(None)).collect();
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                loop {
+                    let i = next.fetch_add(1, AtomicOrdering::Relaxed);
+                    let Some(path) = paths.get(i) else {
+                        break;
+                    };
+                    let hash = calculate_sha256(path);
+                    *results[i].lock().unwrap() = Some(hash);
+                }
+            });
+        }
+    });
+
+    results
+        .into_iter()
+        .map(|slot| slot.into_inner().unwrap().unwrap_or_else(|| Err(io::Error::other("not hashed"))))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prerelease_sorts_below_release() {
+        assert_eq!(compare_versions("1.0-rc1", "1.0"), Ordering::Less);
+        assert_eq!(compare_versions("1.0~beta", "1.0"), Ordering::Less);
+        assert_eq!(compare_versions("1.0-alpha", "1.0-beta"), Ordering::Less);
+        assert_eq!(compare_versions("1.0-rc1", "1.0-rc2"), Ordering::Less);
+        assert_eq!(compare_versions("1.0-rc2", "1.0-rc10"), Ordering::Less);
+        assert_eq!(compare_versions("1.0-rc1", "0.9"), Ordering::Greater);
+        assert_eq!(compare_versions("1.1-rc1", "1.0"), Ordering::Greater);
+    }
+
+    #[test]
+    fn trailing_zeros_compare_equal() {
+        assert_eq!(compare_versions("1.0", "1.0.0"), Ordering::Equal);
+        assert_eq!(compare_versions("2", "2.0.0"), Ordering::Equal);
+        assert_eq!(compare_versions("1.0", "1.0.1"), Ordering::Less);
+        assert!(version_in_range("1.0.0", "=1.0"));
+        assert!(version_in_range("1.0", ">=1.0.0, <2"));
+        assert!(!version_in_range("2.0-rc1", ">=2.0"));
+    }
+
+    #[test]
+    fn numeric_parts_compare_as_numbers() {
+        assert_eq!(compare_versions("1.9", "1.10"), Ordering::Less);
+        assert_eq!(compare_versions("1.0-2", "1.0-1"), Ordering::Greater);
+        assert_eq!(compare_versions("1.0-1", "1.0"), Ordering::Greater);
+        assert!(is_major_jump("1.9", "2.0"));
+        assert!(!is_major_jump("1.0", "1.0.1"));
+        assert!(is_major_jump("0.1", "0.2"));
+    }
+
+    #[test]
+    fn package_file_names_keep_dashed_names_and_versions() {
+        let parse = |file| parse_package_file_name(file).map(|(name, version, arch)| format!("{} {} {}", name, version, arch));
+        assert_eq!(parse("hello-1.1-x86_64.pls").as_deref(), Some("hello 1.1 x86_64"));
+        assert_eq!(parse("lib-3d-1.0-x86_64.pls").as_deref(), Some("lib-3d 1.0 x86_64"));
+        assert_eq!(parse("foo-2fa-2.3.1-any.pls").as_deref(), Some("foo-2fa 2.3.1 any"));
+        assert_eq!(parse("foo-1.0-rc1-aarch64.pls").as_deref(), Some("foo 1.0-rc1 aarch64"));
+        assert_eq!(parse("foo-x86_64.pls"), None);
+        assert_eq!(package_file_version("lib-3d-1.0-rc1-x86_64.pls", "lib-3d"), Some(("1.0-rc1".to_string(), "x86_64".to_string())));
+        assert_eq!(package_file_version("lib-3d-1.0-x86_64.pls", "lib"), None);
+    }
+}
@@ -0,0 +1,156 @@
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::exit::{CONFLICT, Failure, NOT_FOUND, fail};
+use crate::paths::versions_dir;
+use crate::types::PackageInfo;
+use crate::utils::{compare_versions, copy_tree, valid_package_name, valid_version, walk_files};
+use crate::ROOT;
+
+fn package_dir(name: &str) -> PathBuf {
+    Path::new(versions_dir()).join(name)
+}
+
+fn bin_dir() -> PathBuf {
+    Path::new(ROOT).join(Config::load().prefix.trim_start_matches('/')).join("bin")
+}
+
+fn bins(name: &str, version: &str) -> Vec<String> {
+    walk_files(&package_dir(name).join(version).join("bin").to_string_lossy())
+}
+
+fn link_target(name: &str, bin: &str) -> PathBuf {
+    package_dir(name).join("current").join("bin").join(bin)
+}
+
+fn is_ours(link: &Path, name: &str, bin: &str) -> bool {
+    fs::read_link(link).is_ok_and(|target| target == link_target(name, bin))
+}
+
+pub fn parse(reference: &str) -> Option<(&str, &str)> {
+    reference.split_once('@').filter(|(name, version)| !name.is_empty() && !version.is_empty())
+}
+
+pub fn installed(name: &str) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(package_dir(name)) else {
+        return Vec::new();
+    };
+    let mut versions: Vec<String> = entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    versions.sort_by(|a, b| compare_versions(a, b));
+    versions
+}
+
+pub fn active(name: &str) -> Option<String> {
+    fs::read_link(package_dir(name).join("current")).ok().map(|target| target.to_string_lossy().to_string())
+}
+
+pub fn managed() -> Vec<String> {
+    let Ok(entries) = fs::read_dir(versions_dir()) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| !installed(name).is_empty())
+        .collect();
+    names.sort();
+    names
+}
+
+pub fn install(staged_dir: &str, pkg: &PackageInfo) -> Result<bool, Failure> {
+    if !valid_package_name(&pkg.name) || !valid_version(&pkg.version) {
+        return Err(format!("bad name or version '{}' v'{}', not putting it side by side", pkg.name, pkg.version).into());
+    }
+    let dest = package_dir(&pkg.name).join(&pkg.version);
+    let _ = fs::remove_dir_all(&dest);
+    copy_tree(staged_dir, &dest.to_string_lossy()).map_err(|e| format!("couldn't put {} in {}: {}", pkg.name, dest.display(), e))?;
+    if active(&pkg.name).is_some() {
+        return Ok(false);
+    }
+    activate(&pkg.name, &pkg.version).inspect_err(|_| {
+        let _ = fs::remove_dir_all(&dest);
+    })?;
+    Ok(true)
+}
+
+pub fn activate(name: &str, version: &str) -> Result<(), Failure> {
+    let versions = installed(name);
+    if !versions.iter().any(|v| v == version) {
+        return Err(match versions.is_empty() {
+            true => fail(NOT_FOUND, format!("{} isn't installed side by side", name)),
+            false => fail(NOT_FOUND, format!("no {} v{} side by side, there's {}", name, version, versions.join(", "))),
+        });
+    }
+
+    let bin_dir = bin_dir();
+    let wanted = bins(name, version);
+    for bin in &wanted {
+        let link = bin_dir.join(bin);
+        if fs::symlink_metadata(&link).is_ok() && !is_ours(&link, name, bin) {
+            return Err(fail(CONFLICT, format!("{} is already there and pls use doesn't own it", link.display())));
+        }
+    }
+
+    let previous = active(name);
+    let current = package_dir(name).join("current");
+    let temp = package_dir(name).join(".current");
+    let _ = fs::remove_file(&temp);
+    symlink(version, &temp)
+        .and_then(|_| fs::rename(&temp, &current))
+        .map_err(|e| format!("couldn't switch {} to v{}: {}", name, version, e))?;
+
+    for bin in previous.map(|old| bins(name, &old)).unwrap_or_default() {
+        let link = bin_dir.join(&bin);
+        if !wanted.contains(&bin) && is_ours(&link, name, &bin) {
+            let _ = fs::remove_file(&link);
+        }
+    }
+    for bin in &wanted {
+        let link = bin_dir.join(bin);
+        if fs::symlink_metadata(&link).is_err() {
+            if let Some(parent) = link.parent() {
+                fs::create_dir_all(parent).map_err(|e| format!("couldn't create {}: {}", parent.display(), e))?;
+            }
+            symlink(link_target(name, bin), &link).map_err(|e| format!("couldn't link {}: {}", link.display(), e))?;
+        }
+    }
+    Ok(())
+}
+
+pub fn remove(name: &str, version: Option<&str>) -> Result<Vec<String>, Failure> {
+    let versions = installed(name);
+    let doomed: Vec<String> = match version {
+        Some(version) if versions.iter().any(|v| v == version) => vec![version.to_string()],
+        Some(version) => return Err(fail(NOT_FOUND, format!("no {} v{} side by side", name, version))),
+        None => versions.clone(),
+    };
+
+    let current = active(name);
+    if current.as_ref().is_some_and(|current| doomed.contains(current)) {
+        match versions.iter().rev().find(|v| !doomed.contains(v)) {
+            Some(next) => activate(name, next)?,
+            None => {
+                let bin_dir = bin_dir();
+                for bin in current.map(|old| bins(name, &old)).unwrap_or_default() {
+                    let link = bin_dir.join(&bin);
+                    if is_ours(&link, name, &bin) {
+                        let _ = fs::remove_file(&link);
+                    }
+                }
+                let _ = fs::remove_file(package_dir(name).join("current"));
+            }
+        }
+    }
+
+    for version in &doomed {
+        fs::remove_dir_all(package_dir(name).join(version)).map_err(|e| format!("couldn't remove {} v{}: {}", name, version, e))?;
+    }
+    let _ = fs::remove_dir(package_dir(name));
+    Ok(doomed)
+}